@@ -0,0 +1,333 @@
+use crate::ecc::elliptic_curve_finite_field::{Coordinate, CurveOverFiniteField, Point};
+use crate::ecc::finite_field::FieldElement;
+use anyhow::{anyhow, Result};
+use hex_literal::hex;
+use lazy_static::lazy_static;
+use num_bigint::{BigInt, Sign};
+
+/// Domain parameters of a named curve y^2 = x^3 + ax + b over F_p
+/// with generator G of prime order n and cofactor h.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurveParams {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// dotted ASN.1 object identifier, toy curves have none
+    pub oid: Option<&'static str>,
+    pub p: BigInt,
+    pub a: BigInt,
+    pub b: BigInt,
+    pub g: (BigInt, BigInt),
+    pub n: BigInt,
+    pub h: BigInt,
+}
+
+impl CurveParams {
+    pub fn field_element(&self, num: impl Into<BigInt>) -> FieldElement {
+        FieldElement::new(num, self.p.clone())
+    }
+
+    pub fn curve(&self) -> CurveOverFiniteField {
+        CurveOverFiniteField::new(
+            self.field_element(self.a.clone()),
+            self.field_element(self.b.clone()),
+        )
+    }
+
+    pub fn generator(&self) -> Point {
+        Point::new(
+            Some(Coordinate::new(
+                self.field_element(self.g.0.clone()),
+                self.field_element(self.g.1.clone()),
+            )),
+            self.curve(),
+        )
+        .expect("registered generators lie on their curves")
+    }
+
+    /// Builds a point from untrusted coordinates, see [`CurveParams::validate_point`].
+    pub fn point(&self, x: impl Into<BigInt>, y: impl Into<BigInt>) -> Result<Point> {
+        let point = Point::new(
+            Some(Coordinate::new(
+                self.field_element(x),
+                self.field_element(y),
+            )),
+            self.curve(),
+        )?;
+        self.validate_point(&point)?;
+        Ok(point)
+    }
+
+    /// Checks a point received from someone else: it must lie on this curve, not be the
+    /// point at infinity and, if the curve has a cofactor, belong to the subgroup of order n.
+    /// Skipping these checks lets an attacker learn the secret it gets multiplied with
+    /// modulo small orders (invalid-curve and small-subgroup attacks).
+    pub fn validate_point(&self, point: &Point) -> Result<()> {
+        if point.curve() != &self.curve() {
+            return Err(anyhow!("Point is not on {}", self.name));
+        }
+        if point.is_infinity() {
+            return Err(anyhow!("Point at infinity"));
+        }
+        if self.h != BigInt::from(1) && !point.is_in_subgroup(&self.n) {
+            return Err(anyhow!("Point is not in the subgroup of order {}", self.n));
+        }
+        Ok(())
+    }
+
+    /// returns the length in bytes of a serialized field element
+    pub fn byte_len(&self) -> usize {
+        ((self.p.bits() + 7) / 8) as usize
+    }
+}
+
+fn from_hex(bytes: &[u8]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, bytes)
+}
+
+lazy_static! {
+    pub static ref SECP256K1: CurveParams = CurveParams {
+        name: "secp256k1",
+        aliases: &[],
+        oid: Some("1.3.132.0.10"),
+        p: BigInt::from(2).pow(256) - BigInt::from(2).pow(32) - 977,
+        a: BigInt::from(0),
+        b: BigInt::from(7),
+        g: (
+            from_hex(&hex!("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")),
+            from_hex(&hex!("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")),
+        ),
+        n: from_hex(&hex!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")),
+        h: BigInt::from(1),
+    };
+    pub static ref SECP256R1: CurveParams = CurveParams {
+        name: "secp256r1",
+        aliases: &["P-256", "prime256v1"],
+        oid: Some("1.2.840.10045.3.1.7"),
+        p: from_hex(&hex!("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff")),
+        a: from_hex(&hex!("ffffffff00000001000000000000000000000000fffffffffffffffffffffffc")),
+        b: from_hex(&hex!("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b")),
+        g: (
+            from_hex(&hex!("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296")),
+            from_hex(&hex!("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5")),
+        ),
+        n: from_hex(&hex!("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551")),
+        h: BigInt::from(1),
+    };
+    pub static ref SECP384R1: CurveParams = CurveParams {
+        name: "secp384r1",
+        aliases: &["P-384"],
+        oid: Some("1.3.132.0.34"),
+        p: from_hex(&hex!(
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe"
+            "ffffffff0000000000000000ffffffff"
+        )),
+        a: from_hex(&hex!(
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe"
+            "ffffffff0000000000000000fffffffc"
+        )),
+        b: from_hex(&hex!(
+            "b3312fa7e23ee7e4988e056be3f82d19181d9c6efe8141120314088f5013875a"
+            "c656398d8a2ed19d2a85c8edd3ec2aef"
+        )),
+        g: (
+            from_hex(&hex!(
+                "aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a38"
+                "5502f25dbf55296c3a545e3872760ab7"
+            )),
+            from_hex(&hex!(
+                "3617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147ce9da3113b5f0b8c0"
+                "0a60b1ce1d7e819d7a431d7c90ea0e5f"
+            )),
+        ),
+        n: from_hex(&hex!(
+            "ffffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf"
+            "581a0db248b0a77aecec196accc52973"
+        )),
+        h: BigInt::from(1),
+    };
+    /// The book's y^2 = x^3 + 7 over F_223, (15, 86) generates a subgroup of order 7
+    pub static ref TOY_F223: CurveParams = CurveParams {
+        name: "toy-f223",
+        aliases: &[],
+        oid: None,
+        p: BigInt::from(223),
+        a: BigInt::from(0),
+        b: BigInt::from(7),
+        g: (BigInt::from(15), BigInt::from(86)),
+        n: BigInt::from(7),
+        h: BigInt::from(36),
+    };
+    static ref REGISTRY: [&'static CurveParams; 4] = [&SECP256K1, &SECP256R1, &SECP384R1, &TOY_F223];
+}
+
+/// returns every registered curve
+pub fn registry() -> &'static [&'static CurveParams] {
+    REGISTRY.as_slice()
+}
+
+/// looks a curve up by its name or one of its aliases, ignoring case
+pub fn by_name(name: &str) -> Option<&'static CurveParams> {
+    registry().iter().copied().find(|params| {
+        params.name.eq_ignore_ascii_case(name)
+            || params
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+    })
+}
+
+pub fn by_oid(oid: &str) -> Option<&'static CurveParams> {
+    registry()
+        .iter()
+        .copied()
+        .find(|params| params.oid == Some(oid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::discrete_log::{baby_step_giant_step, chinese_remainder};
+    use num_integer::Integer;
+
+    #[test]
+    fn test_generators() {
+        for params in registry() {
+            assert!(
+                CurveOverFiniteField::<FieldElement>::try_new(
+                    params.field_element(params.a.clone()),
+                    params.field_element(params.b.clone())
+                )
+                .is_ok(),
+                "{}",
+                params.name
+            );
+            let g = params.generator();
+            assert_eq!(
+                (&g * &params.n).unwrap().coordinate(),
+                None,
+                "{}",
+                params.name
+            );
+            assert!(
+                (&g * &(&params.n - 1)).unwrap().coordinate().is_some(),
+                "{}",
+                params.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(by_name("secp256k1"), Some(&*SECP256K1));
+        assert_eq!(by_name("p-256"), Some(&*SECP256R1));
+        assert_eq!(by_name("prime256v1"), Some(&*SECP256R1));
+        assert_eq!(by_name("P-384"), Some(&*SECP384R1));
+        assert_eq!(by_name("curve25519"), None);
+        assert_eq!(by_oid("1.3.132.0.10"), Some(&*SECP256K1));
+        assert_eq!(by_oid("1.2.840.10045.3.1.7"), Some(&*SECP256R1));
+        assert_eq!(by_oid("1.3.132.0.34"), Some(&*SECP384R1));
+    }
+
+    #[test]
+    fn test_byte_len() {
+        assert_eq!(SECP256K1.byte_len(), 32);
+        assert_eq!(SECP384R1.byte_len(), 48);
+        assert_eq!(TOY_F223.byte_len(), 1);
+    }
+
+    #[test]
+    fn test_validate_point() {
+        assert!(TOY_F223.validate_point(&TOY_F223.generator()).is_ok());
+        assert!(TOY_F223.point(15, 86).is_ok());
+        assert!(TOY_F223.point(15, 87).is_err());
+        // order 21, outside the subgroup of order 7
+        assert!(TOY_F223.point(47, 71).is_err());
+        assert!(TOY_F223
+            .validate_point(&TOY_F223.curve().infinity())
+            .is_err());
+        assert!(SECP256K1.validate_point(&TOY_F223.generator()).is_err());
+        assert!(SECP256K1.validate_point(&SECP256K1.generator()).is_ok());
+    }
+
+    #[test]
+    fn test_small_subgroup_attack() {
+        let secret = BigInt::from(5);
+        for subgroup in TOY_F223.curve().prime_order_subgroups().unwrap() {
+            if subgroup.order == TOY_F223.n {
+                continue;
+            }
+            // the shared point only takes `order` values, leaking the secret modulo it
+            assert!(TOY_F223.validate_point(&subgroup.generator).is_err());
+            let shared = naive_ecdh(&secret, &subgroup.generator);
+            let leaked =
+                baby_step_giant_step(&subgroup.generator, &shared, &subgroup.order).unwrap();
+            assert_eq!(leaked.exponent, secret.mod_floor(&subgroup.order));
+            assert!(ecdh(&TOY_F223, &secret, &subgroup.generator).is_err());
+        }
+    }
+
+    /// y^2 = x^3 + x + 28 over F_10007, of prime order 9851
+    fn victim_params() -> CurveParams {
+        CurveParams {
+            name: "toy-f10007",
+            aliases: &[],
+            oid: None,
+            p: BigInt::from(10007),
+            a: BigInt::from(1),
+            b: BigInt::from(28),
+            g: (BigInt::from(2), BigInt::from(4582)),
+            n: BigInt::from(9851),
+            h: BigInt::from(1),
+        }
+    }
+
+    /// multiplies whatever point the peer sends
+    fn naive_ecdh(secret: &BigInt, peer: &Point) -> Point {
+        (peer * secret).unwrap()
+    }
+
+    fn ecdh(params: &CurveParams, secret: &BigInt, peer: &Point) -> Result<Point> {
+        params.validate_point(peer)?;
+        peer * secret
+    }
+
+    #[test]
+    fn test_invalid_curve_attack() {
+        let params = victim_params();
+        assert_eq!(params.curve().order().unwrap(), params.n);
+        let secret = BigInt::from(7777);
+        let public = (&params.generator() * &secret).unwrap();
+        assert_eq!(ecdh(&params, &secret, &params.generator()).unwrap(), public);
+
+        // b never enters the addition formulas, so a victim that does not check its input
+        // computes on whatever curve y^2 = x^3 + x + b' the attacker picked, and those
+        // have subgroups of small order
+        let mut residues: Vec<(BigInt, BigInt)> = vec![];
+        let mut modulus = BigInt::from(1);
+        for b in 0.. {
+            if modulus > params.n {
+                break;
+            }
+            let Ok(curve) = CurveOverFiniteField::<FieldElement>::try_new(
+                params.field_element(1),
+                params.field_element(b),
+            ) else {
+                continue;
+            };
+            for subgroup in curve.prime_order_subgroups().unwrap() {
+                if subgroup.order > BigInt::from(100)
+                    || residues.iter().any(|(_, q)| q == &subgroup.order)
+                {
+                    continue;
+                }
+                assert!(ecdh(&params, &secret, &subgroup.generator).is_err());
+                let shared = naive_ecdh(&secret, &subgroup.generator);
+                let leaked =
+                    baby_step_giant_step(&subgroup.generator, &shared, &subgroup.order).unwrap();
+                modulus *= &subgroup.order;
+                residues.push((leaked.exponent, subgroup.order));
+            }
+        }
+        assert_eq!(chinese_remainder(&residues).unwrap(), secret);
+    }
+}
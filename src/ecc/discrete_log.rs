@@ -0,0 +1,380 @@
+//! Generic discrete logarithm solvers. They take time around the square root of the
+//! largest prime factor of the group order, which is why toy groups (and smooth orders)
+//! offer no security at all.
+
+use crate::ecc::elliptic_curve_finite_field::group::factorize;
+use crate::ecc::elliptic_curve_finite_field::{Coordinate, Point};
+use crate::ecc::finite_field::FieldElement;
+use anyhow::{anyhow, Result};
+use num_bigint::{BigInt, RandBigInt};
+use num_integer::Integer;
+use num_traits::ToPrimitive;
+use rand::thread_rng;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// How many random starting points Pollard's rho tries before giving up
+const RHO_RESTARTS: usize = 16;
+
+/// Cyclic group the solvers work in, written multiplicatively
+pub trait DiscreteLogGroup: Clone + PartialEq {
+    /// value identifying an element, used for hash tables and Pollard's rho partition
+    type Key: Hash + Eq;
+
+    fn identity(&self) -> Self;
+
+    fn operate(&self, rhs: &Self) -> Result<Self>;
+
+    fn invert(&self) -> Result<Self>;
+
+    fn power(&self, exponent: &BigInt) -> Result<Self>;
+
+    fn key(&self) -> Self::Key;
+}
+
+/// The multiplicative group F_p^*
+impl DiscreteLogGroup for FieldElement {
+    type Key = BigInt;
+
+    fn identity(&self) -> Self {
+        FieldElement::one(self.prime.clone())
+    }
+
+    fn operate(&self, rhs: &Self) -> Result<Self> {
+        self * rhs
+    }
+
+    fn invert(&self) -> Result<Self> {
+        self.inverse()
+    }
+
+    fn power(&self, exponent: &BigInt) -> Result<Self> {
+        Ok(self.pow(exponent.clone()))
+    }
+
+    fn key(&self) -> Self::Key {
+        self.num.clone()
+    }
+}
+
+/// The points of a curve under addition
+impl DiscreteLogGroup for Point {
+    type Key = Option<Coordinate>;
+
+    fn identity(&self) -> Self {
+        self.curve().infinity()
+    }
+
+    fn operate(&self, rhs: &Self) -> Result<Self> {
+        self + rhs
+    }
+
+    fn invert(&self) -> Result<Self> {
+        Ok(-self)
+    }
+
+    fn power(&self, exponent: &BigInt) -> Result<Self> {
+        self * exponent
+    }
+
+    fn key(&self) -> Self::Key {
+        self.coordinate().cloned()
+    }
+}
+
+/// Exponent x with base^x = target, and the number of group operations it took to find
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub exponent: BigInt,
+    pub steps: u64,
+}
+
+/// Shanks' baby-step giant-step, about 2√n group operations and √n memory,
+/// `order` is the order of `base`.
+pub fn baby_step_giant_step<G: DiscreteLogGroup>(
+    base: &G,
+    target: &G,
+    order: &BigInt,
+) -> Result<Solution> {
+    let m: BigInt = order.sqrt() + 1;
+    let steps = m
+        .to_usize()
+        .ok_or_else(|| anyhow!("Group of order {order} is too large"))?;
+    let mut count = 0;
+
+    // baby steps base^j for j in [0, m)
+    let mut baby = HashMap::with_capacity(steps);
+    let mut current = base.identity();
+    for j in 0..steps {
+        baby.entry(current.key()).or_insert(j);
+        current = current.operate(base)?;
+        count += 1;
+    }
+    // giant steps target * base^(-im)
+    let giant = current.invert()?;
+    let mut current = target.clone();
+    for i in 0..steps {
+        if let Some(j) = baby.get(&current.key()) {
+            return Ok(Solution {
+                exponent: (&m * i + j).mod_floor(order),
+                steps: count,
+            });
+        }
+        current = current.operate(&giant)?;
+        count += 1;
+    }
+    Err(anyhow!(
+        "Target is not in the subgroup generated by the base"
+    ))
+}
+
+/// Pollard's rho with Floyd's cycle detection, about √n group operations and constant
+/// memory. `order` must be the (prime) order of `base`, composite orders are handled by
+/// trying every solution of the final congruence.
+pub fn pollard_rho<G: DiscreteLogGroup>(base: &G, target: &G, order: &BigInt) -> Result<Solution> {
+    let zero = BigInt::from(0);
+    if order <= &BigInt::from(1) {
+        return baby_step_giant_step(base, target, order);
+    }
+    let mut count = 0;
+    // x = base^a * target^b, the partition decides between multiplying by base,
+    // squaring and multiplying by target
+    let step = |(x, a, b): &(G, BigInt, BigInt)| -> Result<(G, BigInt, BigInt)> {
+        let mut hasher = DefaultHasher::new();
+        x.key().hash(&mut hasher);
+        Ok(match hasher.finish() % 3 {
+            0 => (x.operate(base)?, (a + 1u32).mod_floor(order), b.clone()),
+            1 => (
+                x.operate(x)?,
+                (a * 2u32).mod_floor(order),
+                (b * 2u32).mod_floor(order),
+            ),
+            _ => (x.operate(target)?, a.clone(), (b + 1u32).mod_floor(order)),
+        })
+    };
+
+    for _ in 0..RHO_RESTARTS {
+        let a = thread_rng().gen_bigint_range(&zero, order);
+        let b = thread_rng().gen_bigint_range(&zero, order);
+        let start = (base.power(&a)?.operate(&target.power(&b)?)?, a, b);
+        let mut tortoise = step(&start)?;
+        let mut hare = step(&step(&start)?)?;
+        count += 3;
+        while tortoise.0 != hare.0 {
+            tortoise = step(&tortoise)?;
+            hare = step(&step(&hare)?)?;
+            count += 3;
+        }
+        // a1 + x b1 = a2 + x b2, so x (b1 - b2) = a2 - a1
+        let lhs = (&tortoise.2 - &hare.2).mod_floor(order);
+        let rhs = (&hare.1 - &tortoise.1).mod_floor(order);
+        let gcd = lhs.gcd(order);
+        if lhs == zero || !rhs.is_multiple_of(&gcd) {
+            continue;
+        }
+        let reduced = order / &gcd;
+        let inverse = (&lhs / &gcd).modpow(&(totient(&reduced)? - 1), &reduced);
+        let particular = (&rhs / &gcd * inverse).mod_floor(&reduced);
+        for k in num_iter(&gcd) {
+            let exponent = &particular + &reduced * k;
+            if &base.power(&exponent)? == target {
+                return Ok(Solution {
+                    exponent,
+                    steps: count,
+                });
+            }
+        }
+    }
+    Err(anyhow!(
+        "Pollard's rho found no solution, the target may not be in the subgroup"
+    ))
+}
+
+/// Pohlig–Hellman: solves the problem in each prime-power subgroup with baby-step
+/// giant-step, one base-q digit at a time, and recombines with the Chinese remainder
+/// theorem. The cost is driven by the largest prime factor of `order`, the order of `base`.
+pub fn pohlig_hellman<G: DiscreteLogGroup>(
+    base: &G,
+    target: &G,
+    order: &BigInt,
+) -> Result<Solution> {
+    let mut count = 0;
+    let mut residues = vec![];
+    for (prime, exponent) in factorize(order)? {
+        let cofactor = order / prime.pow(exponent);
+        // base and target projected into the subgroup of order q^e
+        let sub_base = base.power(&cofactor)?;
+        let sub_target = target.power(&cofactor)?;
+        // generator of the subgroup of order q
+        let digit_base = sub_base.power(&prime.pow(exponent - 1))?;
+        let mut x = BigInt::from(0);
+        for k in 0..exponent {
+            // (target * base^-x)^(q^(e-1-k)) = digit_base^(d_k)
+            let shifted = sub_target.operate(&sub_base.power(&x)?.invert()?)?;
+            let projected = shifted.power(&prime.pow(exponent - 1 - k))?;
+            let digit = baby_step_giant_step(&digit_base, &projected, &prime)?;
+            count += digit.steps;
+            x += digit.exponent * prime.pow(k);
+        }
+        residues.push((x, prime.pow(exponent)));
+    }
+    let exponent = chinese_remainder(&residues)?;
+    if &base.power(&exponent)? != target {
+        return Err(anyhow!(
+            "Target is not in the subgroup generated by the base"
+        ));
+    }
+    Ok(Solution {
+        exponent,
+        steps: count,
+    })
+}
+
+/// returns x with x = r_i mod m_i for pairwise coprime m_i
+pub fn chinese_remainder(residues: &[(BigInt, BigInt)]) -> Result<BigInt> {
+    let modulus: BigInt = residues.iter().map(|(_, m)| m).product();
+    residues
+        .iter()
+        .try_fold(BigInt::from(0), |acc, (r, m)| {
+            let rest = &modulus / m;
+            let inverse = rest.mod_floor(m).modpow(&(totient(m)? - 1), m);
+            Ok(acc + r * rest * inverse)
+        })
+        .map(|x| x.mod_floor(&modulus))
+}
+
+/// returns Euler's totient, so that a^(φ(m) - 1) is the inverse of a modulo m
+fn totient(m: &BigInt) -> Result<BigInt> {
+    Ok(factorize(m)?
+        .into_iter()
+        .fold(m.clone(), |acc, (prime, _)| acc / &prime * (&prime - 1)))
+}
+
+/// returns the integers in [0, n)
+fn num_iter(n: &BigInt) -> impl Iterator<Item = BigInt> + '_ {
+    std::iter::successors(Some(BigInt::from(0)), |k| Some(k + 1)).take_while(move |k| k < n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::elliptic_curve_finite_field::CurveOverFiniteField;
+
+    fn f223_point(x: i64, y: i64) -> Point {
+        let curve = CurveOverFiniteField::new(FieldElement::new(0, 223), FieldElement::new(7, 223));
+        Point::new(
+            Some(Coordinate::new(
+                FieldElement::new(x, 223),
+                FieldElement::new(y, 223),
+            )),
+            curve,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_curve_subgroups() {
+        // orders 7, 21 and 42
+        for (x, y, order) in [(15, 86, 7), (47, 71, 21), (192, 105, 42)] {
+            let base = f223_point(x, y);
+            let order = BigInt::from(order);
+            assert_eq!(base.order().unwrap(), order);
+            for secret in num_iter(&order) {
+                let target = (&base * &secret).unwrap();
+                let bsgs = baby_step_giant_step(&base, &target, &order).unwrap();
+                assert_eq!(bsgs.exponent, secret);
+                let ph = pohlig_hellman(&base, &target, &order).unwrap();
+                assert_eq!(ph.exponent, secret);
+                let rho = pollard_rho(&base, &target, &order).unwrap();
+                assert_eq!((&base * &rho.exponent).unwrap(), target);
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_in_subgroup() {
+        let base = f223_point(15, 86);
+        let target = f223_point(47, 71);
+        let order = BigInt::from(7);
+        assert!(baby_step_giant_step(&base, &target, &order).is_err());
+        assert!(pohlig_hellman(&base, &target, &order).is_err());
+        assert!(pollard_rho(&base, &target, &order).is_err());
+    }
+
+    #[test]
+    fn test_multiplicative_group() {
+        // 3 generates F_223^*, of order 222 = 2 * 3 * 37
+        let base = FieldElement::new(3, 223);
+        let order = BigInt::from(222);
+        for secret in [0, 1, 2, 100, 221] {
+            let secret = BigInt::from(secret);
+            let target = base.pow(secret.clone());
+            assert_eq!(
+                baby_step_giant_step(&base, &target, &order)
+                    .unwrap()
+                    .exponent,
+                secret
+            );
+            assert_eq!(
+                pohlig_hellman(&base, &target, &order).unwrap().exponent,
+                secret
+            );
+            let rho = pollard_rho(&base, &target, &order).unwrap();
+            assert_eq!(base.pow(rho.exponent), target);
+        }
+    }
+
+    fn primitive_root(prime: &BigInt) -> FieldElement {
+        let factors = factorize(&(prime - 1)).unwrap();
+        (2..)
+            .map(|g| FieldElement::new(g, prime.clone()))
+            .find(|g| {
+                factors
+                    .iter()
+                    .all(|(q, _)| !g.pow((prime - 1) / q).is_one())
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_step_counts() {
+        // p - 1 = 2^2 * 3 * 11^2 * 13 * 53 is smooth, Pohlig–Hellman only ever
+        // searches subgroups of order at most 53
+        let prime = BigInt::from(1_000_429);
+        let base = primitive_root(&prime);
+        let order: BigInt = &prime - 1;
+        let secret = BigInt::from(123_456);
+        let target = base.pow(secret.clone());
+
+        let bsgs = baby_step_giant_step(&base, &target, &order).unwrap();
+        let ph = pohlig_hellman(&base, &target, &order).unwrap();
+        assert_eq!(bsgs.exponent, secret);
+        assert_eq!(ph.exponent, secret);
+        assert!(bsgs.steps <= 2 * 1001);
+        assert!(ph.steps < 200);
+    }
+
+    #[test]
+    fn test_rho_prime_subgroup() {
+        // p - 1 = 2 * 3 * 166667, rho searches the subgroup of prime order 166667
+        let prime = BigInt::from(1_000_003);
+        let q = BigInt::from(166_667);
+        let base = primitive_root(&prime).pow(6);
+        let target = base.pow(4242);
+        let rho = pollard_rho(&base, &target, &q).unwrap();
+        assert_eq!(rho.exponent, BigInt::from(4242));
+        // √q is about 408, expect a small multiple of it
+        assert!(rho.steps > 0 && rho.steps < 100 * 408);
+    }
+
+    #[test]
+    fn test_chinese_remainder() {
+        let residues = [
+            (BigInt::from(2), BigInt::from(3)),
+            (BigInt::from(3), BigInt::from(5)),
+            (BigInt::from(2), BigInt::from(7)),
+        ];
+        assert_eq!(chinese_remainder(&residues).unwrap(), BigInt::from(23));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::ecc::curve_params::CurveParams;
+use crate::ecc::elliptic_curve_finite_field::{Coordinate, Point};
+use anyhow::{anyhow, Result};
+use num_bigint::{BigInt, RandBigInt, Sign};
+use num_integer::Integer;
+use rand::thread_rng;
+use std::collections::VecDeque;
+use std::ops::Div;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub(crate) r: BigInt,
+    pub(crate) s: BigInt,
+}
+
+impl<R: Into<BigInt>, S: Into<BigInt>> From<(R, S)> for Signature {
+    fn from((r, s): (R, S)) -> Self {
+        Signature::new(r.into(), s.into())
+    }
+}
+
+impl Signature {
+    pub fn new(r: BigInt, s: BigInt) -> Self {
+        Self { r, s }
+    }
+
+    pub fn r(&self) -> &BigInt {
+        &self.r
+    }
+
+    pub fn s(&self) -> &BigInt {
+        &self.s
+    }
+
+    pub fn der(&self) -> Vec<u8> {
+        let encode = |big: &BigInt| {
+            let (_, r) = big.to_bytes_be();
+            let r_stripped = r.as_slice();
+            if r_stripped.first().unwrap() >= &b'\x80' {
+                let mut r = vec![b'\x00'];
+                r.extend_from_slice(r_stripped);
+                r
+            } else {
+                r
+            }
+        };
+        let r = encode(&self.r);
+        let s = encode(&self.s);
+        let mut res = VecDeque::new();
+        res.push_back(2);
+        res.extend(r.len().to_be_bytes().into_iter().skip_while(|v| *v == 0u8));
+        res.extend(r.iter());
+
+        res.push_back(2);
+        res.extend(s.len().to_be_bytes().into_iter().skip_while(|v| *v == 0u8));
+        res.extend(s.iter());
+
+        let length: Vec<u8> = res
+            .len()
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|v| *v == 0u8)
+            .collect();
+        length.into_iter().rev().for_each(|v| res.push_front(v));
+        res.push_front(b'\x30');
+        Vec::from(res)
+    }
+
+    /// returns whether s is in the lower half of [1, n), as BIP62/BIP146 policy requires
+    pub fn is_low_s(&self, params: &CurveParams) -> bool {
+        self.s >= BigInt::from(1) && self.s <= params.n.clone().div(2)
+    }
+
+    /// returns the signature with s replaced by n - s if it is in the upper half,
+    /// both verify against the same key
+    pub fn normalize_s(&self, params: &CurveParams) -> Self {
+        if self.s > params.n.clone().div(2) {
+            Self::new(self.r.clone(), &params.n - &self.s)
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Parses a strict DER encoded signature, as required by BIP66
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let [0x30, len, rest @ ..] = der else {
+            return Err(anyhow!("DER signature must start with a sequence"));
+        };
+        if *len as usize != rest.len() {
+            return Err(anyhow!("DER sequence length does not match"));
+        }
+        let parse_int = |data: &[u8]| -> Result<(BigInt, usize)> {
+            let [0x02, len, rest @ ..] = data else {
+                return Err(anyhow!("DER integer expected"));
+            };
+            let len = *len as usize;
+            if len == 0 || len > rest.len() || len >= 0x80 {
+                return Err(anyhow!("Invalid DER integer length"));
+            }
+            let int = &rest[..len];
+            if int[0] & 0x80 != 0 || (len > 1 && int[0] == 0 && int[1] & 0x80 == 0) {
+                return Err(anyhow!("DER integer is negative or not minimally encoded"));
+            }
+            Ok((BigInt::from_bytes_be(Sign::Plus, int), 2 + len))
+        };
+        let (r, r_len) = parse_int(rest)?;
+        let (s, s_len) = parse_int(&rest[r_len..])?;
+        if r_len + s_len != rest.len() {
+            return Err(anyhow!("Trailing bytes after DER signature"));
+        }
+        Ok(Self { r, s })
+    }
+}
+
+/// Signature together with the recovery id needed to get the public key back from it:
+/// bit 0 is the parity of R.y, the higher bits tell how many times n was subtracted from R.x
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoverableSignature {
+    pub(crate) signature: Signature,
+    pub(crate) recid: u8,
+}
+
+impl RecoverableSignature {
+    pub fn new(signature: Signature, recid: u8) -> Self {
+        Self { signature, recid }
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn recid(&self) -> u8 {
+        self.recid
+    }
+
+    /// returns the low-S form, negating s negates R so the parity bit of the recovery id flips
+    pub fn normalize_s(&self, params: &CurveParams) -> Self {
+        if self.signature.is_low_s(params) {
+            self.clone()
+        } else {
+            Self::new(self.signature.normalize_s(params), self.recid ^ 1)
+        }
+    }
+}
+
+/// Signs z with the secret on the given curve, s is normalized to the lower half of [1, n).
+/// Returns None if the secret is not in [1, n).
+pub fn sign(params: &CurveParams, secret: &BigInt, z: &BigInt) -> Option<Signature> {
+    sign_recoverable(params, secret, z).map(|sig| sig.signature)
+}
+
+/// Same as [`sign`] but keeps the recovery id
+pub fn sign_recoverable(
+    params: &CurveParams,
+    secret: &BigInt,
+    z: &BigInt,
+) -> Option<RecoverableSignature> {
+    let n = &params.n;
+    if secret <= &BigInt::from(0) || secret >= n {
+        return None;
+    }
+    let g = params.generator();
+    loop {
+        let k = thread_rng().gen_bigint_range(&BigInt::from(1), n);
+        let point = (&g * &k).ok()?;
+        let Some(Coordinate { x, y }) = point.coordinate() else {
+            continue;
+        };
+        let (overflow, r) = x.num.div_mod_floor(n);
+        if r == BigInt::from(0) {
+            continue;
+        }
+        let k_inv = k.modpow(&(n - 2), n);
+        let s = ((z + &r * secret) * k_inv).mod_floor(n);
+        if s == BigInt::from(0) {
+            continue;
+        }
+        let recid = u8::from(y.num.is_odd()) | u8::try_from(overflow).ok()? << 1;
+        let sig = RecoverableSignature {
+            signature: Signature { r, s },
+            recid,
+        };
+        return Some(sig.normalize_s(params));
+    }
+}
+
+/// Recovers the public key that made the signature of z, Q = r⁻¹(s⋅R - z⋅G).
+/// Returns None if the signature is malformed or the recovery id does not lead to a point.
+pub fn recover(params: &CurveParams, z: &BigInt, sig: &RecoverableSignature) -> Option<Point> {
+    let n = &params.n;
+    let Signature { r, s } = &sig.signature;
+    let zero = BigInt::from(0);
+    if r <= &zero || r >= n || s <= &zero || s >= n {
+        return None;
+    }
+    let x = r + n * BigInt::from(sig.recid >> 1);
+    if x >= params.p {
+        return None;
+    }
+    let big_r = params
+        .curve()
+        .lift_x(&params.field_element(x))
+        .ok()?
+        .into_iter()
+        .find(|p| {
+            p.coordinate()
+                .map(|c| c.y.num.is_odd() == (sig.recid & 1 == 1))
+                .unwrap_or_default()
+        })?;
+    let r_inv = r.modpow(&(n - 2), n);
+    let u = (s * &r_inv).mod_floor(n);
+    let v = (-z * &r_inv).mod_floor(n);
+    let public = ((&big_r * &u).ok()? + (&params.generator() * &v).ok()?).ok()?;
+    if public.is_infinity() || !verify(params, &public, z, &sig.signature) {
+        return None;
+    }
+    Some(public)
+}
+
+/// Verifies the signature of z by the public key on the given curve, both s and n - s are accepted
+pub fn verify(params: &CurveParams, public: &Point, z: &BigInt, sig: &Signature) -> bool {
+    let n = &params.n;
+    let one = BigInt::from(1);
+    if public.curve() != &params.curve() || sig.r < one || &sig.r >= n || sig.s < one || &sig.s >= n
+    {
+        return false;
+    }
+    let s_inv = sig.s.modpow(&(n - 2), n);
+    let u = (z * &s_inv).mod_floor(n);
+    let v = (&sig.r * &s_inv).mod_floor(n);
+    let total = (&params.generator() * &u).and_then(|gu| (public * &v).and_then(|pv| gu + pv));
+    total
+        .map(|p| {
+            p.coordinate()
+                .map(|Coordinate { x, .. }| x.num.mod_floor(n) == sig.r)
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+/// Same as [`verify`] but also rejects signatures whose s is not in the lower half
+pub fn verify_strict(params: &CurveParams, public: &Point, z: &BigInt, sig: &Signature) -> bool {
+    sig.is_low_s(params) && verify(params, public, z, sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::curve_params::{registry, SECP256K1, TOY_F223};
+    use hex_literal::hex;
+
+    #[test]
+    fn test_sign_any_curve() {
+        for params in registry() {
+            let secret = thread_rng().gen_bigint_range(&BigInt::from(1), &params.n);
+            let public = (&params.generator() * &secret).unwrap();
+            let z = thread_rng().gen_bigint_range(&BigInt::from(0), &params.n);
+            let sig = sign(params, &secret, &z).unwrap();
+            assert!(verify(params, &public, &z, &sig), "{}", params.name);
+            // tiny groups make accidental collisions likely
+            if params.n > BigInt::from(1000) {
+                let other_z = (&z + BigInt::from(1)).mod_floor(&params.n);
+                assert!(!verify(params, &public, &other_z, &sig), "{}", params.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_wrong_curve() {
+        let secret = BigInt::from(3);
+        let public = (&TOY_F223.generator() * &secret).unwrap();
+        let z = BigInt::from(5);
+        let sig = sign(&TOY_F223, &secret, &z).unwrap();
+        assert!(verify(&TOY_F223, &public, &z, &sig));
+        assert!(!verify(&SECP256K1, &public, &z, &sig));
+    }
+
+    #[test]
+    fn test_sign_invalid_secret() {
+        assert!(sign(&TOY_F223, &BigInt::from(0), &BigInt::from(1)).is_none());
+        assert!(sign(&TOY_F223, &BigInt::from(7), &BigInt::from(1)).is_none());
+    }
+
+    #[test]
+    fn test_recover_any_curve() {
+        for params in registry() {
+            let secret = thread_rng().gen_bigint_range(&BigInt::from(1), &params.n);
+            let public = (&params.generator() * &secret).unwrap();
+            let z = thread_rng().gen_bigint_range(&BigInt::from(0), &params.n);
+            let sig = sign_recoverable(params, &secret, &z).unwrap();
+            assert_eq!(recover(params, &z, &sig), Some(public), "{}", params.name);
+        }
+    }
+
+    #[test]
+    fn test_low_s() {
+        let params = &*SECP256K1;
+        let secret = BigInt::from(123456789);
+        let public = (&params.generator() * &secret).unwrap();
+        let z = BigInt::from(987654321);
+        let sig = sign(params, &secret, &z).unwrap();
+        assert!(sig.is_low_s(params));
+        assert!(verify_strict(params, &public, &z, &sig));
+
+        let high = Signature::new(sig.r.clone(), &params.n - &sig.s);
+        assert!(!high.is_low_s(params));
+        assert!(verify(params, &public, &z, &high));
+        assert!(!verify_strict(params, &public, &z, &high));
+        assert_eq!(high.normalize_s(params), sig);
+        assert_eq!(sig.normalize_s(params), sig);
+
+        let recoverable = sign_recoverable(params, &secret, &z).unwrap();
+        let flipped = RecoverableSignature::new(
+            Signature::new(
+                recoverable.signature.r.clone(),
+                &params.n - &recoverable.signature.s,
+            ),
+            recoverable.recid ^ 1,
+        );
+        assert_eq!(recover(params, &z, &flipped), Some(public.clone()));
+        assert_eq!(flipped.normalize_s(params), recoverable);
+    }
+
+    #[test]
+    fn test_verify_out_of_range() {
+        let params = &*SECP256K1;
+        let secret = BigInt::from(5);
+        let public = (&params.generator() * &secret).unwrap();
+        let z = BigInt::from(7);
+        let sig = sign(params, &secret, &z).unwrap();
+        for bad in [
+            Signature::new(BigInt::from(0), sig.s.clone()),
+            Signature::new(sig.r.clone(), BigInt::from(0)),
+            Signature::new(&sig.r + &params.n, sig.s.clone()),
+            Signature::new(sig.r.clone(), &sig.s + &params.n),
+        ] {
+            assert!(!verify(params, &public, &z, &bad));
+            assert!(!verify_strict(params, &public, &z, &bad));
+        }
+    }
+
+    #[test]
+    fn test_der() {
+        let sig = Signature::new(
+            BigInt::from_bytes_be(
+                Sign::Plus,
+                hex!("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6").as_slice(),
+            ),
+            BigInt::from_bytes_be(
+                Sign::Plus,
+                hex!("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec").as_slice(),
+            ),
+        );
+        assert_eq!(
+           sig.der(),
+            hex!("3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec")
+        );
+        assert_eq!(Signature::from_der(&sig.der()).unwrap(), sig);
+        assert!(Signature::from_der(&sig.der()[..70]).is_err());
+    }
+
+    #[test]
+    fn test_der_high_bit() {
+        // a leading 0x80 byte needs the zero padding as well, without it the integer
+        // reads as negative
+        let sig = Signature::new(BigInt::from(0x80u32) << 248, BigInt::from(1));
+        let der = sig.der();
+        assert_eq!(&der[..5], &[0x30, 0x26, 0x02, 0x21, 0x00]);
+        assert_eq!(Signature::from_der(&der).unwrap(), sig);
+
+        let sig = Signature::new(BigInt::from(1), BigInt::from(0x80u32) << 248);
+        assert_eq!(
+            sig.der(),
+            [
+                &[0x30, 0x26, 0x02, 0x01, 0x01, 0x02, 0x21, 0x00, 0x80][..],
+                &[0; 31][..]
+            ]
+            .concat()
+        );
+        assert_eq!(Signature::from_der(&sig.der()).unwrap(), sig);
+
+        // the highest byte that needs no padding
+        let sig = Signature::new(BigInt::from(0x7fu32) << 248, BigInt::from(0x7fu32));
+        assert_eq!(&sig.der()[..4], &[0x30, 0x25, 0x02, 0x20]);
+        assert_eq!(&sig.der()[36..], &[0x02, 0x01, 0x7f]);
+        assert_eq!(Signature::from_der(&sig.der()).unwrap(), sig);
+    }
+}
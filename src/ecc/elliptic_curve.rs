@@ -1,34 +1,44 @@
 use anyhow::{anyhow, Error};
 use num_bigint::BigInt;
+use num_rational::BigRational;
 use std::ops::Add;
 
-//y^2 = x^3 + A*x + B
+//y^2 = x^3 + A*x + B over the rational numbers
 #[derive(Clone, Debug, PartialEq, Eq)]
-struct Curve {
-    a: BigInt,
-    b: BigInt,
+pub struct Curve {
+    a: BigRational,
+    b: BigRational,
 }
 
 impl Default for Curve {
     fn default() -> Self {
-        Self {
-            a: 5i64.into(),
-            b: 7i64.into(),
-        }
+        Self::new(5i64, 7i64)
     }
 }
 
 impl Curve {
     pub fn new<A: Into<BigInt>, B: Into<BigInt>>(a: A, b: B) -> Self {
         Self {
-            a: a.into(),
-            b: b.into(),
+            a: BigRational::from_integer(a.into()),
+            b: BigRational::from_integer(b.into()),
         }
     }
+
+    pub fn new_rational(a: BigRational, b: BigRational) -> Self {
+        Self { a, b }
+    }
+
+    pub fn a(&self) -> &BigRational {
+        &self.a
+    }
+
+    pub fn b(&self) -> &BigRational {
+        &self.b
+    }
 }
 
 #[derive(Debug, Eq, Clone, Default)]
-struct Point {
+pub struct Point {
     coordinate: Option<Coordinate>,
     curve: Curve,
 }
@@ -50,6 +60,7 @@ impl Add for &Point {
 
     fn add(self, rhs: Self) -> Self::Output {
         let curve = self.curve.clone();
+        let zero = BigRational::from_integer(0.into());
         match (&self.coordinate, &rhs.coordinate) {
             (None, None) => Point::new(None, curve).unwrap(),
             (None, Some(_)) => rhs.clone(),
@@ -59,13 +70,15 @@ impl Add for &Point {
             {
                 Point::new(None, curve).unwrap()
             }
-            (p1 @ Some(Coordinate { y, .. }), p2) if p1 == p2 && y == &BigInt::from(0i64) => {
+            (p1 @ Some(Coordinate { y, .. }), p2) if p1 == p2 && y == &zero => {
                 Point::new(None, curve).unwrap()
             }
             (p1 @ Some(Coordinate { x: x1, y: y1 }), p2) if p1 == p2 => {
-                let s: BigInt = (3 * x1 * x1 + &self.curve.a) / 2 / y1;
-                let x: BigInt = &s * &s - 2 * x1;
-                let y: BigInt = s * (x1 - &x) - y1;
+                let three = BigRational::from_integer(3.into());
+                let two = BigRational::from_integer(2.into());
+                let s: BigRational = (three * x1 * x1 + &self.curve.a) / (&two * y1);
+                let x: BigRational = &s * &s - two * x1;
+                let y: BigRational = s * (x1 - &x) - y1;
                 Point::new(Some(Coordinate::new(x, y)), curve).unwrap()
             }
             (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 })) => {
@@ -82,64 +95,56 @@ impl Add for Point {
     type Output = Point;
 
     fn add(self, rhs: Self) -> Self::Output {
-        match (&self.coordinate, &rhs.coordinate) {
-            (None, None) => Point::new(None, self.curve).unwrap(),
-            (None, Some(_)) => rhs,
-            (Some(_), None) => self,
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 }))
-                if x1 == x2 && y1 != y2 =>
-            {
-                Point::new(None, self.curve).unwrap()
-            }
-            (p1 @ Some(Coordinate { y, .. }), p2) if p1 == p2 && y == &BigInt::from(0i64) => {
-                Point::new(None, self.curve).unwrap()
-            }
-            (p1 @ Some(Coordinate { x: x1, y: y1 }), p2) if p1 == p2 => {
-                let s: BigInt = (3 * x1 * x1 + &self.curve.a) / 2 / y1;
-                let x: BigInt = &s * &s - 2 * x1;
-                let y: BigInt = s * (x1 - &x) - y1;
-                Point::new(Some(Coordinate::new(x, y)), rhs.curve).unwrap()
-            }
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 })) => {
-                let s = (y2 - y1) / (x2 - x1);
-                let x = &s * &s - x1 - x2;
-                let y = &s * (x1 - &x) - y1;
-                Point::new(Some(Coordinate::new(x, y)), self.curve).unwrap()
-            }
-        }
+        &self + &rhs
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct Coordinate {
-    x: BigInt,
-    y: BigInt,
+    x: BigRational,
+    y: BigRational,
 }
 
 impl<X: Into<BigInt>, Y: Into<BigInt>> From<(X, Y)> for Coordinate {
     fn from((x, y): (X, Y)) -> Self {
         Self {
-            x: x.into(),
-            y: y.into(),
+            x: BigRational::from_integer(x.into()),
+            y: BigRational::from_integer(y.into()),
         }
     }
 }
 
 impl Coordinate {
-    pub fn new(x: BigInt, y: BigInt) -> Coordinate {
+    pub fn new(x: BigRational, y: BigRational) -> Coordinate {
         Self { x, y }
     }
+
+    pub fn x(&self) -> &BigRational {
+        &self.x
+    }
+
+    pub fn y(&self) -> &BigRational {
+        &self.y
+    }
 }
 
 impl Point {
     pub fn new(coordinate: Option<Coordinate>, curve: Curve) -> Result<Point, Error> {
         match &coordinate {
-            Some(Coordinate { x, y }) if y.pow(2) != x.pow(3) + &curve.a * x + &curve.b => {
+            Some(Coordinate { x, y }) if y * y != x * x * x + &curve.a * x + &curve.b => {
                 Err(anyhow!("Invalid point coordinates"))
             }
             _ => Ok(Self { coordinate, curve }),
         }
     }
+
+    pub fn coordinate(&self) -> Option<&Coordinate> {
+        self.coordinate.as_ref()
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
 }
 
 #[cfg(test)]
@@ -190,4 +195,53 @@ mod tests {
         let c = Point::new(None, curve).unwrap();
         assert_eq!(a + b, c);
     }
+
+    #[test]
+    fn test_add_rational_slope() {
+        let rational = |n: i64, d: i64| BigRational::new(n.into(), d.into());
+
+        // slope 72 / 16 = 9 / 2
+        let a = Point::new(Some((2, 5).into()), Curve::default()).unwrap();
+        let b = Point::new(Some((18, 77).into()), Curve::default()).unwrap();
+        let c = Point::new(
+            Some(Coordinate::new(rational(1, 4), rational(23, 8))),
+            Curve::default(),
+        )
+        .unwrap();
+        assert_eq!(&a + &b, c);
+        assert_eq!(&b + &a, c);
+
+        // tangent slope 17 / 10
+        let doubled = Point::new(
+            Some(Coordinate::new(rational(-111, 100), rational(287, 1000))),
+            Curve::default(),
+        )
+        .unwrap();
+        assert_eq!(&a + &a, doubled);
+
+        assert_eq!(&(&a + &b) + &doubled, &a + &(&b + &doubled));
+    }
+
+    #[test]
+    fn test_rational_curve() {
+        let rational = |n: i64, d: i64| BigRational::new(n.into(), d.into());
+        let curve = Curve::new_rational(rational(1, 4), rational(0, 1));
+        // 1/8 + 1/8 = (1/2)^2
+        let a = Point::new(
+            Some(Coordinate::new(rational(1, 2), rational(1, 2))),
+            curve.clone(),
+        )
+        .unwrap();
+        assert!(Point::new(Some((1, 1).into()), curve.clone()).is_err());
+
+        // the tangent at (1/2, 1/2) has slope s = (3/4 + 1/4) / 1 = 1 and meets the curve
+        // again at the point of order 2: x = 1 - 1 = 0, y = 1 * (1/2 - 0) - 1/2 = 0
+        let doubled = Point::new(
+            Some(Coordinate::new(rational(0, 1), rational(0, 1))),
+            curve.clone(),
+        )
+        .unwrap();
+        assert_eq!(&a + &a, doubled);
+        assert_eq!(&doubled + &doubled, Point::new(None, curve).unwrap());
+    }
 }
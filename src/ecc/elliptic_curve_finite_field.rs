@@ -1,31 +1,165 @@
-use crate::ecc::finite_field::FieldElement;
+use crate::ecc::finite_field::{is_prime, FieldElement, FiniteField};
 use anyhow::{anyhow, Result};
-use num_bigint::BigInt;
-use std::ops::{Add, Mul};
+use num_bigint::{BigInt, Sign};
+use std::ops::{Add, Mul, Neg, Sub};
 
-//y^2 = x^3 + A*x + B
+pub mod group;
+
+//y^2 + a1*x*y + a3*y = x^3 + a2*x^2 + A*x + B
+//the short form y^2 = x^3 + A*x + B has a1 = a2 = a3 = 0, fields of characteristic 2 need
+//the general one, e.g. y^2 + x*y = x^3 + a2*x^2 + B
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct CurveOverFiniteField {
-    a: FieldElement,
-    b: FieldElement,
+pub struct CurveOverFiniteField<F = FieldElement> {
+    a1: F,
+    a2: F,
+    a3: F,
+    a: F,
+    b: F,
+}
+
+/// returns the sum of a non-empty sequence of elements of the same field
+fn sum<F: FiniteField>(terms: impl IntoIterator<Item = F>) -> Result<F> {
+    let mut terms = terms.into_iter();
+    let first = terms
+        .next()
+        .ok_or_else(|| anyhow!("Cannot sum an empty sequence"))?;
+    terms.try_fold(first, |acc, term| acc + term)
 }
 
-impl CurveOverFiniteField {
-    pub fn new<A: Into<FieldElement>, B: Into<FieldElement>>(a: A, b: B) -> Self {
+impl<F: FiniteField> CurveOverFiniteField<F> {
+    /// Creates a curve in short Weierstrass form without validating its parameters.
+    /// Meant for trusted constants, use [`CurveOverFiniteField::try_new`] for anything else.
+    pub fn new<A: Into<F>, B: Into<F>>(a: A, b: B) -> Self {
+        let a = a.into();
         Self {
-            a: a.into(),
+            a1: a.constant(0),
+            a2: a.constant(0),
+            a3: a.constant(0),
+            a,
             b: b.into(),
         }
     }
+
+    /// Creates a curve in short Weierstrass form, rejecting non-prime characteristics and
+    /// singular curves, which include every short curve over a field of characteristic 2.
+    pub fn try_new<A: Into<F>, B: Into<F>>(a: A, b: B) -> Result<Self> {
+        Self::new(a, b).validated()
+    }
+
+    /// Creates a curve in general Weierstrass form without validating its parameters,
+    /// `a` and `b` being the coefficients of x and 1 as in the short form.
+    pub fn weierstrass(a1: F, a2: F, a3: F, a: F, b: F) -> Self {
+        Self { a1, a2, a3, a, b }
+    }
+
+    /// Creates a curve in general Weierstrass form, rejecting coefficients of different
+    /// fields, non-prime characteristics and singular curves.
+    pub fn try_weierstrass(a1: F, a2: F, a3: F, a: F, b: F) -> Result<Self> {
+        Self::weierstrass(a1, a2, a3, a, b).validated()
+    }
+
+    fn validated(self) -> Result<Self> {
+        if [&self.a1, &self.a2, &self.a3, &self.b]
+            .iter()
+            .any(|c| !c.same_field(&self.a))
+        {
+            return Err(anyhow!("Curve coefficients belong to different Fields"));
+        }
+        let characteristic = self.a.characteristic();
+        if !is_prime(&characteristic) {
+            return Err(anyhow!("Modulus {characteristic} is not a prime number"));
+        }
+        if self.discriminant()?.is_zero() {
+            return Err(anyhow!("Curve is singular"));
+        }
+        Ok(self)
+    }
+
+    pub fn a1(&self) -> &F {
+        &self.a1
+    }
+
+    pub fn a2(&self) -> &F {
+        &self.a2
+    }
+
+    pub fn a3(&self) -> &F {
+        &self.a3
+    }
+
+    pub fn a(&self) -> &F {
+        &self.a
+    }
+
+    pub fn b(&self) -> &F {
+        &self.b
+    }
+
+    /// returns true if a1 = a2 = a3 = 0
+    pub fn is_short(&self) -> bool {
+        self.a1.is_zero() && self.a2.is_zero() && self.a3.is_zero()
+    }
+
+    /// returns the discriminant, which is zero for singular curves.
+    /// In short form it is -16(4a^3 + 27b^2).
+    pub fn discriminant(&self) -> Result<F> {
+        let (a1, a2, a3, a4, a6) = (&self.a1, &self.a2, &self.a3, &self.a, &self.b);
+        let c = |n: i64| a4.constant(n);
+        let b2 = ((a1.clone() * a1)? + (c(4) * a2)?)?;
+        let b4 = ((c(2) * a4)? + (a1.clone() * a3)?)?;
+        let b6 = ((a3.clone() * a3)? + (c(4) * a6)?)?;
+        let b8 = sum([
+            ((a1.clone() * a1)? * a6)?,
+            ((c(4) * a2)? * a6)?,
+            -((a1.clone() * a3)? * a4)?,
+            ((a2.clone() * a3)? * a3)?,
+            -(a4.clone() * a4)?,
+        ])?;
+        // -b2^2*b8 - 8b4^3 - 27b6^2 + 9b2*b4*b6
+        sum([
+            -((b2.clone() * &b2)? * &b8)?,
+            -(((c(8) * &b4)? * &b4)? * &b4)?,
+            -((c(27) * &b6)? * &b6)?,
+            (((c(9) * &b2)? * &b4)? * &b6)?,
+        ])
+    }
+
+    pub fn infinity(&self) -> Point<F> {
+        Point {
+            coordinate: None,
+            curve: self.clone(),
+        }
+    }
+
+    /// returns x^3 + a2x^2 + ax + b, the square of y at a point with abscissa x
+    /// for curves in short form
+    pub fn rhs(&self, x: &F) -> Result<F> {
+        let x_squared = (x.clone() * x)?;
+        sum([
+            (x_squared.clone() * x)?,
+            (self.a2.clone() * &x_squared)?,
+            (self.a.clone() * x)?,
+            self.b.clone(),
+        ])
+    }
+
+    /// returns y^2 + a1xy + a3y, which equals [`CurveOverFiniteField::rhs`] on the curve
+    fn lhs(&self, x: &F, y: &F) -> Result<F> {
+        sum([
+            (y.clone() * y)?,
+            ((self.a1.clone() * x)? * y)?,
+            (self.a3.clone() * y)?,
+        ])
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Coordinate {
-    pub x: FieldElement,
-    pub y: FieldElement,
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Coordinate<F = FieldElement> {
+    pub x: F,
+    pub y: F,
 }
 
-impl<X: Into<FieldElement>, Y: Into<FieldElement>> From<(X, Y)> for Coordinate {
+impl<F, X: Into<F>, Y: Into<F>> From<(X, Y)> for Coordinate<F> {
     fn from((x, y): (X, Y)) -> Self {
         Self {
             x: x.into(),
@@ -34,308 +168,230 @@ impl<X: Into<FieldElement>, Y: Into<FieldElement>> From<(X, Y)> for Coordinate {
     }
 }
 
-impl Coordinate {
-    pub fn new(x: FieldElement, y: FieldElement) -> Coordinate {
+impl<F> Coordinate<F> {
+    pub fn new(x: F, y: F) -> Coordinate<F> {
         Self { x, y }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Point {
-    pub coordinate: Option<Coordinate>,
-    curve: CurveOverFiniteField,
+pub struct Point<F = FieldElement> {
+    coordinate: Option<Coordinate<F>>,
+    curve: CurveOverFiniteField<F>,
 }
 
-impl Mul<&BigInt> for Point {
-    type Output = Point;
+impl<F: FiniteField> Mul<&BigInt> for Point<F> {
+    type Output = Result<Point<F>>;
 
     fn mul(self, rhs: &BigInt) -> Self::Output {
         (&self).mul(rhs)
     }
 }
 
-impl Mul<BigInt> for Point {
-    type Output = Point;
+impl<F: FiniteField> Mul<BigInt> for Point<F> {
+    type Output = Result<Point<F>>;
 
     fn mul(self, rhs: BigInt) -> Self::Output {
         (&self).mul(&rhs)
     }
 }
 
-impl Mul<&BigInt> for &Point {
-    type Output = Point;
+impl<F: FiniteField> Mul<&BigInt> for &Point<F> {
+    type Output = Result<Point<F>>;
 
     fn mul(self, rhs: &BigInt) -> Self::Output {
-        if rhs == &BigInt::default() {
-            return Point::new(None, self.curve.clone()).unwrap();
+        // (-k)P = k(-P)
+        if rhs.sign() == Sign::Minus {
+            return &-self * &-rhs;
         }
         let mut rhs = rhs.clone();
         let mut current = self.clone();
         let zero = BigInt::from(0);
         let one = BigInt::from(1);
-        let mut res = Point::new(None, self.curve.clone()).unwrap();
+        let mut res = self.curve.infinity();
         while rhs > zero {
             if &rhs & &one > zero {
-                res = (&res + &current).unwrap();
+                res = (&res + &current)?;
             }
-            current = (&current + &current).unwrap();
+            current = (&current + &current)?;
             rhs = &rhs >> 1;
         }
-        res
+        Ok(res)
     }
 }
 
-impl Point {
-    pub fn new(coordinate: Option<Coordinate>, curve: CurveOverFiniteField) -> Result<Point> {
-        match &coordinate {
-            Some(Coordinate { x, y })
-                if (y * y).unwrap()
-                    != (x * &curve.a)
-                        .and_then(|v| v + x.pow(3))
-                        .and_then(|v| v + &curve.b)
-                        .unwrap() =>
-            {
-                Err(anyhow!("Invalid coordinate"))
+impl<F: FiniteField> Point<F> {
+    pub fn new(coordinate: Option<Coordinate<F>>, curve: CurveOverFiniteField<F>) -> Result<Self> {
+        if let Some(Coordinate { x, y }) = &coordinate {
+            if !x.same_field(y) || !x.same_field(&curve.a) {
+                return Err(anyhow!("Coordinates and curve belong to different Fields"));
+            }
+            if curve.lhs(x, y)? != curve.rhs(x)? {
+                return Err(anyhow!("Invalid coordinate"));
             }
-            _ => Ok(Self { coordinate, curve }),
         }
+        Ok(Self { coordinate, curve })
+    }
+
+    /// returns the affine coordinate, None for the point at infinity
+    pub fn coordinate(&self) -> Option<&Coordinate<F>> {
+        self.coordinate.as_ref()
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.coordinate.is_none()
+    }
+
+    /// returns true if the point lies in the subgroup of the given order,
+    /// the check curves with a cofactor need before trusting a peer's point
+    pub fn is_in_subgroup(&self, order: &BigInt) -> bool {
+        (self * order).map_or(false, |p| p.is_infinity())
+    }
+
+    pub fn curve(&self) -> &CurveOverFiniteField<F> {
+        &self.curve
     }
 }
 
-impl PartialEq<Point> for Point {
-    fn eq(&self, other: &Point) -> bool {
+impl<F: FiniteField> PartialEq<Point<F>> for Point<F> {
+    fn eq(&self, other: &Point<F>) -> bool {
         self.curve == other.curve && self.coordinate == other.coordinate
     }
 }
 
-impl PartialEq<&Point> for Point {
-    fn eq(&self, other: &&Point) -> bool {
+impl<F: FiniteField> PartialEq<&Point<F>> for Point<F> {
+    fn eq(&self, other: &&Point<F>) -> bool {
         self.curve == other.curve && self.coordinate == other.coordinate
     }
 }
 
-impl Add for Point {
-    type Output = Result<Point>;
+impl<F: FiniteField> Add for Point<F> {
+    type Output = Result<Point<F>>;
 
     fn add(self, rhs: Self) -> Self::Output {
-        if self.curve != rhs.curve {
-            return Err(anyhow!("Cannot add points on different curves"));
-        }
-        let curve = self.curve.clone();
-        match (&self.coordinate, &rhs.coordinate) {
-            (None, None) => Ok(Point::new(None, self.curve).unwrap()),
-            (None, Some(_)) => Ok(rhs),
-            (Some(_), None) => Ok(self),
-            (
-                Some(Coordinate {
-                    x:
-                        FieldElement {
-                            prime: self_prime_x,
-                            ..
-                        },
-                    y:
-                        FieldElement {
-                            prime: self_prime_y,
-                            ..
-                        },
-                }),
-                Some(Coordinate {
-                    x:
-                        FieldElement {
-                            prime: rhs_prime_x, ..
-                        },
-                    y:
-                        FieldElement {
-                            prime: rhs_prime_y, ..
-                        },
-                }),
-            ) if self_prime_x != self_prime_y
-                || self_prime_y != rhs_prime_x
-                || rhs_prime_x != rhs_prime_y =>
-            {
-                Err(anyhow!("Invalid prime numbers for self/rhs"))
-            }
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 }))
-                if x1 == x2 && y1 != y2 =>
-            {
-                Ok(Point::new(None, self.curve).unwrap())
-            }
-            (p1 @ Some(Coordinate { y, .. }), p2) if p1 == p2 && y.num == BigInt::from(0i64) => {
-                Ok(Point::new(None, self.curve).unwrap())
-            }
-            (p1 @ Some(Coordinate { x: x1, y: y1 }), p2) if p1 == p2 => {
-                let two = FieldElement::new(2, x1.prime.clone());
-                let s = Ok(FieldElement::new(3, x1.prime.clone()))
-                    .and_then(|v| v * x1)
-                    .and_then(|v| v * x1)
-                    .and_then(|v| v + self.curve.a)
-                    .and_then(|v| v / &two)
-                    .and_then(|v| v / y1)
-                    .unwrap();
-                let x = ((&s * &s).unwrap() - (two * x1).unwrap()).unwrap();
-                let y = (x1 - &x).and_then(|v| v * s).and_then(|v| v - y1).unwrap();
-                Ok(Point::new(Some(Coordinate::new(x, y)), curve).unwrap())
-            }
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 })) => {
-                let s = ((y2 - y1).unwrap() / (x2 - x1).unwrap()).unwrap();
-                let x = (&s * &s).and_then(|v| v - x1).and_then(|v| v - x2).unwrap();
-                let y = (x1 - &x).and_then(|v| v * &s).and_then(|v| v - y1).unwrap();
-                Ok(Point::new(Some(Coordinate::new(x, y)), curve).unwrap())
-            }
-        }
+        &self + &rhs
     }
 }
 
-impl Add<&Point> for Point {
-    type Output = Result<Point>;
+impl<F: FiniteField> Add<&Point<F>> for Point<F> {
+    type Output = Result<Point<F>>;
 
-    fn add(self, rhs: &Point) -> Self::Output {
-        if self.curve != rhs.curve {
-            return Err(anyhow!("Cannot add points on different curves"));
-        }
-        let curve = self.curve.clone();
-        match (&self.coordinate, &rhs.coordinate) {
-            (None, None) => Ok(Point::new(None, self.curve).unwrap()),
-            (None, Some(_)) => Ok(rhs.clone()),
-            (Some(_), None) => Ok(self),
-            (
-                Some(Coordinate {
-                    x:
-                        FieldElement {
-                            prime: self_prime_x,
-                            ..
-                        },
-                    y:
-                        FieldElement {
-                            prime: self_prime_y,
-                            ..
-                        },
-                }),
-                Some(Coordinate {
-                    x:
-                        FieldElement {
-                            prime: rhs_prime_x, ..
-                        },
-                    y:
-                        FieldElement {
-                            prime: rhs_prime_y, ..
-                        },
-                }),
-            ) if self_prime_x != self_prime_y
-                || self_prime_y != rhs_prime_x
-                || rhs_prime_x != rhs_prime_y =>
-            {
-                Err(anyhow!("Invalid prime numbers for self/rhs"))
-            }
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 }))
-                if x1 == x2 && y1 != y2 =>
-            {
-                Ok(Point::new(None, self.curve).unwrap())
-            }
-            (p1 @ Some(Coordinate { y, .. }), p2) if p1 == p2 && y.num == BigInt::from(0i64) => {
-                Ok(Point::new(None, self.curve).unwrap())
-            }
-            (p1 @ Some(Coordinate { x: x1, y: y1 }), p2) if p1 == p2 => {
-                let two = FieldElement::new(2, x1.prime.clone());
-                let s = Ok(FieldElement::new(3, x1.prime.clone()))
-                    .and_then(|v| v * x1)
-                    .and_then(|v| v * x1)
-                    .and_then(|v| v + self.curve.a)
-                    .and_then(|v| v / &two)
-                    .and_then(|v| v / y1)
-                    .unwrap();
-                let x = ((&s * &s).unwrap() - (two * x1).unwrap()).unwrap();
-                let y = (x1 - &x).and_then(|v| v * s).and_then(|v| v - y1).unwrap();
-                Ok(Point::new(Some(Coordinate::new(x, y)), curve).unwrap())
-            }
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 })) => {
-                let s = ((y2 - y1).unwrap() / (x2 - x1).unwrap()).unwrap();
-                let x = (&s * &s).and_then(|v| v - x1).and_then(|v| v - x2).unwrap();
-                let y = (x1 - &x).and_then(|v| v * &s).and_then(|v| v - y1).unwrap();
-                Ok(Point::new(Some(Coordinate::new(x, y)), curve).unwrap())
-            }
-        }
+    fn add(self, rhs: &Point<F>) -> Self::Output {
+        &self + rhs
     }
 }
 
-impl Add<&Point> for &Point {
-    type Output = Result<Point>;
+impl<F: FiniteField> Add<&Point<F>> for &Point<F> {
+    type Output = Result<Point<F>>;
 
-    fn add(self, rhs: &Point) -> Self::Output {
+    fn add(self, rhs: &Point<F>) -> Self::Output {
         if self.curve != rhs.curve {
             return Err(anyhow!("Cannot add points on different curves"));
         }
-        let curve = self.curve.clone();
+        let (a1, a2, a3) = (&self.curve.a1, &self.curve.a2, &self.curve.a3);
+        // both points were validated on the curve, so the sums below are too.
+        // The line through them with slope λ meets the curve at a third point,
+        // x = λ^2 + a1λ - a2 - x1 - x2 and y = λ(x1 - x) - y1 - a1x - a3 after reflection.
+        let third_point = |lambda: F, x1: &F, y1: &F, x2: &F| -> Result<Point<F>> {
+            let x = sum([
+                (lambda.clone() * &lambda)?,
+                (a1.clone() * &lambda)?,
+                -a2.clone(),
+                -x1.clone(),
+                -x2.clone(),
+            ])?;
+            let y = sum([
+                (lambda * (x1.clone() - &x)?)?,
+                -y1.clone(),
+                -(a1.clone() * &x)?,
+                -a3.clone(),
+            ])?;
+            Ok(Point {
+                coordinate: Some(Coordinate::new(x, y)),
+                curve: self.curve.clone(),
+            })
+        };
         match (&self.coordinate, &rhs.coordinate) {
-            (None, None) => Ok(Point::new(None, self.curve.clone()).unwrap()),
-            (None, Some(_)) => Ok(rhs.clone()),
-            (Some(_), None) => Ok(self.clone()),
-            (
-                Some(Coordinate {
-                    x:
-                        FieldElement {
-                            prime: self_prime_x,
-                            ..
-                        },
-                    y:
-                        FieldElement {
-                            prime: self_prime_y,
-                            ..
-                        },
-                }),
-                Some(Coordinate {
-                    x:
-                        FieldElement {
-                            prime: rhs_prime_x, ..
-                        },
-                    y:
-                        FieldElement {
-                            prime: rhs_prime_y, ..
-                        },
-                }),
-            ) if self_prime_x != self_prime_y
-                || self_prime_y != rhs_prime_x
-                || rhs_prime_x != rhs_prime_y =>
-            {
-                Err(anyhow!("Invalid prime numbers for self/rhs"))
-            }
-            (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 }))
-                if x1 == x2 && y1 != y2 =>
-            {
-                Ok(Point::new(None, self.curve.clone()).unwrap())
-            }
-            (p1 @ Some(Coordinate { y, .. }), p2) if p1 == p2 && y.num == BigInt::from(0i64) => {
-                Ok(Point::new(None, self.curve.clone()).unwrap())
-            }
-            (p1 @ Some(Coordinate { x: x1, y: y1 }), p2) if p1 == p2 => {
-                let two = FieldElement::new(2, x1.prime.clone());
-                let s = Ok(FieldElement::new(3, x1.prime.clone()))
-                    .and_then(|v| v * x1)
-                    .and_then(|v| v * x1)
-                    .and_then(|v| v + &self.curve.a)
-                    .and_then(|v| v / &two)
-                    .and_then(|v| v / y1)
-                    .unwrap();
-                let x = ((&s * &s).unwrap() - (two * x1).unwrap()).unwrap();
-                let y = (x1 - &x).and_then(|v| v * s).and_then(|v| v - y1).unwrap();
-                Ok(Point::new(Some(Coordinate::new(x, y)), curve).unwrap())
+            (None, _) => Ok(rhs.clone()),
+            (_, None) => Ok(self.clone()),
+            // vertical lines, doubling a point of order 2 included
+            _ if *rhs == -self => Ok(self.curve.infinity()),
+            (Some(Coordinate { x: x1, y: y1 }), _) if self == rhs => {
+                // λ = (3x1^2 + 2a2x1 + a - a1y1) / (2y1 + a1x1 + a3)
+                let numerator = sum([
+                    ((x1.constant(3) * x1)? * x1)?,
+                    ((x1.constant(2) * a2)? * x1)?,
+                    self.curve.a.clone(),
+                    -(a1.clone() * y1)?,
+                ])?;
+                let denominator = sum([(y1.constant(2) * y1)?, (a1.clone() * x1)?, a3.clone()])?;
+                third_point((numerator / denominator)?, x1, y1, x1)
             }
             (Some(Coordinate { x: x1, y: y1 }), Some(Coordinate { x: x2, y: y2 })) => {
-                let s = ((y2 - y1).unwrap() / (x2 - x1).unwrap()).unwrap();
-                let x = (&s * &s).and_then(|v| v - x1).and_then(|v| v - x2).unwrap();
-                let y = (x1 - &x).and_then(|v| v * &s).and_then(|v| v - y1).unwrap();
-                Ok(Point::new(Some(Coordinate::new(x, y)), curve).unwrap())
+                // λ = (y2 - y1) / (x2 - x1)
+                let lambda = ((y2.clone() - y1)? / (x2.clone() - x1)?)?;
+                third_point(lambda, x1, y1, x2)
             }
         }
     }
 }
 
+impl<F: FiniteField> Neg for &Point<F> {
+    type Output = Point<F>;
+
+    fn neg(self) -> Self::Output {
+        let coordinate = self.coordinate.as_ref().map(|Coordinate { x, y }| {
+            // -(x, y) = (x, -y - a1x - a3)
+            let a1_x = (self.curve.a1.clone() * x).expect("same field");
+            let y = sum([-y.clone(), -a1_x, -self.curve.a3.clone()]).expect("same field");
+            Coordinate::new(x.clone(), y)
+        });
+        Point {
+            coordinate,
+            curve: self.curve.clone(),
+        }
+    }
+}
+
+impl<F: FiniteField> Neg for Point<F> {
+    type Output = Point<F>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl<F: FiniteField> Sub<&Point<F>> for &Point<F> {
+    type Output = Result<Point<F>>;
+
+    fn sub(self, rhs: &Point<F>) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+impl<F: FiniteField> Sub for Point<F> {
+    type Output = Result<Point<F>>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl<F: FiniteField> Sub<&Point<F>> for Point<F> {
+    type Output = Result<Point<F>>;
+
+    fn sub(self, rhs: &Point<F>) -> Self::Output {
+        &self - rhs
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use num_bigint::BigInt;
 
     #[test]
+    #[allow(clippy::into_iter_on_ref)]
     fn test_on_curve() {
         let prime = BigInt::from(223);
         let a = FieldElement::new(0, prime.clone());
@@ -363,6 +419,55 @@ mod tests {
         assert!(valid);
     }
 
+    #[test]
+    fn test_try_new_curve() {
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((0, 223), (7, 223)).is_ok());
+        // 4 * (-3)^3 + 27 * 2^2 = 0
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((-3, 223), (2, 223)).is_err());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((0, 223), (0, 223)).is_err());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((0, 221), (7, 221)).is_err());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((0, 223), (7, 227)).is_err());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((1, 0), (7, 0)).is_err());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((1, -7), (7, -7)).is_err());
+        // short curves are singular in characteristic 2, not necessarily in characteristic 3
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((1, 2), (1, 2)).is_err());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((2, 3), (1, 3)).is_ok());
+        assert!(CurveOverFiniteField::<FieldElement>::try_new((0, 3), (1, 3)).is_err());
+        // y^2 + xy = x^3 + 1 over F_2, singular once the constant term is zero
+        let f2 = |n| FieldElement::new(n, 2);
+        let binary =
+            CurveOverFiniteField::try_weierstrass(f2(1), f2(0), f2(0), f2(0), f2(1)).unwrap();
+        assert!(!binary.is_short());
+        // point counting only knows the short form
+        assert!(binary.points().is_err());
+        assert!(binary.lift_x(&f2(0)).is_err());
+        assert!(CurveOverFiniteField::try_weierstrass(f2(1), f2(0), f2(0), f2(0), f2(0)).is_err());
+        assert!(CurveOverFiniteField::try_weierstrass(
+            f2(1),
+            f2(0),
+            f2(0),
+            f2(0),
+            FieldElement::new(1, 3)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_characteristic_3() {
+        // y^2 = x^3 + 2x + 1 over F_3, doubling divides by 2 but never by 3
+        let curve = CurveOverFiniteField::try_new((2, 3), (1, 3)).unwrap();
+        let points = curve.points().unwrap();
+        let order = BigInt::from(points.len());
+        for p in &points {
+            assert!((p * &order).unwrap().is_infinity());
+            for q in &points {
+                let sum = (p + q).unwrap();
+                assert!(Point::new(sum.coordinate().cloned(), curve.clone()).is_ok());
+                assert_eq!(sum, (q + p).unwrap());
+            }
+        }
+    }
+
     #[test]
     fn test_add() {
         let prime = BigInt::from(223);
@@ -463,7 +568,66 @@ mod tests {
                 Point::new(None, curve.clone()).unwrap()
             };
 
-            assert_eq!(p1 * BigInt::from(s), p2);
+            assert_eq!((p1 * BigInt::from(s)).unwrap(), p2);
         }
     }
+
+    #[test]
+    fn test_neg_sub_negative_scalar() {
+        let curve = CurveOverFiniteField::new(FieldElement::new(0, 223), FieldElement::new(7, 223));
+        let point = |x, y| {
+            Point::new(
+                Some(Coordinate::new(
+                    FieldElement::new(x, 223),
+                    FieldElement::new(y, 223),
+                )),
+                curve.clone(),
+            )
+            .unwrap()
+        };
+        let p = point(47, 71);
+        assert_eq!(-&p, point(47, 223 - 71));
+        assert_eq!(-curve.infinity(), curve.infinity());
+        assert_eq!((&p - &p).unwrap(), curve.infinity());
+        assert_eq!((&point(60, 139) - &point(117, 141)).unwrap(), p);
+        assert_eq!((&p * &BigInt::from(-1)).unwrap(), -&p);
+        assert_eq!((&p * &BigInt::from(-4)).unwrap(), point(194, 223 - 51));
+        // 21 is the order of p
+        assert_eq!(
+            (&p * &BigInt::from(-20)).unwrap(),
+            (&p * &BigInt::from(1)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_errors_instead_of_panics() {
+        let curve = CurveOverFiniteField::new(FieldElement::new(0, 223), FieldElement::new(7, 223));
+        let other = CurveOverFiniteField::new(FieldElement::new(0, 223), FieldElement::new(5, 223));
+        let p = Point::new(
+            Some(Coordinate::new(
+                FieldElement::new(47, 223),
+                FieldElement::new(71, 223),
+            )),
+            curve.clone(),
+        )
+        .unwrap();
+        assert!((&p + &other.infinity()).is_err());
+        assert!((&p - &other.infinity()).is_err());
+        assert!(Point::new(
+            Some(Coordinate::new(
+                FieldElement::new(47, 223),
+                FieldElement::new(71, 227),
+            )),
+            curve.clone(),
+        )
+        .is_err());
+        assert!(Point::new(
+            Some(Coordinate::new(
+                FieldElement::new(47, 227),
+                FieldElement::new(71, 227),
+            )),
+            curve,
+        )
+        .is_err());
+    }
 }
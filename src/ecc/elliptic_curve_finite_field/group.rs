@@ -0,0 +1,475 @@
+//! Group structure of curves over small prime fields: point enumeration, group and point
+//! orders, prime-order subgroups. Everything here is at least exponential in the size of
+//! the field, it is meant for toy curves, not for secp256k1.
+
+use super::{Coordinate, CurveOverFiniteField, Point};
+use crate::ecc::finite_field::FieldElement;
+use anyhow::{anyhow, Result};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+/// Largest field whose points are enumerated or counted one by one
+pub const MAX_ENUMERATED_PRIME: u64 = 1 << 20;
+
+/// Largest field whose group order is computed with baby-step giant-step
+pub const MAX_BSGS_PRIME: u64 = 1 << 40;
+
+/// Below this bound [`CurveOverFiniteField::order`] counts points: Mestre's argument behind
+/// the baby-step giant-step search may not pin the order down for tiny fields.
+const COUNTING_BOUND: u64 = 1 << 12;
+
+/// How many points baby-step giant-step tries before giving up
+const BSGS_ATTEMPTS: usize = 32;
+
+/// Subgroup of prime order, `order * cofactor` is the order of the whole group
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subgroup {
+    pub generator: Point,
+    pub order: BigInt,
+    pub cofactor: BigInt,
+}
+
+/// returns the prime factorization of `n` as (prime, exponent) pairs, by trial division
+pub fn factorize(n: &BigInt) -> Result<Vec<(BigInt, u32)>> {
+    let mut n = n
+        .to_u64()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| anyhow!("Cannot factor {n} by trial division"))?;
+    let mut factors = vec![];
+    let mut d = 2u64;
+    while d * d <= n {
+        let mut exponent = 0;
+        while n % d == 0 {
+            n /= d;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factors.push((BigInt::from(d), exponent));
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+    if n > 1 {
+        factors.push((BigInt::from(n), 1));
+    }
+    Ok(factors)
+}
+
+impl CurveOverFiniteField<FieldElement> {
+    fn prime(&self) -> &BigInt {
+        &self.a.prime
+    }
+
+    fn bounded_prime(&self, bound: u64) -> Result<u64> {
+        self.check_short()?;
+        self.prime()
+            .to_u64()
+            .filter(|p| *p <= bound)
+            .ok_or_else(|| anyhow!("Field F_{} is too large", self.prime()))
+    }
+
+    /// the counting and square roots below assume y^2 = x^3 + ax + b
+    fn check_short(&self) -> Result<()> {
+        if !self.is_short() {
+            return Err(anyhow!(
+                "Only curves in short Weierstrass form are supported"
+            ));
+        }
+        Ok(())
+    }
+
+    /// returns the affine points with the given x coordinate, the one with the smaller y first
+    pub fn lift_x(&self, x: &FieldElement) -> Result<Vec<Point>> {
+        self.check_short()?;
+        let Some(y) = self.rhs(x)?.sqrt() else {
+            return Ok(vec![]);
+        };
+        let mut ys = vec![y.clone()];
+        if !y.is_zero() {
+            ys.push(-y);
+        }
+        ys.sort();
+        Ok(ys
+            .into_iter()
+            .map(|y| Point {
+                coordinate: Some(Coordinate::new(x.clone(), y)),
+                curve: self.clone(),
+            })
+            .collect())
+    }
+
+    /// returns the affine points by increasing x, without building them all upfront
+    fn affine_points(&self) -> impl Iterator<Item = Point> + '_ {
+        std::iter::successors(Some(BigInt::from(0)), |x| Some(x + 1))
+            .take_while(|x| x < self.prime())
+            .flat_map(|x| {
+                self.lift_x(&FieldElement::new(x, self.prime().clone()))
+                    .expect("x belongs to the field of the curve")
+            })
+    }
+
+    /// returns every point of the curve, the point at infinity first, then by x and y
+    pub fn points(&self) -> Result<Vec<Point>> {
+        let p = self.bounded_prime(MAX_ENUMERATED_PRIME)?;
+        let (a, b) = (
+            self.a.num.to_u64().unwrap_or_default(),
+            self.b.num.to_u64().unwrap_or_default(),
+        );
+        // square roots are looked up instead of computed with Tonelli–Shanks
+        let mut roots = vec![None; p as usize];
+        for y in (0..p).rev() {
+            roots[(y * y % p) as usize] = Some(y);
+        }
+        let mut points = vec![self.infinity()];
+        for x in 0..p {
+            let rhs = ((x * x % p * x + a * x) % p + b) % p;
+            let Some(y) = roots[rhs as usize] else {
+                continue;
+            };
+            let ys = if y == 0 { vec![0] } else { vec![y, p - y] };
+            points.extend(ys.into_iter().map(|y| Point {
+                coordinate: Some(Coordinate::new(
+                    FieldElement::new(x, p),
+                    FieldElement::new(y, p),
+                )),
+                curve: self.clone(),
+            }));
+        }
+        Ok(points)
+    }
+
+    /// returns the interval [p + 1 - 2√p, p + 1 + 2√p] Hasse's theorem confines the group order to
+    pub fn hasse_interval(&self) -> (BigInt, BigInt) {
+        let p = self.prime();
+        // 2√p is irrational for a prime p, so its floor is the integer square root of 4p
+        let width = (p * BigInt::from(4)).sqrt();
+        (p + 1 - &width, p + 1 + width)
+    }
+
+    pub fn satisfies_hasse_bound(&self, order: &BigInt) -> bool {
+        let (low, high) = self.hasse_interval();
+        &low <= order && order <= &high
+    }
+
+    /// Counts the points with Legendre symbols: x contributes 1 + (x^3 + ax + b | p).
+    pub fn order_by_counting(&self) -> Result<BigInt> {
+        let p = self.bounded_prime(MAX_ENUMERATED_PRIME)?;
+        let mut affine = 0u64;
+        for x in 0..p {
+            let rhs = self.rhs(&FieldElement::new(x, p))?;
+            affine += match (rhs.is_zero(), rhs.is_square()) {
+                (true, _) => 1,
+                (false, true) => 2,
+                (false, false) => 0,
+            };
+        }
+        Ok(BigInt::from(affine + 1))
+    }
+
+    /// returns the quadratic twist y^2 = x^3 + ad^2x + bd^3 of a curve in short form for
+    /// a non-residue d, whose order is 2p + 2 minus the order of this curve
+    pub fn quadratic_twist(&self) -> CurveOverFiniteField {
+        let d = std::iter::successors(Some(BigInt::from(2)), |d| Some(d + 1))
+            .map(|d| FieldElement::new(d, self.prime().clone()))
+            .find(|d| !d.is_square())
+            .expect("half of the non-zero elements are non-residues");
+        CurveOverFiniteField::new(
+            (&self.a * &d.pow(2)).expect("same field"),
+            (&self.b * &d.pow(3)).expect("same field"),
+        )
+    }
+
+    /// returns a k in the Hasse interval with kP = O, in O(p^(1/4)) group operations
+    fn multiple_in_hasse_interval(&self, point: &Point) -> Result<BigInt> {
+        let (low, high) = self.hasse_interval();
+        let m: BigInt = (&high - &low).sqrt() + 1;
+        let steps = m.to_usize().expect("bounded by MAX_BSGS_PRIME");
+
+        // baby steps jP for j in [0, m)
+        let mut baby = HashMap::with_capacity(steps);
+        let mut current = self.infinity();
+        for j in 0..steps {
+            baby.entry(current.coordinate.clone()).or_insert(j);
+            current = (&current + point)?;
+        }
+        // giant steps (low + im)P, looking for jP = -(low + im)P
+        let giant = current;
+        let mut current = (point * &low)?;
+        for i in 0..=steps {
+            if let Some(j) = baby.get(&(-&current).coordinate) {
+                return Ok(&low + &m * i + j);
+            }
+            current = (&current + &giant)?;
+        }
+        Err(anyhow!(
+            "No multiple of the point order in the Hasse interval"
+        ))
+    }
+
+    /// Mestre's baby-step giant-step: collects the orders of points on the curve and on its
+    /// quadratic twist until a single N in the Hasse interval is a multiple of the former
+    /// while 2p + 2 - N is a multiple of the latter.
+    pub fn order_bsgs(&self) -> Result<BigInt> {
+        self.bounded_prime(MAX_BSGS_PRIME)?;
+        let (low, high) = self.hasse_interval();
+        let twist = self.quadratic_twist();
+        let twist_sum: BigInt = self.prime() * 2 + 2;
+
+        let (mut exponent, mut twist_exponent) = (BigInt::from(1), BigInt::from(1));
+        let samples = self.affine_points().zip(twist.affine_points());
+        for (point, twist_point) in samples.take(BSGS_ATTEMPTS) {
+            let multiple = self.multiple_in_hasse_interval(&point)?;
+            exponent = exponent.lcm(&point.order_dividing(&multiple)?);
+            let twist_multiple = twist.multiple_in_hasse_interval(&twist_point)?;
+            twist_exponent = twist_exponent.lcm(&twist_point.order_dividing(&twist_multiple)?);
+
+            let mut candidates =
+                std::iter::successors(Some(low.div_ceil(&exponent) * &exponent), |n| {
+                    Some(n + &exponent)
+                })
+                .take_while(|n| n <= &high)
+                .filter(|n| (&twist_sum - n).is_multiple_of(&twist_exponent));
+            if let (Some(order), None) = (candidates.next(), candidates.next()) {
+                return Ok(order);
+            }
+        }
+        Err(anyhow!("Could not determine the group order"))
+    }
+
+    /// returns the number of points, counted for tiny fields and found with
+    /// baby-step giant-step otherwise
+    pub fn order(&self) -> Result<BigInt> {
+        if self.prime() <= &BigInt::from(COUNTING_BOUND) {
+            self.order_by_counting()
+        } else {
+            self.order_bsgs()
+        }
+    }
+
+    /// returns the index of a subgroup of the given order
+    pub fn cofactor(&self, subgroup_order: &BigInt) -> Result<BigInt> {
+        let order = self.order()?;
+        let (cofactor, remainder) = order.div_rem(subgroup_order);
+        if remainder != BigInt::from(0) {
+            return Err(anyhow!(
+                "{subgroup_order} does not divide the group order {order}"
+            ));
+        }
+        Ok(cofactor)
+    }
+
+    /// returns a generator of a subgroup for each prime dividing the group order
+    pub fn prime_order_subgroups(&self) -> Result<Vec<Subgroup>> {
+        let order = self.order()?;
+        factorize(&order)?
+            .into_iter()
+            .map(|(prime, exponent)| {
+                // (N / q^e)P has order q^i for some i, q^(i-1) times it has order q
+                let q_part = prime.pow(exponent);
+                let mut generator = None;
+                for point in self.affine_points() {
+                    let mut candidate = (&point * &(&order / &q_part))?;
+                    if candidate.is_infinity() {
+                        continue;
+                    }
+                    loop {
+                        let next = (&candidate * &prime)?;
+                        if next.is_infinity() {
+                            break;
+                        }
+                        candidate = next;
+                    }
+                    generator = Some(candidate);
+                    break;
+                }
+                let generator = generator.ok_or_else(|| anyhow!("No point of order {prime}"))?;
+                let cofactor = &order / &prime;
+                Ok(Subgroup {
+                    generator,
+                    order: prime,
+                    cofactor,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Point<FieldElement> {
+    /// returns the order of the point given a multiple of it, such as the group order
+    pub fn order_dividing(&self, multiple: &BigInt) -> Result<BigInt> {
+        if !(self * multiple)?.is_infinity() {
+            return Err(anyhow!("{multiple} is not a multiple of the point order"));
+        }
+        let mut order = multiple.clone();
+        for (prime, _) in factorize(multiple)? {
+            while order.is_multiple_of(&prime) {
+                let smaller = &order / &prime;
+                if !(self * &smaller)?.is_infinity() {
+                    break;
+                }
+                order = smaller;
+            }
+        }
+        Ok(order)
+    }
+
+    /// returns the smallest n > 0 with nP = O
+    pub fn order(&self) -> Result<BigInt> {
+        self.order_dividing(&self.curve.order()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f223_curve() -> CurveOverFiniteField {
+        CurveOverFiniteField::new(FieldElement::new(0, 223), FieldElement::new(7, 223))
+    }
+
+    fn point(curve: &CurveOverFiniteField, x: i64, y: i64) -> Point {
+        let prime = curve.prime().clone();
+        Point::new(
+            Some(Coordinate::new(
+                FieldElement::new(x, prime.clone()),
+                FieldElement::new(y, prime),
+            )),
+            curve.clone(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_factorize() {
+        assert_eq!(
+            factorize(&BigInt::from(252)).unwrap(),
+            vec![
+                (BigInt::from(2), 2),
+                (BigInt::from(3), 2),
+                (BigInt::from(7), 1)
+            ]
+        );
+        assert_eq!(factorize(&BigInt::from(1)).unwrap(), vec![]);
+        assert_eq!(
+            factorize(&BigInt::from(1_000_003)).unwrap(),
+            vec![(BigInt::from(1_000_003), 1)]
+        );
+        assert!(factorize(&BigInt::from(0)).is_err());
+    }
+
+    #[test]
+    fn test_points() {
+        let curve = f223_curve();
+        let points = curve.points().unwrap();
+        assert_eq!(points.len(), 252);
+        assert_eq!(points[0], curve.infinity());
+        assert!(points.contains(&point(&curve, 47, 71)));
+        assert!(points
+            .iter()
+            .skip(1)
+            .all(|p| Point::new(p.coordinate.clone(), curve.clone()).is_ok()));
+        assert_eq!(
+            curve.affine_points().count(),
+            251,
+            "lifting x agrees with the lookup table"
+        );
+        assert_eq!(curve.lift_x(&FieldElement::new(47, 223)).unwrap().len(), 2);
+        assert!(curve.lift_x(&FieldElement::new(4, 223)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_group_order() {
+        let curve = f223_curve();
+        assert_eq!(curve.order_by_counting().unwrap(), BigInt::from(252));
+        assert_eq!(curve.order_bsgs().unwrap(), BigInt::from(252));
+        assert!(curve.satisfies_hasse_bound(&BigInt::from(252)));
+        assert_eq!(
+            curve.quadratic_twist().order_by_counting().unwrap(),
+            BigInt::from(2 * 223 + 2 - 252)
+        );
+        assert_eq!(
+            curve.hasse_interval(),
+            (BigInt::from(195), BigInt::from(253))
+        );
+
+        for (a, b) in [(2, 3), (1, 1), (-3, 5), (0, 7)] {
+            let curve = CurveOverFiniteField::try_new(
+                FieldElement::new(a, 10007),
+                FieldElement::new(b, 10007),
+            )
+            .unwrap();
+            let order = curve.order_by_counting().unwrap();
+            assert_eq!(curve.order_bsgs().unwrap(), order, "a = {a}, b = {b}");
+            assert_eq!(
+                BigInt::from(curve.points().unwrap().len()),
+                order,
+                "a = {a}, b = {b}"
+            );
+            assert!(curve.satisfies_hasse_bound(&order));
+        }
+    }
+
+    #[test]
+    fn test_bsgs_large_field() {
+        let prime: BigInt = BigInt::from(2).pow(31) - 1;
+        let curve = CurveOverFiniteField::try_new(
+            FieldElement::new(2, prime.clone()),
+            FieldElement::new(3, prime.clone()),
+        )
+        .unwrap();
+        let order = curve.order().unwrap();
+        assert!(curve.satisfies_hasse_bound(&order));
+        for point in curve.affine_points().take(5) {
+            assert_eq!((&point * &order).unwrap().coordinate, None);
+        }
+        assert!(curve.points().is_err());
+    }
+
+    #[test]
+    fn test_point_order() {
+        let curve = f223_curve();
+        assert_eq!(point(&curve, 15, 86).order().unwrap(), BigInt::from(7));
+        assert_eq!(point(&curve, 47, 71).order().unwrap(), BigInt::from(21));
+        assert_eq!(curve.infinity().order().unwrap(), BigInt::from(1));
+        assert!(point(&curve, 47, 71)
+            .order_dividing(&BigInt::from(20))
+            .is_err());
+
+        let orders = curve
+            .points()
+            .unwrap()
+            .iter()
+            .map(|p| p.order_dividing(&BigInt::from(252)).unwrap())
+            .collect::<Vec<_>>();
+        assert!(orders
+            .iter()
+            .all(|order| BigInt::from(252).is_multiple_of(order)));
+        // the group is Z/6 x Z/42, not cyclic
+        assert_eq!(orders.iter().max(), Some(&BigInt::from(42)));
+        assert_eq!(
+            orders.iter().filter(|o| **o == BigInt::from(42)).count(),
+            144
+        );
+    }
+
+    #[test]
+    fn test_subgroups() {
+        let curve = f223_curve();
+        assert_eq!(curve.cofactor(&BigInt::from(7)).unwrap(), BigInt::from(36));
+        assert!(curve.cofactor(&BigInt::from(5)).is_err());
+
+        let subgroups = curve.prime_order_subgroups().unwrap();
+        assert_eq!(
+            subgroups
+                .iter()
+                .map(|s| s.order.clone())
+                .collect::<Vec<_>>(),
+            vec![BigInt::from(2), BigInt::from(3), BigInt::from(7)]
+        );
+        for subgroup in subgroups {
+            assert_eq!(subgroup.generator.order().unwrap(), subgroup.order);
+            assert_eq!(&subgroup.order * &subgroup.cofactor, BigInt::from(252));
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use crate::ecc::finite_field::{is_prime, FiniteField};
+use crate::ecc::polynomial::Polynomial;
+use anyhow::anyhow;
+use num_bigint::{BigInt, Sign};
+use num_traits::Signed;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+type Result<T> = std::result::Result<T, anyhow::Error>;
+
+/// Element of F_{p^k}, represented as a polynomial over F_p reduced modulo
+/// an irreducible polynomial of degree k.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionFieldElement {
+    value: Polynomial,
+    modulus: Polynomial,
+}
+
+impl ExtensionFieldElement {
+    /// Creates an element without checking that the modulus is irreducible.
+    /// Meant for trusted constants, use [`ExtensionFieldElement::try_new`] for anything else.
+    pub fn new(value: Polynomial, modulus: Polynomial) -> Result<Self> {
+        if !matches!(modulus.degree(), Some(degree) if degree > 0) {
+            return Err(anyhow!("Polynomial modulus must not be constant"));
+        }
+        let (_, value) = value.div_rem(&modulus)?;
+        Ok(Self { value, modulus })
+    }
+
+    /// Creates an element, rejecting non-prime base fields and reducible moduli.
+    pub fn try_new(value: Polynomial, modulus: Polynomial) -> Result<Self> {
+        if !is_prime(modulus.prime()) {
+            return Err(anyhow!("Modulus {} is not a prime number", modulus.prime()));
+        }
+        if !modulus.is_irreducible() {
+            return Err(anyhow!("Polynomial modulus is not irreducible"));
+        }
+        Self::new(value, modulus)
+    }
+
+    pub fn value(&self) -> &Polynomial {
+        &self.value
+    }
+
+    pub fn modulus(&self) -> &Polynomial {
+        &self.modulus
+    }
+
+    /// returns p^k, the number of elements in the field
+    pub fn order(&self) -> BigInt {
+        let degree = self.modulus.degree().expect("the modulus is not constant");
+        self.modulus.prime().pow(degree as u32)
+    }
+
+    pub fn pow(&self, rhs: impl Into<BigInt>) -> Result<Self> {
+        let exponent = rhs.into();
+        // a^-k = (a^-1)^k with a^-1 = a^(q-2), zero has no inverse so its negative powers stay zero
+        let base = if exponent.sign() == Sign::Minus && !self.value.is_zero() {
+            self.value.pow_mod(&(self.order() - 2), &self.modulus)?
+        } else {
+            self.value.clone()
+        };
+        Ok(Self {
+            value: base.pow_mod(&exponent.abs(), &self.modulus)?,
+            modulus: self.modulus.clone(),
+        })
+    }
+
+    pub fn inverse(&self) -> Result<Self> {
+        if self.value.is_zero() {
+            return Err(anyhow!("Zero has no multiplicative inverse"));
+        }
+        self.pow(-1)
+    }
+
+    fn check_field(&self, rhs: &ExtensionFieldElement) -> Result<()> {
+        if self.modulus != rhs.modulus {
+            Err(anyhow!("Cannot operate on numbers in different Fields"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl FiniteField for ExtensionFieldElement {
+    fn constant(&self, n: impl Into<BigInt>) -> Self {
+        Self {
+            value: Polynomial::new([n], self.modulus.prime().clone()),
+            modulus: self.modulus.clone(),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    fn same_field(&self, other: &Self) -> bool {
+        self.modulus == other.modulus
+    }
+
+    fn characteristic(&self) -> BigInt {
+        self.modulus.prime().clone()
+    }
+}
+
+impl Add<&ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn add(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        self.check_field(rhs)?;
+        Ok(ExtensionFieldElement {
+            value: (&self.value + &rhs.value)?,
+            modulus: self.modulus.clone(),
+        })
+    }
+}
+
+impl Add for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl Add<&ExtensionFieldElement> for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn add(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        &self + rhs
+    }
+}
+
+impl Sub<&ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn sub(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        self.check_field(rhs)?;
+        Ok(ExtensionFieldElement {
+            value: (&self.value - &rhs.value)?,
+            modulus: self.modulus.clone(),
+        })
+    }
+}
+
+impl Sub for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl Sub<&ExtensionFieldElement> for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn sub(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        &self - rhs
+    }
+}
+
+impl Mul<&ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn mul(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        self.check_field(rhs)?;
+        let (_, value) = (&self.value * &rhs.value)?.div_rem(&self.modulus)?;
+        Ok(ExtensionFieldElement {
+            value,
+            modulus: self.modulus.clone(),
+        })
+    }
+}
+
+impl Mul for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl Mul<&ExtensionFieldElement> for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn mul(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        &self * rhs
+    }
+}
+
+impl Div<&ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn div(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        self.check_field(rhs)?;
+        self.mul(&rhs.inverse()?)
+    }
+}
+
+impl Div for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        &self / &rhs
+    }
+}
+
+impl Div<&ExtensionFieldElement> for ExtensionFieldElement {
+    type Output = Result<ExtensionFieldElement>;
+
+    fn div(self, rhs: &ExtensionFieldElement) -> Self::Output {
+        &self / rhs
+    }
+}
+
+impl Neg for &ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn neg(self) -> Self::Output {
+        (&self.constant(0) - self).expect("same field")
+    }
+}
+
+impl Neg for ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::elliptic_curve_finite_field::{Coordinate, CurveOverFiniteField, Point};
+
+    // F_223^2 = F_223[i] / (i^2 + 1)
+    fn gaussian(re: i64, im: i64) -> ExtensionFieldElement {
+        ExtensionFieldElement::new(
+            Polynomial::new([re, im], 223),
+            Polynomial::new([1, 0, 1], 223),
+        )
+        .unwrap()
+    }
+
+    // F_2^8 with the AES polynomial x^8 + x^4 + x^3 + x + 1
+    fn gf256(byte: u8) -> ExtensionFieldElement {
+        let bits = (0..8).map(|i| (byte >> i) & 1);
+        ExtensionFieldElement::new(
+            Polynomial::new(bits, 2),
+            Polynomial::new([1, 1, 0, 1, 1, 0, 0, 0, 1], 2),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(ExtensionFieldElement::try_new(
+            Polynomial::new([1], 7),
+            Polynomial::new([1, 0, 1], 7)
+        )
+        .is_ok());
+        assert!(ExtensionFieldElement::try_new(
+            Polynomial::new([1], 5),
+            Polynomial::new([1, 0, 1], 5)
+        )
+        .is_err());
+        assert!(ExtensionFieldElement::try_new(
+            Polynomial::new([1], 9),
+            Polynomial::new([1, 0, 1], 9)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = gaussian(3, 5);
+        let b = gaussian(10, 220);
+        assert_eq!((&a + &b).unwrap(), gaussian(13, 2));
+        assert_eq!((&a - &b).unwrap(), gaussian(216, 8));
+        // (3 + 5i)(10 - 3i) = 30 + 15 + (50 - 9)i
+        assert_eq!((&a * &b).unwrap(), gaussian(45, 41));
+        assert_eq!(a.inverse().unwrap(), gaussian(46, 72));
+        assert_eq!(((&a / &b).unwrap() * &b).unwrap(), a);
+        assert_eq!(gaussian(0, 1).pow(2).unwrap(), gaussian(-1, 0));
+        assert_eq!(a.pow(a.order() - 1).unwrap(), gaussian(1, 0));
+        assert!(gaussian(0, 0).inverse().is_err());
+        assert_eq!(-&a, gaussian(-3, -5));
+        assert_eq!((-a.clone() + &a).unwrap(), gaussian(0, 0));
+        assert!((a + gf256(1)).is_err());
+    }
+
+    #[test]
+    fn test_pow_of_zero() {
+        let zero = gaussian(0, 0);
+        let q_minus_1: BigInt = zero.order() - 1;
+        assert_eq!(zero.pow(q_minus_1.clone()).unwrap(), zero);
+        assert_eq!(zero.pow(q_minus_1 * 2).unwrap(), zero);
+        assert_eq!(zero.pow(-1).unwrap(), zero);
+        assert_eq!(zero.pow(0).unwrap(), gaussian(1, 0));
+        assert_eq!(gf256(0).pow(255).unwrap(), gf256(0));
+        assert_eq!(gf256(0x53).pow(-2).unwrap(), gf256(0xca).pow(2).unwrap());
+    }
+
+    #[test]
+    fn test_constant_modulus() {
+        for modulus in [Polynomial::new([3], 7), Polynomial::zero(7)] {
+            assert!(ExtensionFieldElement::new(Polynomial::new([1], 7), modulus.clone()).is_err());
+            assert!(ExtensionFieldElement::try_new(Polynomial::new([1], 7), modulus).is_err());
+        }
+    }
+
+    #[test]
+    fn test_binary_field() {
+        assert_eq!(gf256(0x53).order(), BigInt::from(256));
+        assert_eq!((gf256(0x53) + gf256(0xca)).unwrap(), gf256(0x99));
+        assert_eq!((gf256(0x53) * gf256(0xca)).unwrap(), gf256(0x01));
+        assert_eq!((gf256(0x57) * gf256(0x83)).unwrap(), gf256(0xc1));
+        assert_eq!(gf256(0x53).inverse().unwrap(), gf256(0xca));
+    }
+
+    #[test]
+    fn test_binary_curve() {
+        // F_2^4 = F_2[g] / (g^4 + g + 1), y^2 + xy = x^3 + g^4x^2 + 1
+        let modulus = Polynomial::new([1, 1, 0, 0, 1], 2);
+        let g = ExtensionFieldElement::try_new(Polynomial::new([0, 1], 2), modulus).unwrap();
+        let power = |k: i64| g.pow(k).unwrap();
+        let (zero, one) = (g.constant(0), g.constant(1));
+        assert!(
+            CurveOverFiniteField::<ExtensionFieldElement>::try_new(power(4), one.clone()).is_err()
+        );
+        assert!(CurveOverFiniteField::try_weierstrass(
+            one.clone(),
+            power(4),
+            zero.clone(),
+            zero.clone(),
+            zero.clone()
+        )
+        .is_err());
+        let curve = CurveOverFiniteField::try_weierstrass(
+            one.clone(),
+            power(4),
+            zero.clone(),
+            zero.clone(),
+            one.clone(),
+        )
+        .unwrap();
+
+        let elements: Vec<_> = std::iter::once(zero.clone())
+            .chain((0..15).map(power))
+            .collect();
+        let mut points = vec![curve.infinity()];
+        for x in &elements {
+            for y in &elements {
+                if let Ok(p) =
+                    Point::new(Some(Coordinate::new(x.clone(), y.clone())), curve.clone())
+                {
+                    points.push(p);
+                }
+            }
+        }
+        let expected = [
+            (0, 13),
+            (3, 13),
+            (5, 11),
+            (6, 14),
+            (9, 13),
+            (10, 8),
+            (12, 12),
+            (0, 6),
+            (3, 8),
+            (5, 3),
+            (6, 8),
+            (9, 10),
+            (10, 1),
+        ];
+        assert_eq!(points.len(), 16);
+        for (x, y) in expected {
+            assert!(Point::new(Some(Coordinate::new(power(x), power(y))), curve.clone()).is_ok());
+        }
+        assert!(Point::new(
+            Some(Coordinate::new(power(12), zero.clone())),
+            curve.clone()
+        )
+        .is_ok());
+        // (0, 1) is the point of order 2, since -(x, y) = (x, x + y) here
+        let two_torsion = Point::new(Some(Coordinate::new(zero, one)), curve.clone()).unwrap();
+        assert_eq!(-&two_torsion, two_torsion);
+        assert!((&two_torsion + &two_torsion).unwrap().is_infinity());
+
+        let order = BigInt::from(16);
+        for p in &points {
+            assert!((p * &order).unwrap().is_infinity());
+            assert!((p + &-p).unwrap().is_infinity());
+            for q in &points {
+                let sum = (p + q).unwrap();
+                assert!(Point::new(sum.coordinate().cloned(), curve.clone()).is_ok());
+                assert_eq!(sum, (q + p).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_curve_over_extension_field() {
+        let curve = CurveOverFiniteField::try_new(gaussian(0, 0), gaussian(7, 0)).unwrap();
+        let point = |x, y| Point::new(Some(Coordinate::new(x, y)), curve.clone());
+
+        // points with coordinates in the base field behave as over F_223
+        let p = point(gaussian(47, 0), gaussian(71, 0)).unwrap();
+        assert_eq!(
+            (&p + &p).unwrap(),
+            point(gaussian(36, 0), gaussian(111, 0)).unwrap()
+        );
+
+        let q = point(gaussian(0, 1), gaussian(34, 141)).unwrap();
+        let doubled = (&q + &q).unwrap();
+        assert_eq!(
+            doubled,
+            point(gaussian(133, 17), gaussian(164, 88)).unwrap()
+        );
+        assert_eq!((&doubled + &q).unwrap(), (&q + &doubled).unwrap());
+        assert!(point(gaussian(0, 1), gaussian(34, 140)).is_err());
+    }
+}
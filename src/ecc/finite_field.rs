@@ -1,11 +1,65 @@
 use anyhow::anyhow;
-use num_bigint::BigInt;
-use num_integer::Integer;
-use std::ops::{Add, Div, Mul, Sub};
+use num_bigint::{BigInt, Sign};
+use num_integer::{Integer, Roots};
+use num_primes::{BigUint, Verification};
+use num_traits::Signed;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::iter::{Product, Sum};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 type Result<T> = std::result::Result<T, anyhow::Error>;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Below this bound primality is decided by trial division: `num-primes` mishandles
+/// very small candidates (it rejects 2 and its Fermat round may pick a zero witness).
+const TRIAL_DIVISION_BOUND: u64 = 1 << 20;
+
+/// returns true if `n` is a prime number
+pub fn is_prime(n: &BigInt) -> bool {
+    if n < &BigInt::from(2) {
+        return false;
+    }
+    if n < &BigInt::from(TRIAL_DIVISION_BOUND) {
+        let n = u64::try_from(n).expect("bounded by TRIAL_DIVISION_BOUND");
+        return (2..=n.sqrt()).all(|d| n % d != 0);
+    }
+    let (_, bytes) = n.to_bytes_be();
+    Verification::is_prime(&BigUint::from_bytes_be(&bytes))
+}
+
+/// Arithmetic the elliptic curve code needs from the field its coordinates belong to.
+pub trait FiniteField:
+    Clone
+    + Debug
+    + Eq
+    + Add<Output = Result<Self>>
+    + for<'a> Add<&'a Self, Output = Result<Self>>
+    + Sub<Output = Result<Self>>
+    + for<'a> Sub<&'a Self, Output = Result<Self>>
+    + Mul<Output = Result<Self>>
+    + for<'a> Mul<&'a Self, Output = Result<Self>>
+    + Div<Output = Result<Self>>
+    + for<'a> Div<&'a Self, Output = Result<Self>>
+    + Neg<Output = Self>
+{
+    /// returns the integer `n` as an element of the same field as `self`
+    fn constant(&self, n: impl Into<BigInt>) -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    /// returns true if `other` belongs to the same field as `self`
+    fn same_field(&self, other: &Self) -> bool;
+
+    fn characteristic(&self) -> BigInt;
+}
+
+/// Element of the prime field F_prime.
+///
+/// Binary operators return `Result` because both operands must belong to the same field,
+/// compound assignments (`+=`, ...) panic instead. `num_traits::Zero`/`One` cannot be
+/// implemented since the modulus is only known at runtime, use [`FieldElement::zero`]
+/// and [`FieldElement::one`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FieldElement {
     pub num: BigInt,
     pub prime: BigInt,
@@ -18,25 +72,159 @@ impl<N: Into<BigInt>, P: Into<BigInt>> From<(N, P)> for FieldElement {
 }
 
 impl FieldElement {
+    /// Creates an element without validating the modulus.
+    /// Meant for trusted constants, use [`FieldElement::try_new`] for anything else.
+    /// A non-positive modulus is kept unreduced so that checked constructors can reject it.
     pub fn new(num: impl Into<BigInt>, prime: impl Into<BigInt>) -> Self {
+        let (num, prime) = (num.into(), prime.into());
+        if prime.sign() != Sign::Plus {
+            return Self { num, prime };
+        }
+        Self {
+            num: num.mod_floor(&prime),
+            prime,
+        }
+    }
+
+    /// Creates an element, rejecting moduli that are not prime.
+    pub fn try_new(num: impl Into<BigInt>, prime: impl Into<BigInt>) -> Result<Self> {
         let prime = prime.into();
-        let (num, prime) = (num.into() % &prime, prime);
-        Self { num, prime }
+        if prime.sign() != Sign::Plus || !is_prime(&prime) {
+            return Err(anyhow!("Modulus {prime} is not a prime number"));
+        }
+        Ok(Self::new(num, prime))
+    }
+
+    pub fn zero(prime: impl Into<BigInt>) -> Self {
+        Self::new(0, prime)
+    }
+
+    pub fn one(prime: impl Into<BigInt>) -> Self {
+        Self::new(1, prime)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == BigInt::from(0)
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.num == BigInt::from(1)
     }
 
     pub fn pow(&self, rhs: impl Into<BigInt>) -> Self {
         let exponent = rhs.into();
-        let exponent = if exponent.lt(&0i32.into()) {
-            BigInt::from(-1i64).add(&self.prime).add(&exponent)
+        // a^-k = (a^-1)^k with a^-1 = a^(p-2), zero has no inverse so its negative powers stay zero
+        let base = if exponent.sign() == Sign::Minus && !self.is_zero() {
+            self.num.modpow(&(&self.prime - 2), &self.prime)
         } else {
-            exponent
+            self.num.clone()
         };
 
         Self {
-            num: self.num.modpow(&exponent, &self.prime),
+            num: base.modpow(&exponent.abs(), &self.prime),
             prime: self.prime.clone(),
         }
     }
+
+    /// returns the multiplicative inverse, zero has none
+    pub fn inverse(&self) -> Result<Self> {
+        if self.is_zero() {
+            Err(anyhow!("Zero has no multiplicative inverse"))
+        } else {
+            Ok(self.pow(-1))
+        }
+    }
+
+    /// returns true if the element is a quadratic residue, zero included (Euler's criterion)
+    pub fn is_square(&self) -> bool {
+        if self.is_zero() || self.prime == BigInt::from(2) {
+            return true;
+        }
+        let exponent = (&self.prime - 1) / 2;
+        self.num.modpow(&exponent, &self.prime) == BigInt::from(1)
+    }
+
+    /// returns a square root with Tonelli–Shanks, or None for non-residues
+    pub fn sqrt(&self) -> Option<Self> {
+        if !self.is_square() {
+            return None;
+        }
+        if self.is_zero() || self.prime == BigInt::from(2) {
+            return Some(self.clone());
+        }
+        if self.prime.mod_floor(&BigInt::from(4)) == BigInt::from(3) {
+            return Some(self.pow((&self.prime + 1) / 4));
+        }
+        // p - 1 = q * 2^s with q odd
+        let mut q: BigInt = &self.prime - 1;
+        let mut s = 0u32;
+        while q.is_even() {
+            q /= 2;
+            s += 1;
+        }
+        let non_residue = (2..)
+            .map(|z| Self::new(z, self.prime.clone()))
+            .find(|z| !z.is_square())
+            .expect("half of the non-zero elements are non-residues");
+        let mut m = s;
+        let mut c = non_residue.pow(q.clone());
+        let mut t = self.pow(q.clone());
+        let mut r = self.pow((q + 1) / 2);
+        while !t.is_one() {
+            let mut i = 0;
+            let mut t_pow = t.clone();
+            while !t_pow.is_one() {
+                t_pow = t_pow.pow(2);
+                i += 1;
+            }
+            let b = c.pow(BigInt::from(2).pow(m - i - 1));
+            m = i;
+            c = b.pow(2);
+            t = (t * &c).expect("same field");
+            r = (r * b).expect("same field");
+        }
+        Some(r)
+    }
+}
+
+impl Display for FieldElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FieldElement_{}({})", self.prime, self.num)
+    }
+}
+
+impl PartialOrd for FieldElement {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders by field, then by the canonical representative in [0, prime).
+/// Field elements have no order compatible with arithmetic, this one is for sorting and maps.
+impl Ord for FieldElement {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.prime
+            .cmp(&other.prime)
+            .then_with(|| self.num.cmp(&other.num))
+    }
+}
+
+impl FiniteField for FieldElement {
+    fn constant(&self, n: impl Into<BigInt>) -> Self {
+        Self::new(n, self.prime.clone())
+    }
+
+    fn is_zero(&self) -> bool {
+        FieldElement::is_zero(self)
+    }
+
+    fn same_field(&self, other: &Self) -> bool {
+        self.prime == other.prime
+    }
+
+    fn characteristic(&self) -> BigInt {
+        self.prime.clone()
+    }
 }
 
 impl Add for FieldElement {
@@ -150,6 +338,8 @@ impl Div for FieldElement {
     fn div(self, rhs: Self) -> Self::Output {
         if self.prime != rhs.prime {
             Err(anyhow!("Cannot add two numbers in different Fields"))
+        } else if rhs.is_zero() {
+            Err(anyhow!("Division by zero"))
         } else {
             let num = (self.num * rhs.num.modpow(&self.prime.sub(2), &rhs.prime)) % &rhs.prime;
             Ok(Self {
@@ -166,6 +356,8 @@ impl Div<&FieldElement> for FieldElement {
     fn div(self, rhs: &FieldElement) -> Self::Output {
         if self.prime != rhs.prime {
             Err(anyhow!("Cannot add two numbers in different Fields"))
+        } else if rhs.is_zero() {
+            Err(anyhow!("Division by zero"))
         } else {
             let num = (&self.num
                 * &(rhs.num).modpow(&(&self.prime).sub(&BigInt::from(2)), &rhs.prime))
@@ -184,6 +376,8 @@ impl Div<&FieldElement> for &FieldElement {
     fn div(self, rhs: &FieldElement) -> Self::Output {
         if self.prime != rhs.prime {
             Err(anyhow!("Cannot add two numbers in different Fields"))
+        } else if rhs.is_zero() {
+            Err(anyhow!("Division by zero"))
         } else {
             let num = (&self.num
                 * &(rhs.num).modpow(&(&self.prime).sub(&BigInt::from(2)), &rhs.prime))
@@ -241,6 +435,174 @@ impl Mul<&FieldElement> for &FieldElement {
     }
 }
 
+impl Add<FieldElement> for &FieldElement {
+    type Output = Result<FieldElement>;
+
+    fn add(self, rhs: FieldElement) -> Self::Output {
+        self + &rhs
+    }
+}
+
+impl Sub<FieldElement> for &FieldElement {
+    type Output = Result<FieldElement>;
+
+    fn sub(self, rhs: FieldElement) -> Self::Output {
+        self - &rhs
+    }
+}
+
+impl Mul<FieldElement> for &FieldElement {
+    type Output = Result<FieldElement>;
+
+    fn mul(self, rhs: FieldElement) -> Self::Output {
+        self * &rhs
+    }
+}
+
+impl Div<FieldElement> for &FieldElement {
+    type Output = Result<FieldElement>;
+
+    fn div(self, rhs: FieldElement) -> Self::Output {
+        self / &rhs
+    }
+}
+
+impl Neg for FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> Self::Output {
+        (&self).neg()
+    }
+}
+
+impl Neg for &FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> Self::Output {
+        FieldElement::new(-&self.num, self.prime.clone())
+    }
+}
+
+/// # Panics
+/// if both numbers are not in the same field
+impl AddAssign<&FieldElement> for FieldElement {
+    fn add_assign(&mut self, rhs: &FieldElement) {
+        *self = (&*self + rhs).expect("Cannot add two numbers in different Fields");
+    }
+}
+
+impl AddAssign for FieldElement {
+    fn add_assign(&mut self, rhs: FieldElement) {
+        *self += &rhs;
+    }
+}
+
+/// # Panics
+/// if both numbers are not in the same field
+impl SubAssign<&FieldElement> for FieldElement {
+    fn sub_assign(&mut self, rhs: &FieldElement) {
+        *self = (&*self - rhs).expect("Cannot subtract two numbers in different Fields");
+    }
+}
+
+impl SubAssign for FieldElement {
+    fn sub_assign(&mut self, rhs: FieldElement) {
+        *self -= &rhs;
+    }
+}
+
+/// # Panics
+/// if both numbers are not in the same field
+impl MulAssign<&FieldElement> for FieldElement {
+    fn mul_assign(&mut self, rhs: &FieldElement) {
+        *self = (&*self * rhs).expect("Cannot multiply two numbers in different Fields");
+    }
+}
+
+impl MulAssign for FieldElement {
+    fn mul_assign(&mut self, rhs: FieldElement) {
+        *self *= &rhs;
+    }
+}
+
+/// # Panics
+/// if both numbers are not in the same field or rhs is zero
+impl DivAssign<&FieldElement> for FieldElement {
+    fn div_assign(&mut self, rhs: &FieldElement) {
+        *self = (&*self / rhs).expect("Cannot divide these numbers");
+    }
+}
+
+impl DivAssign for FieldElement {
+    fn div_assign(&mut self, rhs: FieldElement) {
+        *self /= &rhs;
+    }
+}
+
+impl num_traits::Inv for FieldElement {
+    type Output = Result<FieldElement>;
+
+    fn inv(self) -> Self::Output {
+        self.inverse()
+    }
+}
+
+impl num_traits::Inv for &FieldElement {
+    type Output = Result<FieldElement>;
+
+    fn inv(self) -> Self::Output {
+        self.inverse()
+    }
+}
+
+impl<E: Into<BigInt>> num_traits::Pow<E> for FieldElement {
+    type Output = FieldElement;
+
+    fn pow(self, rhs: E) -> Self::Output {
+        FieldElement::pow(&self, rhs)
+    }
+}
+
+impl<E: Into<BigInt>> num_traits::Pow<E> for &FieldElement {
+    type Output = FieldElement;
+
+    fn pow(self, rhs: E) -> Self::Output {
+        FieldElement::pow(self, rhs)
+    }
+}
+
+/// Sums numbers of the same field, an empty iterator has no field to sum in and fails.
+impl Sum<FieldElement> for Result<FieldElement> {
+    fn sum<I: Iterator<Item = FieldElement>>(mut iter: I) -> Self {
+        let first = iter
+            .next()
+            .ok_or_else(|| anyhow!("Cannot sum an empty sequence"))?;
+        iter.try_fold(first, |acc, v| acc + v)
+    }
+}
+
+impl<'a> Sum<&'a FieldElement> for Result<FieldElement> {
+    fn sum<I: Iterator<Item = &'a FieldElement>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+/// Multiplies numbers of the same field, an empty iterator has no field to multiply in and fails.
+impl Product<FieldElement> for Result<FieldElement> {
+    fn product<I: Iterator<Item = FieldElement>>(mut iter: I) -> Self {
+        let first = iter
+            .next()
+            .ok_or_else(|| anyhow!("Cannot multiply an empty sequence"))?;
+        iter.try_fold(first, |acc, v| acc * v)
+    }
+}
+
+impl<'a> Product<&'a FieldElement> for Result<FieldElement> {
+    fn product<I: Iterator<Item = &'a FieldElement>>(iter: I) -> Self {
+        iter.cloned().product()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,6 +617,29 @@ mod tests {
         assert_ne!(a, c);
     }
 
+    #[test]
+    fn test_try_new() {
+        assert!(FieldElement::try_new(5u64, 31u64).is_ok());
+        assert!(FieldElement::try_new(5u64, 2u64).is_ok());
+        assert!(FieldElement::try_new(5u64, 223u64).is_ok());
+        assert!(FieldElement::try_new(5u64, 0u64).is_err());
+        assert!(FieldElement::try_new(5u64, 1u64).is_err());
+        assert!(FieldElement::try_new(5u64, -31i64).is_err());
+        assert!(FieldElement::try_new(5u64, 221u64).is_err());
+
+        let p: BigInt = BigInt::from(2).pow(256) - BigInt::from(2).pow(32) - 977;
+        assert!(FieldElement::try_new(5u64, p.clone()).is_ok());
+        assert!(FieldElement::try_new(5u64, p * 3).is_err());
+    }
+
+    #[test]
+    fn test_new_reduces_to_canonical_representative() {
+        assert_eq!(FieldElement::new(-1, 31), FieldElement::new(30, 31));
+        assert_eq!(FieldElement::new(-32, 31).num, BigInt::from(30));
+        // a zero modulus is left for the checked constructors to reject instead of panicking
+        assert_eq!(FieldElement::new(5, 0).num, BigInt::from(5));
+    }
+
     #[test]
     fn test_add() {
         let a = FieldElement::new(2u64, 31u64);
@@ -332,4 +717,151 @@ mod tests {
             FieldElement::new(13u64, 31u64),
         )
     }
+
+    #[test]
+    fn test_div_by_zero() {
+        let a = FieldElement::new(2u64, 19u64);
+        assert!((&a / &FieldElement::zero(19u64)).is_err());
+        assert!(FieldElement::zero(19u64).inverse().is_err());
+        assert_eq!(a.inverse().unwrap(), FieldElement::new(10u64, 19u64));
+        assert_eq!(
+            num_traits::Inv::inv(a.clone()).unwrap(),
+            FieldElement::new(10u64, 19u64)
+        );
+    }
+
+    #[test]
+    fn test_neg() {
+        let a = FieldElement::new(2u64, 19u64);
+        assert_eq!(-&a, FieldElement::new(17u64, 19u64));
+        assert_eq!((&a + -a.clone()).unwrap(), FieldElement::zero(19u64));
+        assert_eq!(-FieldElement::zero(19u64), FieldElement::zero(19u64));
+    }
+
+    #[test]
+    fn test_assign() {
+        let mut a = FieldElement::new(17u64, 31u64);
+        a += FieldElement::new(21u64, 31u64);
+        assert_eq!(a, FieldElement::new(7u64, 31u64));
+        a -= &FieldElement::new(8u64, 31u64);
+        assert_eq!(a, FieldElement::new(30u64, 31u64));
+        a *= FieldElement::new(2u64, 31u64);
+        assert_eq!(a, FieldElement::new(29u64, 31u64));
+        a /= &FieldElement::new(29u64, 31u64);
+        assert!(a.is_one());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_assign_different_fields() {
+        let mut a = FieldElement::new(17u64, 31u64);
+        a += FieldElement::new(17u64, 37u64);
+    }
+
+    #[test]
+    fn test_mixed_references() {
+        let a = FieldElement::new(24u64, 31u64);
+        let b = FieldElement::new(19u64, 31u64);
+        assert_eq!((&a * b.clone()).unwrap(), FieldElement::new(22u64, 31u64));
+        assert_eq!((&a + b.clone()).unwrap(), FieldElement::new(12u64, 31u64));
+        assert_eq!((&a - b.clone()).unwrap(), FieldElement::new(5u64, 31u64));
+        assert_eq!(((&a / b.clone()).unwrap() * b).unwrap(), a);
+        assert_eq!(
+            num_traits::Pow::pow(&a, 2u32),
+            FieldElement::new(18u64, 31u64)
+        );
+    }
+
+    #[test]
+    fn test_sum_product() {
+        let elements: Vec<_> = [3u64, 5, 30]
+            .iter()
+            .map(|n| FieldElement::new(*n, 31u64))
+            .collect();
+        let sum: Result<FieldElement> = elements.iter().sum();
+        assert_eq!(sum.unwrap(), FieldElement::new(7u64, 31u64));
+        let product: Result<FieldElement> = elements.into_iter().product();
+        assert_eq!(product.unwrap(), FieldElement::new(16u64, 31u64));
+
+        let empty: Result<FieldElement> = Vec::<FieldElement>::new().into_iter().sum();
+        assert!(empty.is_err());
+        let mixed: Result<FieldElement> = [
+            FieldElement::new(1u64, 31u64),
+            FieldElement::new(1u64, 37u64),
+        ]
+        .iter()
+        .sum();
+        assert!(mixed.is_err());
+    }
+
+    #[test]
+    fn test_display_ord_hash() {
+        use std::collections::HashSet;
+
+        let a = FieldElement::new(4u64, 31u64);
+        assert_eq!(a.to_string(), "FieldElement_31(4)");
+
+        let mut elements = [
+            FieldElement::new(5u64, 37u64),
+            FieldElement::new(30u64, 31u64),
+            FieldElement::new(4u64, 31u64),
+        ];
+        elements.sort();
+        assert_eq!(elements[0], a);
+        assert_eq!(elements[2], FieldElement::new(5u64, 37u64));
+
+        let set: HashSet<_> = [a.clone(), FieldElement::new(35u64, 31u64)]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_pow_large_negative_exponent() {
+        let a = FieldElement::new(17u64, 31u64);
+        assert_eq!(a.pow(-33i64), a.pow(-3i64));
+        assert_eq!(a.pow(-33i64), FieldElement::new(29u64, 31u64));
+    }
+
+    #[test]
+    fn test_pow_of_zero() {
+        let zero = FieldElement::zero(31u64);
+        assert_eq!(zero.pow(30u64), zero);
+        assert_eq!(zero.pow(60u64), zero);
+        assert_eq!(zero.pow(-1i64), zero);
+        assert!(zero.pow(0u64).is_one());
+    }
+
+    #[test]
+    fn test_pow_in_f2() {
+        let (zero, one) = (FieldElement::zero(2u64), FieldElement::one(2u64));
+        for exponent in [1i64, 2, 5, -1, -4] {
+            assert_eq!(zero.pow(exponent), zero, "0^{exponent}");
+            assert_eq!(one.pow(exponent), one, "1^{exponent}");
+        }
+        assert!(zero.pow(0u64).is_one());
+        assert_eq!(one.inverse().unwrap(), one);
+        assert!(zero.inverse().is_err());
+    }
+
+    #[test]
+    fn test_sqrt() {
+        for prime in [2u64, 17, 97, 223, 65537] {
+            let mut residues = 0;
+            for num in 0..prime.min(500) {
+                let a = FieldElement::new(num, prime);
+                match a.sqrt() {
+                    Some(root) => {
+                        residues += 1;
+                        assert!(a.is_square());
+                        assert_eq!((&root * &root).unwrap(), a, "{num} mod {prime}");
+                    }
+                    None => assert!(!a.is_square(), "{num} mod {prime}"),
+                }
+            }
+            if prime > 2 && prime < 500 {
+                assert_eq!(residues, (prime + 1) / 2, "{prime}");
+            }
+        }
+    }
 }
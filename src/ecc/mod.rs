@@ -1,4 +1,10 @@
+pub mod curve_params;
+pub mod discrete_log;
+pub mod ecdsa;
 pub mod elliptic_curve;
 pub mod elliptic_curve_finite_field;
+pub mod extension_field;
 pub mod finite_field;
+pub mod polynomial;
 pub mod secp256k1;
+pub mod svg;
@@ -0,0 +1,414 @@
+use crate::ecc::finite_field::FieldElement;
+use anyhow::anyhow;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use std::ops::{Add, Mul, Sub};
+
+type Result<T> = std::result::Result<T, anyhow::Error>;
+
+/// Polynomial over the prime field F_prime, coefficients are stored from the lowest degree up
+/// and the highest stored coefficient is never zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    coefficients: Vec<BigInt>,
+    prime: BigInt,
+}
+
+impl Polynomial {
+    pub fn new<C: Into<BigInt>>(
+        coefficients: impl IntoIterator<Item = C>,
+        prime: impl Into<BigInt>,
+    ) -> Self {
+        let prime = prime.into();
+        let coefficients = coefficients
+            .into_iter()
+            .map(|c| c.into().mod_floor(&prime))
+            .collect();
+        Self::normalized(coefficients, prime)
+    }
+
+    pub fn zero(prime: impl Into<BigInt>) -> Self {
+        Self::new(Vec::<BigInt>::new(), prime)
+    }
+
+    pub fn one(prime: impl Into<BigInt>) -> Self {
+        Self::new([1], prime)
+    }
+
+    /// returns coefficient * x^degree
+    pub fn monomial(
+        coefficient: impl Into<BigInt>,
+        degree: usize,
+        prime: impl Into<BigInt>,
+    ) -> Self {
+        let mut coefficients = vec![BigInt::from(0); degree];
+        coefficients.push(coefficient.into());
+        Self::new(coefficients, prime)
+    }
+
+    fn normalized(mut coefficients: Vec<BigInt>, prime: BigInt) -> Self {
+        while coefficients.last() == Some(&BigInt::from(0)) {
+            coefficients.pop();
+        }
+        Self {
+            coefficients,
+            prime,
+        }
+    }
+
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    pub fn coefficients(&self) -> &[BigInt] {
+        &self.coefficients
+    }
+
+    /// returns the coefficient of x^degree
+    pub fn coefficient(&self, degree: usize) -> FieldElement {
+        FieldElement::new(
+            self.coefficients.get(degree).cloned().unwrap_or_default(),
+            self.prime.clone(),
+        )
+    }
+
+    /// returns None for the zero polynomial
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    pub fn leading_coefficient(&self) -> FieldElement {
+        self.coefficient(self.degree().unwrap_or_default())
+    }
+
+    /// returns the polynomial multiplied by the scalar k
+    pub fn scale(&self, k: &FieldElement) -> Result<Self> {
+        if k.prime != self.prime {
+            return Err(anyhow!(
+                "Cannot scale a polynomial by a number from another Field"
+            ));
+        }
+        Ok(Self::new(
+            self.coefficients.iter().map(|c| c * &k.num),
+            self.prime.clone(),
+        ))
+    }
+
+    /// evaluates the polynomial at x using Horner's scheme
+    pub fn evaluate(&self, x: &FieldElement) -> Result<FieldElement> {
+        self.coefficients
+            .iter()
+            .rev()
+            .try_fold(FieldElement::new(0, self.prime.clone()), |acc, c| {
+                (acc * x).and_then(|v| v + FieldElement::new(c.clone(), self.prime.clone()))
+            })
+    }
+
+    /// returns the polynomial divided by its leading coefficient
+    pub fn monic(&self) -> Result<Self> {
+        if self.is_zero() {
+            return Err(anyhow!("Zero polynomial cannot be made monic"));
+        }
+        self.scale(&self.leading_coefficient().pow(-1))
+    }
+
+    /// returns the quotient and the remainder of the euclidean division by rhs
+    pub fn div_rem(&self, rhs: &Polynomial) -> Result<(Polynomial, Polynomial)> {
+        if self.prime != rhs.prime {
+            return Err(anyhow!("Cannot divide polynomials over different Fields"));
+        }
+        let Some(rhs_degree) = rhs.degree() else {
+            return Err(anyhow!("Division by the zero polynomial"));
+        };
+        let lc_inv = rhs.leading_coefficient().pow(-1).num;
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![BigInt::from(0); remainder.len().saturating_sub(rhs_degree)];
+        for shift in (0..quotient.len()).rev() {
+            let factor = (&remainder[shift + rhs_degree] * &lc_inv).mod_floor(&self.prime);
+            if factor == BigInt::from(0) {
+                continue;
+            }
+            for (i, c) in rhs.coefficients.iter().enumerate() {
+                remainder[shift + i] = (&remainder[shift + i] - &factor * c).mod_floor(&self.prime);
+            }
+            quotient[shift] = factor;
+        }
+        remainder.truncate(rhs_degree);
+        Ok((
+            Self::normalized(quotient, self.prime.clone()),
+            Self::normalized(remainder, self.prime.clone()),
+        ))
+    }
+
+    /// returns the monic greatest common divisor
+    pub fn gcd(&self, rhs: &Polynomial) -> Result<Polynomial> {
+        let (mut a, mut b) = (self.clone(), rhs.clone());
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b)?;
+            (a, b) = (b, r);
+        }
+        if a.is_zero() {
+            Ok(a)
+        } else {
+            a.monic()
+        }
+    }
+
+    /// returns self^exponent mod modulus
+    pub fn pow_mod(&self, exponent: &BigInt, modulus: &Polynomial) -> Result<Polynomial> {
+        if exponent < &BigInt::from(0) {
+            return Err(anyhow!(
+                "Negative exponents are not supported for polynomials"
+            ));
+        }
+        let mut base = self.div_rem(modulus)?.1;
+        let mut res = Self::one(self.prime.clone()).div_rem(modulus)?.1;
+        let mut exponent = exponent.clone();
+        while exponent > BigInt::from(0) {
+            if exponent.is_odd() {
+                res = (&res * &base)?.div_rem(modulus)?.1;
+            }
+            base = (&base * &base)?.div_rem(modulus)?.1;
+            exponent >>= 1;
+        }
+        Ok(res)
+    }
+
+    /// Rabin's test: f of degree n is irreducible over F_p iff x^(p^n) = x mod f
+    /// and gcd(x^(p^(n/q)) - x, f) = 1 for every prime q dividing n.
+    pub fn is_irreducible(&self) -> bool {
+        let Some(n) = self.degree() else {
+            return false;
+        };
+        if n == 0 {
+            return false;
+        }
+        let x = Self::monomial(1, 1, self.prime.clone());
+        // x^(p^k) mod f for k = 0..=n
+        let mut frobenius = vec![x.clone()];
+        for k in 0..n {
+            match frobenius[k].pow_mod(&self.prime, self) {
+                Ok(next) => frobenius.push(next),
+                Err(_) => return false,
+            }
+        }
+        let reduced_x = match x.div_rem(self) {
+            Ok((_, r)) => r,
+            Err(_) => return false,
+        };
+        if frobenius[n] != reduced_x {
+            return false;
+        }
+        prime_factors(n).into_iter().all(|q| {
+            (&frobenius[n / q] - &x)
+                .and_then(|h| h.gcd(self))
+                .map(|g| g.degree() == Some(0))
+                .unwrap_or_default()
+        })
+    }
+}
+
+fn prime_factors(mut n: usize) -> Vec<usize> {
+    let mut factors = vec![];
+    let mut d = 2;
+    while d * d <= n {
+        if n % d == 0 {
+            factors.push(d);
+            while n % d == 0 {
+                n /= d;
+            }
+        }
+        d += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+impl Add<&Polynomial> for &Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn add(self, rhs: &Polynomial) -> Self::Output {
+        if self.prime != rhs.prime {
+            return Err(anyhow!("Cannot add polynomials over different Fields"));
+        }
+        let len = self.coefficients.len().max(rhs.coefficients.len());
+        let zero = BigInt::from(0);
+        let coefficients = (0..len)
+            .map(|i| {
+                let a = self.coefficients.get(i).unwrap_or(&zero);
+                let b = rhs.coefficients.get(i).unwrap_or(&zero);
+                (a + b).mod_floor(&self.prime)
+            })
+            .collect();
+        Ok(Polynomial::normalized(coefficients, self.prime.clone()))
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl Add<&Polynomial> for Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn add(self, rhs: &Polynomial) -> Self::Output {
+        &self + rhs
+    }
+}
+
+impl Sub<&Polynomial> for &Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn sub(self, rhs: &Polynomial) -> Self::Output {
+        if self.prime != rhs.prime {
+            return Err(anyhow!("Cannot subtract polynomials over different Fields"));
+        }
+        let len = self.coefficients.len().max(rhs.coefficients.len());
+        let zero = BigInt::from(0);
+        let coefficients = (0..len)
+            .map(|i| {
+                let a = self.coefficients.get(i).unwrap_or(&zero);
+                let b = rhs.coefficients.get(i).unwrap_or(&zero);
+                (a - b).mod_floor(&self.prime)
+            })
+            .collect();
+        Ok(Polynomial::normalized(coefficients, self.prime.clone()))
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl Sub<&Polynomial> for Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn sub(self, rhs: &Polynomial) -> Self::Output {
+        &self - rhs
+    }
+}
+
+impl Mul<&Polynomial> for &Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn mul(self, rhs: &Polynomial) -> Self::Output {
+        if self.prime != rhs.prime {
+            return Err(anyhow!("Cannot multiply polynomials over different Fields"));
+        }
+        if self.is_zero() || rhs.is_zero() {
+            return Ok(Polynomial::zero(self.prime.clone()));
+        }
+        let mut coefficients =
+            vec![BigInt::from(0); self.coefficients.len() + rhs.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in rhs.coefficients.iter().enumerate() {
+                coefficients[i + j] = (&coefficients[i + j] + a * b).mod_floor(&self.prime);
+            }
+        }
+        Ok(Polynomial::normalized(coefficients, self.prime.clone()))
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl Mul<&Polynomial> for Polynomial {
+    type Output = Result<Polynomial>;
+
+    fn mul(self, rhs: &Polynomial) -> Self::Output {
+        &self * rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        let a = Polynomial::new([1, 2, 0, 0], 7);
+        assert_eq!(a.degree(), Some(1));
+        assert_eq!(Polynomial::new([7, -7, 14], 7), Polynomial::zero(7));
+        assert_eq!(Polynomial::zero(7).degree(), None);
+        assert_eq!(Polynomial::new([-1], 7).coefficients(), &[BigInt::from(6)]);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        // (x + 1) * (x + 6) = x^2 + 6 mod 7
+        let a = Polynomial::new([1, 1], 7);
+        let b = Polynomial::new([6, 1], 7);
+        assert_eq!((&a * &b).unwrap(), Polynomial::new([6, 0, 1], 7));
+        assert_eq!((&a + &b).unwrap(), Polynomial::new([0, 2], 7));
+        assert_eq!((&a - &a).unwrap(), Polynomial::zero(7));
+        assert!((a + Polynomial::new([1], 5)).is_err());
+    }
+
+    #[test]
+    fn test_div_rem() {
+        // x^3 + 2x + 1 = (x^2 + 3x + 4)(x + 4) + 6 mod 7
+        let a = Polynomial::new([1, 2, 0, 1], 7);
+        let b = Polynomial::new([4, 1], 7);
+        let (q, r) = a.div_rem(&b).unwrap();
+        assert_eq!(q, Polynomial::new([4, 3, 1], 7));
+        assert_eq!(r, Polynomial::new([6], 7));
+        assert_eq!(((&q * &b).unwrap() + r).unwrap(), a);
+
+        assert!(a.div_rem(&Polynomial::zero(7)).is_err());
+    }
+
+    #[test]
+    fn test_gcd() {
+        let a = Polynomial::new([1, 1], 7);
+        let b = Polynomial::new([2, 1], 7);
+        let c = Polynomial::new([3, 1], 7);
+        let ab = (&a * &b).unwrap();
+        let ac = (&a * &c).unwrap().scale(&FieldElement::new(3, 7)).unwrap();
+        assert_eq!(ab.gcd(&ac).unwrap(), a);
+        assert_eq!(b.gcd(&c).unwrap(), Polynomial::one(7));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let a = Polynomial::new([1, 2, 0, 1], 7);
+        assert_eq!(
+            a.evaluate(&FieldElement::new(3, 7)).unwrap(),
+            FieldElement::new(6, 7)
+        );
+    }
+
+    #[test]
+    fn test_is_irreducible() {
+        // -1 is a square mod 5 but not mod 7
+        assert!(!Polynomial::new([1, 0, 1], 5).is_irreducible());
+        assert!(Polynomial::new([1, 0, 1], 7).is_irreducible());
+        assert!(Polynomial::new([1, 0, 1], 223).is_irreducible());
+        // AES polynomial x^8 + x^4 + x^3 + x + 1
+        assert!(Polynomial::new([1, 1, 0, 1, 1, 0, 0, 0, 1], 2).is_irreducible());
+        // x^4 + 1 = (x + 1)^4 over F_2
+        assert!(!Polynomial::new([1, 0, 0, 0, 1], 2).is_irreducible());
+        // (x^2 + x + 1)^2 has no roots but is reducible
+        assert!(!Polynomial::new([1, 0, 1, 0, 1], 2).is_irreducible());
+        assert!(Polynomial::new([1, 1], 2).is_irreducible());
+        assert!(!Polynomial::new([1], 2).is_irreducible());
+    }
+}
@@ -1,39 +1,43 @@
 use super::elliptic_curve_finite_field::Point as ECPoint;
 use crate::{
+    ecc::curve_params::SECP256K1, ecc::ecdsa,
     ecc::elliptic_curve_finite_field::Coordinate as ECCoordinate,
     ecc::elliptic_curve_finite_field::CurveOverFiniteField, ecc::finite_field::FieldElement,
 };
 use anyhow::{anyhow, Result};
-use hex_literal::hex;
-use num_bigint::{BigInt, RandBigInt, Sign};
+use num_bigint::{BigInt, Sign};
 use num_integer::Integer;
-use std::collections::VecDeque;
-use std::ops::{Add, Div, Mul};
+use std::ops::{Add, Mul};
 
 use crate::ecc::secp256k1::sec_format::SecFormat;
 use lazy_static::lazy_static;
-use rand::thread_rng;
 
+pub use crate::ecc::ecdsa::{RecoverableSignature, Signature};
+
+pub mod address;
+pub mod batch;
+pub mod bip32;
+pub mod bip322;
+pub mod bip39;
+pub mod ecdh;
+pub mod ecies;
+pub mod hash_to_curve;
+pub mod message;
+pub mod musig2;
+pub mod recovery;
+pub mod schnorr;
+pub mod script;
 pub mod sec_format;
-
-const _N: [u8; 32] = hex!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
-const _A: u64 = 0;
-const _B: u64 = 7;
+pub mod taproot;
+pub mod transaction;
+pub mod tweak;
 
 lazy_static! {
-    static ref N: BigInt = BigInt::from_bytes_be(Sign::Plus, _N.as_slice());
-    static ref A: BigInt = BigInt::from(_A);
-    static ref B: BigInt = BigInt::from(_B);
-    static ref P: BigInt = BigInt::from(2).pow(256) - BigInt::from(2).pow(32) - 977;
-    static ref G: Point = {
-        let g_x = hex!("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
-        let g_y = hex!("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
-        Point::new(Some(Coordinate::new(
-            Field::from(g_x.as_slice()),
-            Field::from(g_y.as_slice()),
-        )))
-        .unwrap()
-    };
+    static ref N: BigInt = SECP256K1.n.clone();
+    static ref A: BigInt = SECP256K1.a.clone();
+    static ref B: BigInt = SECP256K1.b.clone();
+    static ref P: BigInt = SECP256K1.p.clone();
+    static ref G: Point = Point(SECP256K1.generator());
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -96,35 +100,30 @@ impl TryFrom<&[u8]> for Point {
     type Error = anyhow::Error;
     /// returns a Point object from a SEC binary
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let Some(lead_byte) = value.first() else { return Err(anyhow!("empty input"))};
+        let Some(lead_byte) = value.first() else {
+            return Err(anyhow!("empty input"));
+        };
         match lead_byte {
             b'\x04' if value.len() < 65 => {
                 Err(anyhow!("unacceptable length of uncompressed sec signature"))
             }
-            b'\x04' => Point::new(Some(Coordinate::from((
-                BigInt::from_bytes_be(Sign::Plus, &value[1..33]),
-                BigInt::from_bytes_be(Sign::Plus, &value[33..65]),
-            )))),
+            b'\x04' => {
+                let x = BigInt::from_bytes_be(Sign::Plus, &value[1..33]);
+                let y = BigInt::from_bytes_be(Sign::Plus, &value[33..65]);
+                // reducing them mod p would accept several encodings of the same point
+                if x >= *P || y >= *P {
+                    return Err(anyhow!("coordinate is not a field element"));
+                }
+                Point::new(Some(Coordinate::from((x, y))))
+            }
             b'\x02' | b'\x03' if value.len() < 33 => {
                 Err(anyhow!("unacceptable length of compressed sec signature"))
             }
 
-            b'\x02' | b'\x03' => {
-                let y_is_even = *lead_byte == b'\x02';
-                let x = Field::from(&value[1..33]);
-                let alpha: Field = Field((x.pow(3).0 + Field::new(B.clone()).0).unwrap());
-                let beta = alpha.sqrt();
-                let chosen_beta = {
-                    // choose even_beta
-                    if y_is_even && beta.0.num.is_even() {
-                        beta
-                    } else {
-                        // choose odd_beta
-                        Field::new(&*P - &beta.0.num)
-                    }
-                };
-                Point::new(Some(Coordinate::new(x, chosen_beta)))
-            }
+            b'\x02' | b'\x03' => Point::lift_x(
+                &BigInt::from_bytes_be(Sign::Plus, &value[1..33]),
+                *lead_byte == b'\x02',
+            ),
             _ => Err(anyhow!("unacceptable lead byte")),
         }
     }
@@ -140,22 +139,46 @@ impl Point {
     }
 
     pub fn coordinate(&self) -> Option<&ECCoordinate> {
-        self.0.coordinate.as_ref()
+        self.0.coordinate()
     }
 
-    pub fn verify(&self, z: &BigInt, sig: &Signature) -> bool {
-        let s_inv = sig.s.modpow(&(&*N - 2), &N);
-        let u = (z * &s_inv).mod_floor(&N);
-        let v = (&sig.r * &s_inv).mod_floor(&N);
-        let total = &*G * &u + self * &v;
-        total
-            .map(|p| {
-                p.coordinate()
-                    .map(|ECCoordinate { x, .. }| x.num == sig.r)
-                    .unwrap_or_default()
-            })
+    /// returns the point with the given x-coordinate and parity of y
+    pub fn lift_x(x: &BigInt, y_is_even: bool) -> Result<Self> {
+        if x >= &*P {
+            return Err(anyhow!("x-coordinate is not a field element"));
+        }
+        let x = Field::new(x.clone());
+        let alpha: Field = Field((x.pow(3).0 + Field::new(B.clone()).0)?);
+        let beta = alpha.sqrt();
+        let y = if y_is_even == beta.0.num.is_even() {
+            beta
+        } else {
+            Field::new(&*P - &beta.0.num)
+        };
+        // fails when alpha is not a square, so x is not on the curve
+        Point::new(Some(Coordinate::new(x, y)))
+    }
+
+    /// returns whether the y-coordinate is even, false for the point at infinity
+    pub fn has_even_y(&self) -> bool {
+        self.coordinate()
+            .map(|c| c.y.num.is_even())
             .unwrap_or_default()
     }
+
+    pub fn verify(&self, z: &BigInt, sig: &Signature) -> bool {
+        ecdsa::verify(&SECP256K1, &self.0, z, sig)
+    }
+
+    /// verifies like [`Point::verify`] but only accepts low-S signatures
+    pub fn verify_strict(&self, z: &BigInt, sig: &Signature) -> bool {
+        ecdsa::verify_strict(&SECP256K1, &self.0, z, sig)
+    }
+
+    /// returns the binary version of the SEC format
+    pub fn sec<F: SecFormat>(&self) -> F::Output {
+        F::sec(self)
+    }
 }
 
 impl PartialEq<Point> for Point {
@@ -214,59 +237,11 @@ impl Mul<&BigInt> for &Point {
     type Output = Point;
 
     fn mul(self, rhs: &BigInt) -> Self::Output {
-        Point((&self.0).mul(&rhs.mod_floor(&*N)))
-    }
-}
-
-#[derive(Debug, Default)]
-pub struct Signature {
-    r: BigInt,
-    s: BigInt,
-}
-
-impl<R: Into<BigInt>, S: Into<BigInt>> From<(R, S)> for Signature {
-    fn from((r, s): (R, S)) -> Self {
-        Signature::new(r.into(), s.into())
-    }
-}
-
-impl Signature {
-    pub fn new(r: BigInt, s: BigInt) -> Self {
-        Self { r, s }
-    }
-
-    pub fn der(&self) -> Vec<u8> {
-        let encode = |big: &BigInt| {
-            let (_, r) = big.to_bytes_be();
-            let r_stripped = r.as_slice();
-            if r_stripped.first().unwrap() > &b'\x80' {
-                let mut r = vec![b'\x00'];
-                r.extend_from_slice(r_stripped);
-                r
-            } else {
-                r
-            }
-        };
-        let r = encode(&self.r);
-        let s = encode(&self.s);
-        let mut res = VecDeque::new();
-        res.push_back(2);
-        res.extend(r.len().to_be_bytes().into_iter().skip_while(|v| *v == 0u8));
-        res.extend(r.iter());
-
-        res.push_back(2);
-        res.extend(s.len().to_be_bytes().into_iter().skip_while(|v| *v == 0u8));
-        res.extend(s.iter());
-
-        let length: Vec<u8> = res
-            .len()
-            .to_be_bytes()
-            .into_iter()
-            .skip_while(|v| *v == 0u8)
-            .collect();
-        length.into_iter().rev().for_each(|v| res.push_front(v));
-        res.push_front(b'\x30');
-        Vec::from(res)
+        Point(
+            (&self.0)
+                .mul(&rhs.mod_floor(&*N))
+                .expect("multiples of a secp256k1 point stay on secp256k1"),
+        )
     }
 }
 
@@ -283,20 +258,16 @@ impl PrivateKey {
     }
 
     pub fn sign(&self, z: &BigInt) -> Option<Signature> {
-        let k: BigInt = thread_rng().gen_bigint(129);
-        let Point(ECPoint {
-            coordinate: Some(ECCoordinate { x:FieldElement{num: r, ..}, .. }),
-            ..
-        }) = &*G * &k else {return None};
-        let k_inv = k.modpow(&(&*N - 2), &N);
-        let s = ((z + &r * &self.secret) * k_inv).mod_floor(&N);
-        let s = if s > (&*N).div(2) { &*N - s } else { s };
-        Some(Signature { r, s })
+        ecdsa::sign(&SECP256K1, &self.secret.mod_floor(&N), z)
     }
 
-    /// returns the binary version of the SEC format
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    /// returns the binary version of the SEC format of the public key
     pub fn sec<F: SecFormat>(&self) -> F::Output {
-        F::sec(self)
+        F::sec(&self.point)
     }
 }
 
@@ -304,6 +275,9 @@ impl PrivateKey {
 mod tests {
     use super::*;
     use crate::ecc::secp256k1::sec_format::{Compressed, Uncompressed};
+    use hex_literal::hex;
+    use num_bigint::RandBigInt;
+    use rand::thread_rng;
 
     #[test]
     fn test_infinity() {
@@ -345,12 +319,17 @@ mod tests {
         }
     }
     #[test]
+    #[allow(clippy::useless_conversion)]
     fn test_sign() {
         (0..5).into_iter().for_each(|_| {
             let pk = PrivateKey::new(thread_rng().gen_bigint(129));
             let z = thread_rng().gen_bigint_range(&BigInt::from(0), &BigInt::from(2).pow(256));
             let sig = pk.sign(&z).unwrap();
-            assert!(pk.point.verify(&z, &sig))
+            assert!(pk.point.verify(&z, &sig));
+            assert!(pk.point.verify_strict(&z, &sig));
+            let high_s = Signature::new(sig.r().clone(), &*N - sig.s());
+            assert!(pk.point.verify(&z, &high_s));
+            assert!(!pk.point.verify_strict(&z, &high_s));
         });
     }
 
@@ -392,21 +371,4 @@ mod tests {
             assert_eq!(actual, expected_secs[idx]);
         }
     }
-    #[test]
-    fn test_der() {
-        let sig = Signature::new(
-            BigInt::from_bytes_be(
-                Sign::Plus,
-                hex!("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6").as_slice(),
-            ),
-            BigInt::from_bytes_be(
-                Sign::Plus,
-                hex!("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec").as_slice(),
-            ),
-        );
-        assert_eq!(
-           sig.der(),
-            hex!("3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec")
-        );
-    }
 }
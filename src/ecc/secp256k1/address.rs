@@ -0,0 +1,157 @@
+//! Bitcoin addresses of secp256k1 public keys.
+
+use super::script::{p2pkh_script, p2sh_script, witness_program_script};
+use super::sec_format::{Compressed, Uncompressed};
+use super::Point;
+use anyhow::{anyhow, Result};
+use bech32::{hrp, segwit, Hrp};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+/// returns RIPEMD160(SHA256(data))
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    fn p2pkh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet => 0x6f,
+        }
+    }
+
+    fn p2sh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet => 0xc4,
+        }
+    }
+
+    pub(crate) fn hrp(self) -> Hrp {
+        match self {
+            Network::Mainnet => hrp::BC,
+            Network::Testnet => hrp::TB,
+        }
+    }
+}
+
+fn base58check(prefix: u8, hash: &[u8; 20]) -> String {
+    let mut payload = vec![prefix];
+    payload.extend_from_slice(hash);
+    bs58::encode(payload).with_check().into_string()
+}
+
+impl Point {
+    /// returns the hash160 of the SEC format of the key
+    pub fn hash160(&self, compressed: bool) -> Result<[u8; 20]> {
+        let sec = if compressed {
+            self.sec::<Compressed>().map(|sec| hash160(&sec))
+        } else {
+            self.sec::<Uncompressed>().map(|sec| hash160(&sec))
+        };
+        sec.ok_or_else(|| anyhow!("The point at infinity has no address"))
+    }
+
+    /// returns the legacy pay-to-pubkey-hash address
+    pub fn p2pkh(&self, compressed: bool, network: Network) -> Result<String> {
+        Ok(base58check(
+            network.p2pkh_prefix(),
+            &self.hash160(compressed)?,
+        ))
+    }
+
+    /// returns the pay-to-witness-pubkey-hash address nested in pay-to-script-hash
+    pub fn p2sh_p2wpkh(&self, network: Network) -> Result<String> {
+        let mut redeem_script = vec![0x00, 0x14];
+        redeem_script.extend_from_slice(&self.hash160(true)?);
+        Ok(base58check(network.p2sh_prefix(), &hash160(&redeem_script)))
+    }
+
+    /// returns the native segwit v0 pay-to-witness-pubkey-hash address
+    pub fn p2wpkh(&self, network: Network) -> Result<String> {
+        Ok(segwit::encode_v0(network.hrp(), &self.hash160(true)?)?)
+    }
+}
+
+/// returns the output script an address of either network pays to
+pub fn script_pubkey(address: &str) -> Result<Vec<u8>> {
+    if let Ok((hrp, version, program)) = segwit::decode(address) {
+        if hrp != hrp::BC && hrp != hrp::TB {
+            return Err(anyhow!("Unknown address prefix {hrp}"));
+        }
+        return Ok(witness_program_script(version.to_u8(), &program));
+    }
+    let payload = bs58::decode(address).with_check(None).into_vec()?;
+    let [prefix, hash @ ..] = payload.as_slice() else {
+        return Err(anyhow!("Empty address payload"));
+    };
+    let hash: &[u8; 20] = hash
+        .try_into()
+        .map_err(|_| anyhow!("Address must hold a 20-byte hash"))?;
+    match *prefix {
+        0x00 | 0x6f => Ok(p2pkh_script(hash)),
+        0x05 | 0xc4 => Ok(p2sh_script(hash)),
+        _ => Err(anyhow!("Unknown address version {prefix}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::G;
+
+    #[test]
+    fn test_addresses() {
+        assert_eq!(
+            hex::encode(G.hash160(true).unwrap()),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+        assert_eq!(
+            G.p2pkh(true, Network::Mainnet).unwrap(),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(
+            G.p2pkh(false, Network::Mainnet).unwrap(),
+            "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm"
+        );
+        assert_eq!(
+            G.p2sh_p2wpkh(Network::Mainnet).unwrap(),
+            "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN"
+        );
+        assert_eq!(
+            G.p2wpkh(Network::Mainnet).unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            G.p2wpkh(Network::Testnet).unwrap(),
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        );
+        assert!(Point::new(None).unwrap().p2wpkh(Network::Mainnet).is_err());
+    }
+
+    #[test]
+    fn test_script_pubkey() {
+        let hash = G.hash160(true).unwrap();
+        assert_eq!(
+            script_pubkey("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").unwrap(),
+            p2pkh_script(&hash)
+        );
+        assert_eq!(
+            script_pubkey("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap(),
+            witness_program_script(0, &hash)
+        );
+        assert_eq!(
+            script_pubkey("3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN").unwrap(),
+            p2sh_script(&hash160(&witness_program_script(0, &hash)))
+        );
+        assert!(script_pubkey("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMh").is_err());
+        assert!(script_pubkey("ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9").is_err());
+    }
+}
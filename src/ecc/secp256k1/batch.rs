@@ -0,0 +1,179 @@
+//! Batch verification of many signatures at once.
+//!
+//! Schnorr batches are checked with a random linear combination of the BIP340 equations
+//! folded into one multi-scalar multiplication, ECDSA batches are verified in parallel.
+//! When a batch fails, the entries are checked one by one to report the first bad one.
+
+use super::schnorr::{self, challenge, XOnlyPublicKey};
+use super::{Point, Signature, G, N};
+use num_bigint::{BigInt, RandBigInt, Sign};
+use num_integer::Integer;
+use rand::thread_rng;
+use rayon::prelude::*;
+use std::fmt;
+
+/// Returned when a batch does not verify, holds the index of the first invalid entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchError {
+    pub index: usize,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "signature at index {} is invalid", self.index)
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// Computes Σ scalar⋅point with a single shared chain of doublings (Straus/Shamir's trick)
+pub fn multi_scalar_mul(terms: &[(BigInt, Point)]) -> anyhow::Result<Point> {
+    let scalars: Vec<BigInt> = terms.iter().map(|(k, _)| k.mod_floor(&N)).collect();
+    let bits = scalars.iter().map(|k| k.bits()).max().unwrap_or_default();
+    let mut acc = Point::new(None)?;
+    for bit in (0..bits).rev() {
+        acc = (&acc + &acc)?;
+        for (k, (_, point)) in scalars.iter().zip(terms) {
+            if k.bit(bit) {
+                acc = (&acc + point)?;
+            }
+        }
+    }
+    Ok(acc)
+}
+
+/// Verifies BIP340 signatures of (public key, message, signature) entries all at once
+pub fn verify_schnorr(
+    entries: &[(&XOnlyPublicKey, &[u8], &schnorr::Signature)],
+) -> Result<(), BatchError> {
+    if schnorr_combination_holds(entries) {
+        return Ok(());
+    }
+    match entries
+        .par_iter()
+        .position_first(|(public, msg, sig)| !public.verify(msg, sig))
+    {
+        Some(index) => Err(BatchError { index }),
+        // each valid signature zeroes its term of the combination, so this cannot happen
+        // unless the combination itself is broken, and the single checks are the authority
+        None => Ok(()),
+    }
+}
+
+/// checks (Σ aᵢsᵢ)⋅G = Σ aᵢ⋅Rᵢ + Σ aᵢeᵢ⋅Pᵢ with a₀ = 1 and random aᵢ
+fn schnorr_combination_holds(entries: &[(&XOnlyPublicKey, &[u8], &schnorr::Signature)]) -> bool {
+    let mut s_sum = BigInt::from(0);
+    let mut terms = Vec::with_capacity(2 * entries.len() + 1);
+    for (i, (public, msg, sig)) in entries.iter().enumerate() {
+        if sig.s() >= &*N {
+            return false;
+        }
+        let Ok(r) = Point::lift_x(&BigInt::from_bytes_be(Sign::Plus, sig.r()), true) else {
+            return false;
+        };
+        let a = if i == 0 {
+            BigInt::from(1)
+        } else {
+            thread_rng().gen_bigint_range(&BigInt::from(1), &N)
+        };
+        let e = challenge(sig.r(), &public.serialize(), msg);
+        s_sum += &a * sig.s();
+        // moving the right-hand side over, the whole sum has to be the point at infinity
+        terms.push((-(&a * e), public.point().clone()));
+        terms.push((-a, r));
+    }
+    terms.push((s_sum, G.clone()));
+    multi_scalar_mul(&terms)
+        .map(|sum| sum.coordinate().is_none())
+        .unwrap_or_default()
+}
+
+/// Verifies ECDSA signatures of (public key, z, signature) entries in parallel
+pub fn verify_ecdsa(entries: &[(&Point, &BigInt, &Signature)]) -> Result<(), BatchError> {
+    match entries
+        .par_iter()
+        .position_first(|(public, z, sig)| !public.verify(z, sig))
+    {
+        Some(index) => Err(BatchError { index }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::PrivateKey;
+
+    #[test]
+    fn test_multi_scalar_mul() {
+        let p = &*G * &BigInt::from(7);
+        let terms = [
+            (BigInt::from(3), G.clone()),
+            (BigInt::from(5), p.clone()),
+            (-BigInt::from(2), p),
+        ];
+        assert_eq!(
+            multi_scalar_mul(&terms).unwrap(),
+            &*G * &BigInt::from(3 + 5 * 7 - 2 * 7)
+        );
+        assert!(multi_scalar_mul(&[]).unwrap().coordinate().is_none());
+    }
+
+    #[test]
+    fn test_verify_schnorr() {
+        let keys: Vec<PrivateKey> = (1..=4u32)
+            .map(|i| PrivateKey::new(BigInt::from(i * 0x1234567)))
+            .collect();
+        let publics: Vec<XOnlyPublicKey> = keys
+            .iter()
+            .map(|k| k.x_only_public_key().unwrap().0)
+            .collect();
+        let msgs: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; i as usize * 10]).collect();
+        let mut sigs: Vec<schnorr::Signature> = keys
+            .iter()
+            .zip(&msgs)
+            .map(|(k, m)| k.sign_schnorr(m).unwrap())
+            .collect();
+        let verify = |sigs: &[schnorr::Signature]| {
+            let entries: Vec<_> = publics
+                .iter()
+                .zip(&msgs)
+                .zip(sigs)
+                .map(|((p, m), s)| (p, m.as_slice(), s))
+                .collect();
+            verify_schnorr(&entries)
+        };
+        assert_eq!(verify(&sigs), Ok(()));
+        assert_eq!(verify_schnorr(&[]), Ok(()));
+
+        // a signature of another message
+        sigs[2] = keys[2].sign_schnorr(b"other").unwrap();
+        assert_eq!(verify(&sigs), Err(BatchError { index: 2 }));
+    }
+
+    #[test]
+    fn test_verify_ecdsa() {
+        let keys: Vec<PrivateKey> = (1..=4u32)
+            .map(|i| PrivateKey::new(BigInt::from(i * 0x7654321)))
+            .collect();
+        let zs: Vec<BigInt> = (0..4u32).map(|i| BigInt::from(i * 1000 + 1)).collect();
+        let mut sigs: Vec<Signature> = keys
+            .iter()
+            .zip(&zs)
+            .map(|(k, z)| k.sign(z).unwrap())
+            .collect();
+        let verify = |sigs: &[Signature]| {
+            let entries: Vec<_> = keys
+                .iter()
+                .zip(&zs)
+                .zip(sigs)
+                .map(|((k, z), s)| (k.point(), z, s))
+                .collect();
+            verify_ecdsa(&entries)
+        };
+        assert_eq!(verify(&sigs), Ok(()));
+
+        sigs[3] = keys[3].sign(&BigInt::from(42)).unwrap();
+        assert_eq!(verify(&sigs), Err(BatchError { index: 3 }));
+    }
+}
@@ -0,0 +1,566 @@
+//! BIP32 hierarchical deterministic keys: extended private and public keys, child derivation
+//! with HMAC-SHA512, derivation paths and the xprv/xpub/tprv/tpub Base58Check serialization.
+
+use super::address::Network;
+use super::sec_format::{to_32_bytes, Compressed};
+use super::{Point, PrivateKey, N};
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use num_bigint::{BigInt, Sign};
+use sha2::Sha512;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// child numbers from 2^31 on derive hardened keys
+pub const HARDENED: u32 = 0x8000_0000;
+
+const XPRV: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const XPUB: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const TPRV: [u8; 4] = [0x04, 0x35, 0x83, 0x94];
+const TPUB: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+
+/// returns HMAC-SHA512(key, data) split into its left and right halves
+fn hmac_sha512(key: &[u8], data: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    let i = mac.finalize().into_bytes();
+    let mut il = [0; 32];
+    let mut ir = [0; 32];
+    il.copy_from_slice(&i[..32]);
+    ir.copy_from_slice(&i[32..]);
+    (il, ir)
+}
+
+impl PrivateKey {
+    /// returns the first 4 bytes of the hash160 of the compressed public key
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.point
+            .fingerprint()
+            .expect("a private key in [1, n) has a finite public key")
+    }
+}
+
+impl Point {
+    /// returns the first 4 bytes of the hash160 of the compressed key, identifying it as a parent
+    pub fn fingerprint(&self) -> Result<[u8; 4]> {
+        let hash = self.hash160(true)?;
+        Ok([hash[0], hash[1], hash[2], hash[3]])
+    }
+}
+
+/// A sequence of child numbers such as `m/84'/0'/0'/0/5`, hardened ones carry [`HARDENED`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(children: Vec<u32>) -> Self {
+        Self(children)
+    }
+
+    pub fn children(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    /// parses `m` followed by `/`-separated indices, marked hardened by `'`, `h` or `H`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(anyhow!("Derivation path must start with m"));
+        }
+        parts
+            .map(|part| {
+                let (index, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(index) => (index, true),
+                    None => (part, false),
+                };
+                let index: u32 = index
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid child index {part:?}"))?;
+                if index >= HARDENED {
+                    return Err(anyhow!("Child index {index} is out of range"));
+                }
+                Ok(if hardened { index | HARDENED } else { index })
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for &child in &self.0 {
+            if child >= HARDENED {
+                write!(f, "/{}'", child - HARDENED)?;
+            } else {
+                write!(f, "/{child}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fields an extended key carries besides the key itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Metadata {
+    network: Network,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+    chain_code: [u8; 32],
+}
+
+impl Metadata {
+    fn child(
+        &self,
+        parent_fingerprint: [u8; 4],
+        child_number: u32,
+        chain_code: [u8; 32],
+    ) -> Result<Self> {
+        Ok(Self {
+            network: self.network,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or_else(|| anyhow!("Maximum derivation depth reached"))?,
+            parent_fingerprint,
+            child_number,
+            chain_code,
+        })
+    }
+
+    /// returns the 78-byte serialization with the given version and 33-byte key
+    fn serialize(&self, version: [u8; 4], key: &[u8; 33]) -> String {
+        let mut payload = Vec::with_capacity(78);
+        payload.extend_from_slice(&version);
+        payload.push(self.depth);
+        payload.extend_from_slice(&self.parent_fingerprint);
+        payload.extend_from_slice(&self.child_number.to_be_bytes());
+        payload.extend_from_slice(&self.chain_code);
+        payload.extend_from_slice(key);
+        bs58::encode(payload).with_check().into_string()
+    }
+
+    /// parses a Base58Check extended key, returns its metadata, version and 33-byte key
+    fn deserialize(s: &str) -> Result<(Self, [u8; 4], [u8; 33])> {
+        let payload = bs58::decode(s).with_check(None).into_vec()?;
+        if payload.len() != 78 {
+            return Err(anyhow!("Extended key must be 78 bytes"));
+        }
+        let version: [u8; 4] = payload[..4].try_into()?;
+        let network = match version {
+            XPRV | XPUB => Network::Mainnet,
+            TPRV | TPUB => Network::Testnet,
+            _ => {
+                return Err(anyhow!(
+                    "Unknown extended key version {}",
+                    hex::encode(version)
+                ))
+            }
+        };
+        let metadata = Self {
+            network,
+            depth: payload[4],
+            parent_fingerprint: payload[5..9].try_into()?,
+            child_number: u32::from_be_bytes(payload[9..13].try_into()?),
+            chain_code: payload[13..45].try_into()?,
+        };
+        if metadata.depth == 0
+            && (metadata.parent_fingerprint != [0; 4] || metadata.child_number != 0)
+        {
+            return Err(anyhow!("Master key must have no parent and child number 0"));
+        }
+        Ok((metadata, version, payload[45..].try_into()?))
+    }
+}
+
+/// A private key extended with a chain code, from which child keys derive
+#[derive(Debug)]
+pub struct ExtendedPrivateKey {
+    key: PrivateKey,
+    metadata: Metadata,
+}
+
+impl Clone for ExtendedPrivateKey {
+    fn clone(&self) -> Self {
+        Self {
+            key: PrivateKey {
+                secret: self.key.secret.clone(),
+                point: self.key.point.clone(),
+            },
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl PartialEq for ExtendedPrivateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.key.secret == other.key.secret && self.metadata == other.metadata
+    }
+}
+
+impl ExtendedPrivateKey {
+    /// Generates the master key of a seed of 128 to 512 bits
+    pub fn new_master(seed: &[u8], network: Network) -> Result<Self> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(anyhow!("Seed must be between 16 and 64 bytes"));
+        }
+        let (il, chain_code) = hmac_sha512(b"Bitcoin seed", seed);
+        let secret = BigInt::from_bytes_be(Sign::Plus, &il);
+        if secret == BigInt::from(0) || secret >= *N {
+            return Err(anyhow!("Seed gives an invalid master key"));
+        }
+        Ok(Self {
+            key: PrivateKey::new(secret),
+            metadata: Metadata {
+                network,
+                depth: 0,
+                parent_fingerprint: [0; 4],
+                child_number: 0,
+                chain_code,
+            },
+        })
+    }
+
+    pub fn private_key(&self) -> &PrivateKey {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.metadata.chain_code
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.metadata.depth
+    }
+
+    pub fn parent_fingerprint(&self) -> [u8; 4] {
+        self.metadata.parent_fingerprint
+    }
+
+    pub fn child_number(&self) -> u32 {
+        self.metadata.child_number
+    }
+
+    pub fn network(&self) -> Network {
+        self.metadata.network
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.key.fingerprint()
+    }
+
+    /// returns the extended public key with the same chain code
+    pub fn public(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            point: self.key.point.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Derives the child with the given number, hardened when it is at least [`HARDENED`]
+    pub fn derive_child(&self, child_number: u32) -> Result<Self> {
+        let mut data = Vec::with_capacity(37);
+        if child_number >= HARDENED {
+            data.push(0);
+            data.extend_from_slice(&to_32_bytes(&self.key.secret));
+        } else {
+            data.extend_from_slice(&self.public().sec());
+        }
+        data.extend_from_slice(&child_number.to_be_bytes());
+        let (il, chain_code) = hmac_sha512(&self.metadata.chain_code, &data);
+        let key = self
+            .key
+            .tweak_add(&BigInt::from_bytes_be(Sign::Plus, &il))
+            .map_err(|_| anyhow!("Child {child_number} is invalid, use the next one"))?;
+        Ok(Self {
+            key,
+            metadata: self
+                .metadata
+                .child(self.fingerprint(), child_number, chain_code)?,
+        })
+    }
+
+    /// Derives the descendant at the given path
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        path.children()
+            .iter()
+            .try_fold(self.clone(), |key, &child| key.derive_child(child))
+    }
+}
+
+impl Display for ExtendedPrivateKey {
+    /// writes the xprv or tprv serialization
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let version = match self.metadata.network {
+            Network::Mainnet => XPRV,
+            Network::Testnet => TPRV,
+        };
+        let mut key = [0; 33];
+        key[1..].copy_from_slice(&to_32_bytes(&self.key.secret));
+        write!(f, "{}", self.metadata.serialize(version, &key))
+    }
+}
+
+impl FromStr for ExtendedPrivateKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (metadata, version, key) = Metadata::deserialize(s)?;
+        if version != XPRV && version != TPRV {
+            return Err(anyhow!("Not an extended private key"));
+        }
+        if key[0] != 0 {
+            return Err(anyhow!("Private key must be prefixed with a zero byte"));
+        }
+        let secret = BigInt::from_bytes_be(Sign::Plus, &key[1..]);
+        if secret == BigInt::from(0) || secret >= *N {
+            return Err(anyhow!("Private key is out of range"));
+        }
+        Ok(Self {
+            key: PrivateKey::new(secret),
+            metadata,
+        })
+    }
+}
+
+/// A public key extended with a chain code, from which non-hardened child keys derive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPublicKey {
+    point: Point,
+    metadata: Metadata,
+}
+
+impl ExtendedPublicKey {
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.metadata.chain_code
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.metadata.depth
+    }
+
+    pub fn parent_fingerprint(&self) -> [u8; 4] {
+        self.metadata.parent_fingerprint
+    }
+
+    pub fn child_number(&self) -> u32 {
+        self.metadata.child_number
+    }
+
+    pub fn network(&self) -> Network {
+        self.metadata.network
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.point
+            .fingerprint()
+            .expect("extended public keys are never the point at infinity")
+    }
+
+    /// returns the compressed SEC format of the key
+    fn sec(&self) -> [u8; 33] {
+        self.point
+            .sec::<Compressed>()
+            .expect("extended public keys are never the point at infinity")
+    }
+
+    /// Derives the non-hardened child with the given number
+    pub fn derive_child(&self, child_number: u32) -> Result<Self> {
+        if child_number >= HARDENED {
+            return Err(anyhow!(
+                "Hardened children cannot be derived from a public key"
+            ));
+        }
+        let mut data = Vec::with_capacity(37);
+        data.extend_from_slice(&self.sec());
+        data.extend_from_slice(&child_number.to_be_bytes());
+        let (il, chain_code) = hmac_sha512(&self.metadata.chain_code, &data);
+        let point = self
+            .point
+            .tweak_add(&BigInt::from_bytes_be(Sign::Plus, &il))
+            .map_err(|_| anyhow!("Child {child_number} is invalid, use the next one"))?;
+        Ok(Self {
+            point,
+            metadata: self
+                .metadata
+                .child(self.fingerprint(), child_number, chain_code)?,
+        })
+    }
+
+    /// Derives the descendant at the given path, which must not contain hardened steps
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        path.children()
+            .iter()
+            .try_fold(self.clone(), |key, &child| key.derive_child(child))
+    }
+}
+
+impl Display for ExtendedPublicKey {
+    /// writes the xpub or tpub serialization
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let version = match self.metadata.network {
+            Network::Mainnet => XPUB,
+            Network::Testnet => TPUB,
+        };
+        write!(f, "{}", self.metadata.serialize(version, &self.sec()))
+    }
+}
+
+impl FromStr for ExtendedPublicKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (metadata, version, key) = Metadata::deserialize(s)?;
+        if version != XPUB && version != TPUB {
+            return Err(anyhow!("Not an extended public key"));
+        }
+        if key[0] != 0x02 && key[0] != 0x03 {
+            return Err(anyhow!("Public key must be compressed"));
+        }
+        Ok(Self {
+            point: Point::try_from(key.as_slice())?,
+            metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    fn check_vector(seed: &[u8], chain: &[(&str, &str, &str)]) {
+        let master = ExtendedPrivateKey::new_master(seed, Network::Mainnet).unwrap();
+        for (path, xpub, xprv) in chain {
+            let path: DerivationPath = path.parse().unwrap();
+            let key = master.derive_path(&path).unwrap();
+            assert_eq!(key.to_string(), *xprv);
+            assert_eq!(key.public().to_string(), *xpub);
+            assert_eq!(xprv.parse::<ExtendedPrivateKey>().unwrap(), key);
+            assert_eq!(xpub.parse::<ExtendedPublicKey>().unwrap(), key.public());
+        }
+    }
+
+    #[test]
+    fn test_vector_1() {
+        check_vector(
+            &hex!("000102030405060708090a0b0c0d0e0f"),
+            &[
+                (
+                    "m",
+                    "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+                    "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+                ),
+                (
+                    "m/0H",
+                    "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+                    "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+                ),
+                (
+                    "m/0H/1",
+                    "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+                    "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+                ),
+                (
+                    "m/0H/1/2H",
+                    "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+                    "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+                ),
+                (
+                    "m/0H/1/2H/2",
+                    "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+                    "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+                ),
+                (
+                    "m/0H/1/2H/2/1000000000",
+                    "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+                    "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_vector_3() {
+        // the master key has leading zeros that must be kept
+        check_vector(
+            &hex!("4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be"),
+            &[
+                (
+                    "m",
+                    "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+                    "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+                ),
+                (
+                    "m/0H",
+                    "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+                    "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_public_derivation() {
+        let master = ExtendedPrivateKey::new_master(
+            &hex!("000102030405060708090a0b0c0d0e0f"),
+            Network::Testnet,
+        )
+        .unwrap();
+        let account = master.derive_path(&"m/84'/1'/0'".parse().unwrap()).unwrap();
+        let path: DerivationPath = "m/0/5".parse().unwrap();
+        let from_private = account.derive_path(&path).unwrap().public();
+        let from_public = account.public().derive_path(&path).unwrap();
+        assert_eq!(from_private, from_public);
+        assert_eq!(from_public.depth(), 5);
+        assert_eq!(from_public.child_number(), 5);
+        assert!(from_public.to_string().starts_with("tpub"));
+        assert!(account.to_string().starts_with("tprv"));
+        assert!(account.public().derive_child(HARDENED).is_err());
+        assert_eq!(
+            from_public.parent_fingerprint(),
+            account.derive_child(0).unwrap().fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let path: DerivationPath = "m/84'/0h/0H/0/5".parse().unwrap();
+        assert_eq!(path.children(), &[84 | HARDENED, HARDENED, HARDENED, 0, 5]);
+        assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap(),
+            DerivationPath::default()
+        );
+        for bad in ["", "84'/0", "m/", "m/x", "m/-1", "m/2147483648", "m//0"] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        for bad in [
+            // pubkey version / prvkey mismatch
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm",
+            // invalid checksum
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL",
+            // zero depth with non-zero parent fingerprint
+            "xprv9s2SPatNQ9Vc6GTbVMFPFo7jsaZySyzk7L8n2uqKXJen3KUmvQNTuLh3fhZMBoG3G4ZW1N2kZuHEPY53qmbZzCHshoQnNf4GvELZfqTUrcv",
+            "not an extended key",
+        ] {
+            assert!(bad.parse::<ExtendedPrivateKey>().is_err(), "{bad}");
+            assert!(bad.parse::<ExtendedPublicKey>().is_err(), "{bad}");
+        }
+        assert!(ExtendedPrivateKey::new_master(&[0; 15], Network::Mainnet).is_err());
+        assert!(ExtendedPrivateKey::new_master(&[0; 65], Network::Mainnet).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! BIP322 generic signed messages. The address is challenged by a virtual `to_spend`
+//! transaction paying to its script, and signed by a virtual `to_sign` transaction spending it.
+//! The simple format only ships the witness of `to_sign`, the full format the whole transaction.
+
+use super::address::{hash160, script_pubkey};
+use super::schnorr::tagged_hash;
+use super::script::{
+    p2pkh_script, p2sh_script, push_data, verify_input, witness_program_script, OP_0, OP_RETURN,
+};
+use super::sec_format::{Compressed, Uncompressed};
+use super::transaction::{
+    parse_witness, serialize_witness, OutPoint, Transaction, TxIn, TxOut, SIGHASH_ALL,
+    SIGHASH_DEFAULT,
+};
+use super::PrivateKey;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use num_bigint::{BigInt, Sign};
+
+/// returns the tagged hash of the message committed to by `to_spend`
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    tagged_hash("BIP0322-signed-message", message)
+}
+
+/// returns the transaction whose only output pays to the message challenge
+pub fn to_spend(message_challenge: &[u8], message: &[u8]) -> Transaction {
+    let mut script_sig = vec![OP_0];
+    push_data(&mut script_sig, &message_hash(message));
+    Transaction {
+        version: 0,
+        inputs: vec![TxIn {
+            previous_output: OutPoint {
+                txid: [0; 32],
+                vout: 0xffff_ffff,
+            },
+            script_sig,
+            sequence: 0,
+            witness: vec![],
+        }],
+        outputs: vec![TxOut {
+            value: 0,
+            script_pubkey: message_challenge.to_vec(),
+        }],
+        lock_time: 0,
+    }
+}
+
+/// returns the unsigned transaction spending `to_spend` into an OP_RETURN output
+pub fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: 0,
+        inputs: vec![TxIn {
+            previous_output: OutPoint {
+                txid: to_spend.txid(),
+                vout: 0,
+            },
+            ..Default::default()
+        }],
+        outputs: vec![TxOut {
+            value: 0,
+            script_pubkey: vec![OP_RETURN],
+        }],
+        lock_time: 0,
+    }
+}
+
+impl PrivateKey {
+    /// fills in scriptSig and witness of the `to_sign` input for the challenge
+    fn sign_to_sign(&self, to_spend: &Transaction, tx: &mut Transaction) -> Result<()> {
+        let challenge = &to_spend.outputs[0].script_pubkey;
+        let ecdsa = |sighash: [u8; 32]| -> Result<Vec<u8>> {
+            let mut sig = self
+                .sign(&BigInt::from_bytes_be(Sign::Plus, &sighash))
+                .ok_or_else(|| anyhow!("Secret is not in [1, n)"))?
+                .der();
+            sig.push(SIGHASH_ALL);
+            Ok(sig)
+        };
+        let compressed = self
+            .sec::<Compressed>()
+            .ok_or_else(|| anyhow!("Secret is not in [1, n)"))?;
+        let hash = hash160(&compressed);
+        let input = 0;
+
+        if challenge == &witness_program_script(0, &hash)
+            || challenge == &p2sh_script(&hash160(&witness_program_script(0, &hash)))
+        {
+            if challenge[0] != OP_0 {
+                push_data(
+                    &mut tx.inputs[input].script_sig,
+                    &witness_program_script(0, &hash),
+                );
+            }
+            let sighash = tx.segwit_v0_sighash(input, &p2pkh_script(&hash), 0, SIGHASH_ALL)?;
+            tx.inputs[input].witness = vec![ecdsa(sighash)?, compressed.to_vec()];
+            return Ok(());
+        }
+
+        let output = self.tap_tweak(None)?;
+        if challenge == &witness_program_script(1, &output.x_only_public_key()?.0.serialize()) {
+            let sighash = tx.taproot_key_path_sighash(input, &to_spend.outputs, SIGHASH_DEFAULT)?;
+            tx.inputs[input].witness = vec![output.sign_schnorr(&sighash)?.serialize().to_vec()];
+            return Ok(());
+        }
+
+        let uncompressed = self.sec::<Uncompressed>().expect("checked above");
+        for public in [&compressed[..], &uncompressed[..]] {
+            if challenge == &p2pkh_script(&hash160(public)) {
+                let sighash = tx.legacy_sighash(input, challenge, SIGHASH_ALL)?;
+                push_data(&mut tx.inputs[input].script_sig, &ecdsa(sighash)?);
+                push_data(&mut tx.inputs[input].script_sig, public);
+                return Ok(());
+            }
+        }
+        Err(anyhow!("The key cannot sign for this address"))
+    }
+
+    /// Signs the message for a segwit address, returns the base64 encoded witness
+    pub fn sign_bip322_simple(&self, address: &str, message: &[u8]) -> Result<String> {
+        let to_spend = to_spend(&script_pubkey(address)?, message);
+        let mut to_sign = to_sign(&to_spend);
+        self.sign_to_sign(&to_spend, &mut to_sign)?;
+        if !to_sign.inputs[0].script_sig.is_empty() {
+            return Err(anyhow!(
+                "The simple format only carries witnesses, use the full format"
+            ));
+        }
+        Ok(STANDARD.encode(serialize_witness(&to_sign.inputs[0].witness)))
+    }
+
+    /// Signs the message for any supported address, returns the base64 encoded `to_sign`
+    pub fn sign_bip322_full(&self, address: &str, message: &[u8]) -> Result<String> {
+        let to_spend = to_spend(&script_pubkey(address)?, message);
+        let mut to_sign = to_sign(&to_spend);
+        self.sign_to_sign(&to_spend, &mut to_sign)?;
+        Ok(STANDARD.encode(to_sign.serialize()))
+    }
+}
+
+/// Verifies a simple BIP322 signature, the base64 encoded witness of `to_sign`
+pub fn verify_simple(address: &str, message: &[u8], signature: &str) -> bool {
+    let check = || -> Result<()> {
+        let witness = parse_witness(&STANDARD.decode(signature)?)?;
+        let to_spend = to_spend(&script_pubkey(address)?, message);
+        let mut to_sign = to_sign(&to_spend);
+        to_sign.inputs[0].witness = witness;
+        verify_input(&to_sign, 0, &to_spend.outputs)
+    };
+    check().is_ok()
+}
+
+/// Verifies a full BIP322 signature, the base64 encoded `to_sign` transaction
+pub fn verify_full(address: &str, message: &[u8], signature: &str) -> bool {
+    let check = || -> Result<()> {
+        let to_sign = Transaction::parse(&STANDARD.decode(signature)?)?;
+        let to_spend = to_spend(&script_pubkey(address)?, message);
+        let expected = self::to_sign(&to_spend);
+        // version, lock time and sequence are free to prove timelocks
+        if to_sign.inputs.len() != 1
+            || to_sign.inputs[0].previous_output != expected.inputs[0].previous_output
+            || to_sign.outputs != expected.outputs
+        {
+            return Err(anyhow!("Not a to_sign transaction of this message"));
+        }
+        verify_input(&to_sign, 0, &to_spend.outputs)
+    };
+    check().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::address::Network;
+
+    // key and signatures from the BIP322 test vectors
+    const WIF: &str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+    const P2WPKH: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+
+    fn key() -> PrivateKey {
+        let payload = bs58::decode(WIF).with_check(Some(0x80)).into_vec().unwrap();
+        PrivateKey::new(BigInt::from_bytes_be(Sign::Plus, &payload[1..33]))
+    }
+
+    fn display_txid(tx: &Transaction) -> String {
+        let mut txid = tx.txid();
+        txid.reverse();
+        hex::encode(txid)
+    }
+
+    #[test]
+    fn test_message_hash() {
+        assert_eq!(
+            hex::encode(message_hash(b"")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(message_hash(b"Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn test_transactions() {
+        let challenge = script_pubkey(P2WPKH).unwrap();
+        for (message, to_spend_id, to_sign_id) in [
+            (
+                &b""[..],
+                "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7",
+                "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6",
+            ),
+            (
+                b"Hello World",
+                "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b",
+                "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf",
+            ),
+        ] {
+            let to_spend = to_spend(&challenge, message);
+            assert_eq!(display_txid(&to_spend), to_spend_id);
+            assert_eq!(display_txid(&to_sign(&to_spend)), to_sign_id);
+        }
+    }
+
+    #[test]
+    fn test_verify_vectors() {
+        assert_eq!(key().point().p2wpkh(Network::Mainnet).unwrap(), P2WPKH);
+        assert!(verify_simple(P2WPKH, b"", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="));
+        assert!(verify_simple(P2WPKH, b"Hello World", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="));
+        assert!(!verify_simple(P2WPKH, b"Hello World", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="));
+        assert!(verify_simple(P2TR, b"Hello World", "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ=="));
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let key = key();
+        let p2tr_signature = key.sign_bip322_simple(P2TR, b"Hello World").unwrap();
+        assert!(verify_simple(P2TR, b"Hello World", &p2tr_signature));
+        assert!(!verify_simple(P2TR, b"Hello Worle", &p2tr_signature));
+
+        let network = Network::Testnet;
+        let addresses = [
+            P2WPKH.to_string(),
+            P2TR.to_string(),
+            key.point().p2sh_p2wpkh(network).unwrap(),
+            key.point().p2pkh(true, network).unwrap(),
+            key.point().p2pkh(false, network).unwrap(),
+        ];
+        for address in &addresses {
+            let full = key.sign_bip322_full(address, b"proof").unwrap();
+            assert!(verify_full(address, b"proof", &full), "{address}");
+            assert!(!verify_full(address, b"proof!", &full), "{address}");
+            assert!(!verify_simple(address, b"proof", &full), "{address}");
+        }
+        // legacy and nested segwit need a scriptSig
+        assert!(key.sign_bip322_simple(&addresses[2], b"proof").is_err());
+        assert!(key.sign_bip322_simple(&addresses[3], b"proof").is_err());
+        let other = PrivateKey::new(BigInt::from(42));
+        assert!(other.sign_bip322_simple(P2WPKH, b"proof").is_err());
+        assert!(!verify_full(P2WPKH, b"proof", "AAAA"));
+    }
+}
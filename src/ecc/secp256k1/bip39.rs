@@ -0,0 +1,255 @@
+//! BIP39 mnemonic phrases: entropy of 128 to 256 bits plus a SHA-256 checksum, split into
+//! 11-bit indices of the English word list, and the PBKDF2-HMAC-SHA512 seed they stretch to.
+
+use super::address::Network;
+use super::bip32::ExtendedPrivateKey;
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+
+const PBKDF2_ROUNDS: u32 = 2048;
+
+lazy_static! {
+    static ref ENGLISH: Vec<&'static str> = include_str!("bip39/english.txt").lines().collect();
+}
+
+/// returns PBKDF2-HMAC-SHA512 with a single 64-byte output block
+fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 64] {
+    let mac = Hmac::<Sha512>::new_from_slice(password).expect("HMAC accepts keys of any length");
+    let mut u: [u8; 64] = mac
+        .clone()
+        .chain_update(salt)
+        .chain_update(1u32.to_be_bytes())
+        .finalize()
+        .into_bytes()
+        .into();
+    let mut res = u;
+    for _ in 1..rounds {
+        u = mac.clone().chain_update(u).finalize().into_bytes().into();
+        res.iter_mut().zip(u).for_each(|(r, u)| *r ^= u);
+    }
+    res
+}
+
+/// A mnemonic phrase of 12, 15, 18, 21 or 24 English words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    words: Vec<&'static str>,
+}
+
+impl Mnemonic {
+    /// Encodes 16, 20, 24, 28 or 32 bytes of entropy
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self> {
+        if !(16..=32).contains(&entropy.len()) || entropy.len() % 4 != 0 {
+            return Err(anyhow!(
+                "Entropy must be 16 to 32 bytes in steps of 4, got {}",
+                entropy.len()
+            ));
+        }
+        // the checksum is the first entropy bits / 32 bits of the hash
+        let checksum = Sha256::digest(entropy)[0];
+        let bits = entropy.len() * 8 + entropy.len() / 4;
+        let bit = |i: usize| {
+            let byte = entropy.get(i / 8).unwrap_or(&checksum);
+            (byte >> (7 - i % 8)) & 1
+        };
+        let words = (0..bits / 11)
+            .map(|word| {
+                let index = (0..11).fold(0, |acc, i| acc << 1 | usize::from(bit(word * 11 + i)));
+                ENGLISH[index]
+            })
+            .collect();
+        Ok(Self { words })
+    }
+
+    /// Generates a mnemonic of the given number of words from fresh entropy
+    pub fn generate(word_count: usize) -> Result<Self> {
+        if word_count % 3 != 0 {
+            return Err(anyhow!("Word count must be a multiple of 3"));
+        }
+        let mut entropy = vec![0; word_count * 4 / 3];
+        thread_rng().fill_bytes(&mut entropy);
+        Self::from_entropy(&entropy)
+    }
+
+    pub fn words(&self) -> &[&'static str] {
+        &self.words
+    }
+
+    /// returns the entropy the words encode, without the checksum
+    pub fn entropy(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; (self.words.len() * 11 + 7) / 8];
+        let mut pos = 0;
+        for word in &self.words {
+            let index = ENGLISH
+                .binary_search(word)
+                .expect("mnemonic words come from the word list");
+            for i in (0..11).rev() {
+                bytes[pos / 8] |= (((index >> i) & 1) as u8) << (7 - pos % 8);
+                pos += 1;
+            }
+        }
+        bytes.truncate(self.words.len() * 4 / 3);
+        bytes
+    }
+
+    /// Stretches the phrase to a 64-byte seed, the passphrase is NFKD normalized first so
+    /// that every way of typing it gives the same seed
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt: String = format!("mnemonic{passphrase}").nfkd().collect();
+        pbkdf2_hmac_sha512(self.to_string().as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS)
+    }
+
+    /// returns the BIP32 master key of the seed
+    pub fn to_master_key(&self, passphrase: &str, network: Network) -> Result<ExtendedPrivateKey> {
+        ExtendedPrivateKey::new_master(&self.to_seed(passphrase), network)
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = anyhow::Error;
+
+    /// parses and validates a phrase, rejecting unknown words and bad checksums
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s
+            .split_whitespace()
+            .map(|word| {
+                ENGLISH
+                    .binary_search(&word)
+                    .map(|index| ENGLISH[index])
+                    .map_err(|_| anyhow!("Unknown word {word:?}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !(12..=24).contains(&words.len()) || words.len() % 3 != 0 {
+            return Err(anyhow!(
+                "Mnemonic must have 12 to 24 words in steps of 3, got {}",
+                words.len()
+            ));
+        }
+        let mnemonic = Self { words };
+        if Self::from_entropy(&mnemonic.entropy())? != mnemonic {
+            return Err(anyhow!("Invalid mnemonic checksum"));
+        }
+        Ok(mnemonic)
+    }
+}
+
+impl Display for Mnemonic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_word_list() {
+        assert_eq!(ENGLISH.len(), 2048);
+        assert!(ENGLISH.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            Sha256::digest(include_str!("bip39/english.txt")).as_slice(),
+            hex!("2f5eed53a4727b4bf8880d8f3f199efc90e58503646d9ff8eff3a2ed3b24dbda")
+        );
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vectors: [(&[u8], &str, [u8; 64], &str); 5] = [
+            (
+                &hex!("00000000000000000000000000000000"),
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                hex!("c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"),
+                "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+            ),
+            (
+                &hex!("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f"),
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+                hex!("2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607"),
+                "xprv9s21ZrQH143K2gA81bYFHqU68xz1cX2APaSq5tt6MFSLeXnCKV1RVUJt9FWNTbrrryem4ZckN8k4Ls1H6nwdvDTvnV7zEXs2HgPezuVccsq",
+            ),
+            (
+                &hex!("ffffffffffffffffffffffffffffffff"),
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+                hex!("ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069"),
+                "xprv9s21ZrQH143K2V4oox4M8Zmhi2Fjx5XK4Lf7GKRvPSgydU3mjZuKGCTg7UPiBUD7ydVPvSLtg9hjp7MQTYsW67rZHAXeccqYqrsx8LcXnyd",
+            ),
+            (
+                &hex!("9e885d952ad362caeb4efe34a8e91bd2"),
+                "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+                hex!("274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028"),
+                "xprv9s21ZrQH143K2oZ9stBYpoaZ2ktHj7jLz7iMqpgg1En8kKFTXJHsjxry1JbKH19YrDTicVwKPehFKTbmaxgVEc5TpHdS1aYhB2s9aFJBeJH",
+            ),
+            (
+                &hex!("0000000000000000000000000000000000000000000000000000000000000000"),
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+                hex!("bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8"),
+                "xprv9s21ZrQH143K32qBagUJAMU2LsHg3ka7jqMcV98Y7gVeVyNStwYS3U7yVVoDZ4btbRNf4h6ibWpY22iRmXq35qgLs79f312g2kj5539ebPM",
+            ),
+        ];
+        for (entropy, phrase, seed, xprv) in vectors {
+            let mnemonic = Mnemonic::from_entropy(entropy).unwrap();
+            assert_eq!(mnemonic.to_string(), phrase);
+            assert_eq!(phrase.parse::<Mnemonic>().unwrap(), mnemonic);
+            assert_eq!(mnemonic.entropy(), entropy);
+            assert_eq!(mnemonic.to_seed("TREZOR"), seed);
+            assert_eq!(
+                mnemonic
+                    .to_master_key("TREZOR", Network::Mainnet)
+                    .unwrap()
+                    .to_string(),
+                xprv
+            );
+        }
+    }
+
+    #[test]
+    fn test_passphrase_normalization() {
+        let mnemonic: Mnemonic =
+            "legal winner thank year wave sausage worth useful legal winner thank yellow"
+                .parse()
+                .unwrap();
+        // "é" precomposed and as "e" followed by a combining acute accent
+        let seed = mnemonic.to_seed("caf\u{e9}");
+        assert_eq!(mnemonic.to_seed("cafe\u{301}"), seed);
+        assert_ne!(mnemonic.to_seed("cafe"), seed);
+        // compatibility characters decompose too: the "ﬁ" ligature is "fi"
+        assert_eq!(mnemonic.to_seed("\u{fb01}"), mnemonic.to_seed("fi"));
+    }
+
+    #[test]
+    fn test_generate() {
+        for word_count in [12, 15, 18, 21, 24] {
+            let mnemonic = Mnemonic::generate(word_count).unwrap();
+            assert_eq!(mnemonic.words().len(), word_count);
+            assert_eq!(mnemonic.to_string().parse::<Mnemonic>().unwrap(), mnemonic);
+        }
+        assert!(Mnemonic::generate(13).is_err());
+        assert!(Mnemonic::generate(27).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        for bad in [
+            // bad checksum
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+            // unknown word
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon bitcoin",
+            // wrong length
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "",
+        ] {
+            assert!(bad.parse::<Mnemonic>().is_err(), "{bad}");
+        }
+        assert!(Mnemonic::from_entropy(&[0; 15]).is_err());
+        assert!(Mnemonic::from_entropy(&[0; 18]).is_err());
+        assert!(Mnemonic::from_entropy(&[0; 36]).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Elliptic-curve Diffie–Hellman key agreement on secp256k1.
+
+use super::sec_format::Compressed;
+use super::{Point, PrivateKey};
+use crate::ecc::curve_params::SECP256K1;
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+/// The point both sides of an exchange arrive at, kept in compressed SEC form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedSecret {
+    compressed: [u8; 33],
+}
+
+impl SharedSecret {
+    /// returns the x-coordinate of the shared point, to feed into a KDF of one's choice
+    pub fn x(&self) -> [u8; 32] {
+        let mut x = [0; 32];
+        x.copy_from_slice(&self.compressed[1..]);
+        x
+    }
+
+    /// returns SHA-256 of the compressed shared point, what libsecp256k1's
+    /// `secp256k1_ecdh` outputs with its default hash function
+    pub fn hashed(&self) -> [u8; 32] {
+        Sha256::digest(self.compressed).into()
+    }
+}
+
+impl PrivateKey {
+    /// Derives the secret shared with the owner of `peer`, rejecting peer keys that are not
+    /// valid secp256k1 points and exchanges that end at the point at infinity.
+    pub fn ecdh(&self, peer: &Point) -> Result<SharedSecret> {
+        SECP256K1.validate_point(&peer.0)?;
+        let shared = peer * &self.secret;
+        let compressed = shared
+            .sec::<Compressed>()
+            .ok_or_else(|| anyhow!("Shared point is the point at infinity"))?;
+        Ok(SharedSecret { compressed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::sec_format::Uncompressed;
+    use crate::ecc::secp256k1::{G, N, P};
+    use hex_literal::hex;
+    use num_bigint::{BigInt, RandBigInt, Sign};
+    use rand::thread_rng;
+
+    #[test]
+    fn test_agreement() {
+        for _ in 0..3 {
+            let alice = PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N));
+            let bob = PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N));
+            let alice_shared = alice.ecdh(bob.point()).unwrap();
+            let bob_shared = bob.ecdh(alice.point()).unwrap();
+            assert_eq!(alice_shared, bob_shared);
+            assert_eq!(alice_shared.x(), bob_shared.x());
+            assert_eq!(alice_shared.hashed(), bob_shared.hashed());
+        }
+    }
+
+    #[test]
+    fn test_libsecp256k1_hash() {
+        // 1 * G = G, hashed as SHA-256(02 || Gx)
+        let shared = PrivateKey::new(BigInt::from(1)).ecdh(&G).unwrap();
+        assert_eq!(
+            shared.x(),
+            hex!("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+        );
+        assert_eq!(
+            shared.hashed(),
+            hex!("0f715baf5d4c2ed329785cef29e562f73488c8a2bb9dbc5700b361d54b9b0554")
+        );
+    }
+
+    #[test]
+    fn test_invalid_peer() {
+        let key = PrivateKey::new(BigInt::from(1234));
+        assert!(key.ecdh(&Point::new(None).unwrap()).is_err());
+        // a secret that is a multiple of n lands on the point at infinity
+        assert!(PrivateKey::new(N.clone()).ecdh(&G).is_err());
+    }
+
+    #[test]
+    fn test_unreduced_peer_coordinates() {
+        // a point whose x stays below 2^256 once p is added to it
+        let point = (1..)
+            .find_map(|x| Point::lift_x(&BigInt::from(x), true).ok())
+            .unwrap();
+        let sec = point.sec::<Uncompressed>().unwrap();
+        assert_eq!(Point::try_from(sec.as_slice()).unwrap(), point);
+
+        let x = BigInt::from_bytes_be(Sign::Plus, &sec[1..33]) + &*P;
+        let mut unreduced = sec;
+        unreduced[1..33].copy_from_slice(&x.to_bytes_be().1);
+        assert!(Point::try_from(unreduced.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_compressed_peer_key() {
+        // keys with both parities of y survive a round trip through compressed SEC
+        let alice = PrivateKey::new(BigInt::from(5001));
+        for secret in [5001, 5002, 5003, 5004] {
+            let bob = PrivateKey::new(BigInt::from(secret));
+            let sec = bob.sec::<Compressed>().unwrap();
+            let peer = Point::try_from(sec.as_slice()).unwrap();
+            assert_eq!(&peer, bob.point());
+            assert_eq!(alice.ecdh(&peer).unwrap(), bob.ecdh(alice.point()).unwrap());
+        }
+    }
+}
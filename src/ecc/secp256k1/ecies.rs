@@ -0,0 +1,167 @@
+//! ECIES hybrid encryption to secp256k1 public keys in the `eciespy` wire format:
+//! `ephemeral public key (65 bytes, uncompressed SEC) || nonce (16) || tag (16) || ciphertext`.
+//! The AES-256-GCM key is HKDF-SHA256 of the uncompressed ephemeral key followed by the
+//! uncompressed shared point, with no salt and no info.
+
+use super::sec_format::Uncompressed;
+use super::{Point, PrivateKey, N};
+use crate::ecc::curve_params::SECP256K1;
+use aes_gcm::aead::consts::U16;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::aes::Aes256;
+use aes_gcm::AesGcm;
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use num_bigint::{BigInt, RandBigInt};
+use rand::{thread_rng, RngCore};
+use sha2::Sha256;
+
+const PUBLIC_KEY_LEN: usize = 65;
+const NONCE_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+
+/// AES-256-GCM with the 16-byte nonce `eciespy` uses
+type Aes256Gcm16 = AesGcm<Aes256, U16>;
+
+/// derives the symmetric key from the ephemeral public key and the shared point
+fn derive_key(ephemeral: &Point, shared: &Point) -> Result<[u8; 32]> {
+    let (Some(ephemeral), Some(shared)) = (
+        ephemeral.sec::<Uncompressed>(),
+        shared.sec::<Uncompressed>(),
+    ) else {
+        return Err(anyhow!("Shared point is the point at infinity"));
+    };
+    let mut master = Vec::with_capacity(2 * PUBLIC_KEY_LEN);
+    master.extend_from_slice(&ephemeral);
+    master.extend_from_slice(&shared);
+    let mut key = [0; 32];
+    Hkdf::<Sha256>::new(None, &master)
+        .expand(&[], &mut key)
+        .map_err(|e| anyhow!("HKDF failed: {e}"))?;
+    Ok(key)
+}
+
+fn encrypt_with(
+    receiver: &Point,
+    ephemeral: &PrivateKey,
+    nonce: [u8; NONCE_LEN],
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    SECP256K1.validate_point(&receiver.0)?;
+    let key = derive_key(&ephemeral.point, &(receiver * &ephemeral.secret))?;
+    let mut ciphertext = plaintext.to_vec();
+    let tag = Aes256Gcm16::new(GenericArray::from_slice(&key))
+        .encrypt_in_place_detached(GenericArray::from_slice(&nonce), &[], &mut ciphertext)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut res = Vec::with_capacity(PUBLIC_KEY_LEN + NONCE_LEN + TAG_LEN + ciphertext.len());
+    res.extend_from_slice(
+        &ephemeral
+            .sec::<Uncompressed>()
+            .expect("ephemeral secret is in [1, n)"),
+    );
+    res.extend_from_slice(&nonce);
+    res.extend_from_slice(&tag);
+    res.extend_from_slice(&ciphertext);
+    Ok(res)
+}
+
+/// Encrypts the plaintext to the receiver's public key with a fresh ephemeral key and nonce
+pub fn encrypt(receiver: &Point, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let ephemeral = PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N));
+    let mut nonce = [0; NONCE_LEN];
+    thread_rng().fill_bytes(&mut nonce);
+    encrypt_with(receiver, &ephemeral, nonce, plaintext)
+}
+
+/// Decrypts a payload produced by [`encrypt`] (or `eciespy`), failing if it was tampered with
+pub fn decrypt(receiver: &PrivateKey, payload: &[u8]) -> Result<Vec<u8>> {
+    if payload.len() < PUBLIC_KEY_LEN + NONCE_LEN + TAG_LEN {
+        return Err(anyhow!("Payload is too short"));
+    }
+    let (ephemeral, rest) = payload.split_at(PUBLIC_KEY_LEN);
+    let (nonce, rest) = rest.split_at(NONCE_LEN);
+    let (tag, ciphertext) = rest.split_at(TAG_LEN);
+    if ephemeral[0] != b'\x04' {
+        return Err(anyhow!("Ephemeral key must be uncompressed"));
+    }
+    let ephemeral = Point::try_from(ephemeral)?;
+    SECP256K1.validate_point(&ephemeral.0)?;
+
+    let key = derive_key(&ephemeral, &(&ephemeral * &receiver.secret))?;
+    let mut plaintext = ciphertext.to_vec();
+    Aes256Gcm16::new(GenericArray::from_slice(&key))
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            &[],
+            &mut plaintext,
+            GenericArray::from_slice(tag),
+        )
+        .map_err(|_| anyhow!("Authentication failed"))?;
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+    use num_bigint::Sign;
+
+    fn key(bytes: [u8; 32]) -> PrivateKey {
+        PrivateKey::new(BigInt::from_bytes_be(Sign::Plus, &bytes))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let receiver = PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N));
+        for plaintext in [&b""[..], b"hello world", &[0xab; 1000]] {
+            let payload = encrypt(receiver.point(), plaintext).unwrap();
+            assert_eq!(payload.len(), 97 + plaintext.len());
+            assert_eq!(decrypt(&receiver, &payload).unwrap(), plaintext);
+        }
+        // fresh ephemeral keys and nonces every time
+        assert_ne!(
+            encrypt(receiver.point(), b"hello").unwrap(),
+            encrypt(receiver.point(), b"hello").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_eciespy_format() {
+        let receiver = key(hex!(
+            "95d3c5e483e9b1d4f5fc8e79b2deaf51362fad456d24f00c9d1dc1d4f6cac0f5"
+        ));
+        let ephemeral = key(hex!(
+            "2b8f3d0a6a1f7b0f9e5f0cd96c6b3a4c9f0d7e6a5b4c3d2e1f0a9b8c7d6e5f40"
+        ));
+        let expected = hex!(
+            "04bceeb9f407140eb19a7951d30958228a4ffb8ab95356803ef97925473bf0ad2c669d2d8e089347ce31809bfb118229bfb8c8c42c66782009bda02674f963887b"
+            "000102030405060708090a0b0c0d0e0f"
+            "e60f825eacf423f04c24be0c1ab1bb69"
+            "98894fb74c8eaf1491d949"
+        );
+        let nonce = hex!("000102030405060708090a0b0c0d0e0f");
+        let payload = encrypt_with(receiver.point(), &ephemeral, nonce, b"hello world").unwrap();
+        assert_eq!(payload, expected);
+        assert_eq!(decrypt(&receiver, &expected).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn test_tamper_detection() {
+        let receiver = PrivateKey::new(BigInt::from(0xdeadbeefu32));
+        let payload = encrypt(receiver.point(), b"attack at dawn").unwrap();
+        for i in [0, 1, 64, 65, 80, 96, payload.len() - 1] {
+            let mut tampered = payload.clone();
+            tampered[i] ^= 1;
+            assert!(decrypt(&receiver, &tampered).is_err(), "byte {i}");
+        }
+        assert!(decrypt(&receiver, &payload[..96]).is_err());
+        assert!(decrypt(&PrivateKey::new(BigInt::from(1)), &payload).is_err());
+    }
+
+    #[test]
+    fn test_invalid_receiver() {
+        assert!(encrypt(&Point::new(None).unwrap(), b"hello").is_err());
+    }
+}
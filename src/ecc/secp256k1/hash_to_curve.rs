@@ -0,0 +1,331 @@
+//! RFC 9380 hashing to secp256k1, suites `secp256k1_XMD:SHA-256_SSWU_RO_` ([`hash_to_curve`])
+//! and `secp256k1_XMD:SHA-256_SSWU_NU_` ([`encode_to_curve`]).
+//!
+//! secp256k1 has a = 0, which the simplified SWU map cannot handle, so field elements are
+//! mapped to the 3-isogenous curve y^2 = x^3 + A'x + B' and then carried over to secp256k1.
+
+use super::{Coordinate, Field, Point, P};
+use crate::ecc::finite_field::FieldElement;
+use anyhow::{anyhow, Result};
+use hex_literal::hex;
+use lazy_static::lazy_static;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use sha2::{Digest, Sha256};
+
+/// SHA-256 output and block sizes in bytes
+const B_IN_BYTES: usize = 32;
+const S_IN_BYTES: usize = 64;
+
+/// bytes hashed per field element: ceil((ceil(log2(p)) + k) / 8) with k = 128
+const L: usize = 48;
+
+fn constant(bytes: &[u8]) -> FieldElement {
+    FieldElement::new(BigInt::from_bytes_be(Sign::Plus, bytes), P.clone())
+}
+
+lazy_static! {
+    /// coefficients of the isogenous curve E'
+    static ref ISO_A: FieldElement =
+        constant(&hex!("3f8731abdd661adca08a5558f0f5d272e953d363cb6f0e5d405447c01a444533"));
+    static ref ISO_B: FieldElement = FieldElement::new(1771, P.clone());
+    static ref Z: FieldElement = FieldElement::new(-11, P.clone());
+    /// coefficients of the 3-isogeny map, lowest degree first
+    static ref X_NUM: [FieldElement; 4] = [
+        constant(&hex!("8e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38daaaaa8c7")),
+        constant(&hex!("07d3d4c80bc321d5b9f315cea7fd44c5d595d2fc0bf63b92dfff1044f17c6581")),
+        constant(&hex!("534c328d23f234e6e2a413deca25caece4506144037c40314ecbd0b53d9dd262")),
+        constant(&hex!("8e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38e38daaaaa88c")),
+    ];
+    static ref X_DEN: [FieldElement; 3] = [
+        constant(&hex!("d35771193d94918a9ca34ccbb7b640dd86cd409542f8487d9fe6b745781eb49b")),
+        constant(&hex!("edadc6f64383dc1df7c4b2d51b54225406d36b641f5e41bbc52a56612a8c6d14")),
+        FieldElement::one(P.clone()),
+    ];
+    static ref Y_NUM: [FieldElement; 4] = [
+        constant(&hex!("4bda12f684bda12f684bda12f684bda12f684bda12f684bda12f684b8e38e23c")),
+        constant(&hex!("c75e0c32d5cb7c0fa9d0a54b12a0a6d5647ab046d686da6fdffc90fc201d71a3")),
+        constant(&hex!("29a6194691f91a73715209ef6512e576722830a201be2018a765e85a9ecee931")),
+        constant(&hex!("2f684bda12f684bda12f684bda12f684bda12f684bda12f684bda12f38e38d84")),
+    ];
+    static ref Y_DEN: [FieldElement; 4] = [
+        constant(&hex!("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffff93b")),
+        constant(&hex!("7a06534bb8bdb49fd5e9e6632722c2989467c1bfc8e8d978dfb425d2685c2573")),
+        constant(&hex!("6484aa716545ca2cf3a70c3fa8fe337e0a3d21162f0d6299a7bf8192bfd2a76f")),
+        FieldElement::one(P.clone()),
+    ];
+}
+
+/// expand_message_xmd with SHA-256 (RFC 9380 section 5.3.1)
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len_in_bytes: usize) -> Result<Vec<u8>> {
+    let ell = (len_in_bytes + B_IN_BYTES - 1) / B_IN_BYTES;
+    if ell > 255 || len_in_bytes > 65535 {
+        return Err(anyhow!("Cannot expand a message to {len_in_bytes} bytes"));
+    }
+    // long tags are hashed down first (section 5.3.3)
+    let dst = if dst.len() > 255 {
+        Sha256::new()
+            .chain_update(b"H2C-OVERSIZE-DST-")
+            .chain_update(dst)
+            .finalize()
+            .to_vec()
+    } else {
+        dst.to_vec()
+    };
+    let dst_prime = [dst.as_slice(), &[dst.len() as u8]].concat();
+
+    let b_0 = Sha256::new()
+        .chain_update([0u8; S_IN_BYTES])
+        .chain_update(msg)
+        .chain_update((len_in_bytes as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Sha256::new()
+        .chain_update(b_0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = b_i.to_vec();
+    for i in 2..=ell {
+        let mixed: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha256::new()
+            .chain_update(mixed)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len_in_bytes);
+    Ok(uniform_bytes)
+}
+
+/// hashes a message to `count` elements of the secp256k1 base field (section 5.2)
+pub fn hash_to_field(msg: &[u8], dst: &[u8], count: usize) -> Result<Vec<Field>> {
+    let uniform_bytes = expand_message_xmd(msg, dst, count * L)?;
+    Ok(uniform_bytes
+        .chunks(L)
+        .map(|chunk| Field::new(BigInt::from_bytes_be(Sign::Plus, chunk)))
+        .collect())
+}
+
+/// returns sqrt(value) if value is a square
+fn checked_sqrt(value: &FieldElement) -> Result<Option<FieldElement>> {
+    let Field(root) = Field(value.clone()).sqrt();
+    Ok((&(&root * &root)? == value).then_some(root))
+}
+
+/// sgn0 for prime fields (section 4.1)
+fn sgn0(value: &FieldElement) -> bool {
+    value.num.is_odd()
+}
+
+/// evaluates the polynomial with the given coefficients, lowest degree first
+fn evaluate(coefficients: &[FieldElement], x: &FieldElement) -> Result<FieldElement> {
+    coefficients
+        .iter()
+        .rev()
+        .try_fold(FieldElement::zero(P.clone()), |acc, c| (acc * x)? + c)
+}
+
+/// simplified SWU map to the isogenous curve E' (section 6.6.2)
+fn map_to_curve_simple_swu(u: &FieldElement) -> Result<(FieldElement, FieldElement)> {
+    let z_u2 = (&*Z * &(u * u)?)?;
+    let tv1 = (&(&z_u2 * &z_u2)? + &z_u2)?;
+    let one = FieldElement::one(P.clone());
+    let x1 = if tv1.is_zero() {
+        (&*ISO_B / &(&*Z * &*ISO_A)?)?
+    } else {
+        (&(-&*ISO_B / &*ISO_A)? * &(&one + &tv1.inverse()?)?)?
+    };
+    let g = |x: &FieldElement| -> Result<FieldElement> {
+        let ax = (&*ISO_A * x)?;
+        (x.pow(3) + ax)? + &*ISO_B
+    };
+    let (x, y) = match checked_sqrt(&g(&x1)?)? {
+        Some(y) => (x1, y),
+        None => {
+            let x2 = (&z_u2 * &x1)?;
+            let y = checked_sqrt(&g(&x2)?)?
+                .ok_or_else(|| anyhow!("Neither candidate is on the isogenous curve"))?;
+            (x2, y)
+        }
+    };
+    let y = if sgn0(u) != sgn0(&y) { -y } else { y };
+    Ok((x, y))
+}
+
+/// the 3-isogeny from E' to secp256k1 (appendix E.1)
+fn iso_map(x: &FieldElement, y: &FieldElement) -> Result<Point> {
+    let x_den = evaluate(&*X_DEN, x)?;
+    let y_den = evaluate(&*Y_DEN, x)?;
+    if x_den.is_zero() || y_den.is_zero() {
+        // exceptional points of the isogeny map to the identity
+        return Point::new(None);
+    }
+    let x_out = (evaluate(&*X_NUM, x)? / x_den)?;
+    let y_out = ((y * &evaluate(&*Y_NUM, x)?)? / y_den)?;
+    Point::new(Some(Coordinate::new(Field(x_out), Field(y_out))))
+}
+
+/// maps a field element to a point of secp256k1
+pub fn map_to_curve(u: &Field) -> Result<Point> {
+    let (x, y) = map_to_curve_simple_swu(&u.0)?;
+    iso_map(&x, &y)
+}
+
+/// Hashes a message to a uniformly distributed point, suitable for random oracles.
+/// secp256k1 has cofactor 1, so no cofactor clearing is needed.
+pub fn hash_to_curve(msg: &[u8], dst: &[u8]) -> Result<Point> {
+    let u = hash_to_field(msg, dst, 2)?;
+    map_to_curve(&u[0])? + map_to_curve(&u[1])?
+}
+
+/// Encodes a message to a point, cheaper than [`hash_to_curve`] but not uniformly distributed.
+pub fn encode_to_curve(msg: &[u8], dst: &[u8]) -> Result<Point> {
+    let u = hash_to_field(msg, dst, 1)?;
+    map_to_curve(&u[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RO_DST: &[u8] = b"QUUX-V01-CS02-with-secp256k1_XMD:SHA-256_SSWU_RO_";
+    const NU_DST: &[u8] = b"QUUX-V01-CS02-with-secp256k1_XMD:SHA-256_SSWU_NU_";
+
+    fn point(x: [u8; 32], y: [u8; 32]) -> Point {
+        Point::new(Some(Coordinate::new(
+            x.as_slice().into(),
+            y.as_slice().into(),
+        )))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let vectors: [(&[u8], usize, &[u8]); 4] = [
+            (
+                b"",
+                0x20,
+                &hex!("68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"),
+            ),
+            (
+                b"abc",
+                0x20,
+                &hex!("d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"),
+            ),
+            (
+                b"abcdef0123456789",
+                0x20,
+                &hex!("eff31487c770a893cfb36f912fbfcbff40d5661771ca4b2cb4eafe524333f5c1"),
+            ),
+            (
+                b"",
+                0x80,
+                &hex!(
+                    "af84c27ccfd45d41914fdff5df25293e221afc53d8ad2ac06d5e3e29485dadbe"
+                    "e0d121587713a3e0dd4d5e69e93eb7cd4f5df4cd103e188cf60cb02edc3edf18"
+                    "eda8576c412b18ffb658e3dd6ec849469b979d444cf7b26911a08e63cf31f9dc"
+                    "c541708d3491184472c2c29bb749d4286b004ceb5ee6b9a7fa5b646c993f0ced"
+                ),
+            ),
+        ];
+        for (msg, len, expected) in vectors {
+            assert_eq!(expand_message_xmd(msg, dst, len).unwrap(), expected);
+        }
+        assert!(expand_message_xmd(b"", dst, 256 * 32).is_err());
+    }
+
+    #[test]
+    fn test_hash_to_curve() {
+        let u = hash_to_field(b"", RO_DST, 2).unwrap();
+        assert_eq!(
+            u,
+            [
+                Field::from(
+                    hex!("6b0f9910dd2ba71c78f2ee9f04d73b5f4c5f7fc773a701abea1e573cab002fb3")
+                        .as_slice()
+                ),
+                Field::from(
+                    hex!("1ae6c212e08fe1a5937f6202f929a2cc8ef4ee5b9782db68b0d5799fd8f09e16")
+                        .as_slice()
+                ),
+            ]
+        );
+        assert_eq!(
+            map_to_curve(&u[0]).unwrap(),
+            point(
+                hex!("74519ef88b32b425a095e4ebcc84d81b64e9e2c2675340a720bb1a1857b99f1e"),
+                hex!("c174fa322ab7c192e11748beed45b508e9fdb1ce046dee9c2cd3a2a86b410936"),
+            )
+        );
+        assert_eq!(
+            map_to_curve(&u[1]).unwrap(),
+            point(
+                hex!("44548adb1b399263ded3510554d28b4bead34b8cf9a37b4bd0bd2ba4db87ae63"),
+                hex!("96eb8e2faf05e368efe5957c6167001760233e6dd2487516b46ae725c4cce0c6"),
+            )
+        );
+
+        let vectors: [(&[u8], [u8; 32], [u8; 32]); 3] = [
+            (
+                b"",
+                hex!("c1cae290e291aee617ebaef1be6d73861479c48b841eaba9b7b5852ddfeb1346"),
+                hex!("64fa678e07ae116126f08b022a94af6de15985c996c3a91b64c406a960e51067"),
+            ),
+            (
+                b"abc",
+                hex!("3377e01eab42db296b512293120c6cee72b6ecf9f9205760bd9ff11fb3cb2c4b"),
+                hex!("7f95890f33efebd1044d382a01b1bee0900fb6116f94688d487c6c7b9c8371f6"),
+            ),
+            (
+                b"abcdef0123456789",
+                hex!("bac54083f293f1fe08e4a70137260aa90783a5cb84d3f35848b324d0674b0e3a"),
+                hex!("4436476085d4c3c4508b60fcf4389c40176adce756b398bdee27bca19758d828"),
+            ),
+        ];
+        for (msg, x, y) in vectors {
+            assert_eq!(hash_to_curve(msg, RO_DST).unwrap(), point(x, y));
+        }
+    }
+
+    #[test]
+    fn test_encode_to_curve() {
+        let u = hash_to_field(b"", NU_DST, 1).unwrap();
+        assert_eq!(
+            u[0],
+            Field::from(
+                hex!("0137fcd23bc3da962e8808f97474d097a6c8aa2881fceef4514173635872cf3b").as_slice()
+            )
+        );
+
+        let q128 = format!("q128_{}", "q".repeat(128));
+        let vectors: [(&[u8], [u8; 32], [u8; 32]); 4] = [
+            (
+                b"",
+                hex!("a4792346075feae77ac3b30026f99c1441b4ecf666ded19b7522cf65c4c55c5b"),
+                hex!("62c59e2a6aeed1b23be5883e833912b08ba06be7f57c0e9cdc663f31639ff3a7"),
+            ),
+            (
+                b"abc",
+                hex!("3f3b5842033fff837d504bb4ce2a372bfeadbdbd84a1d2b678b6e1d7ee426b9d"),
+                hex!("902910d1fef15d8ae2006fc84f2a5a7bda0e0407dc913062c3a493c4f5d876a5"),
+            ),
+            (
+                b"abcdef0123456789",
+                hex!("07644fa6281c694709f53bdd21bed94dab995671e4a8cd1904ec4aa50c59bfdf"),
+                hex!("c79f8d1dad79b6540426922f7fbc9579c3018dafeffcd4552b1626b506c21e7b"),
+            ),
+            (
+                q128.as_bytes(),
+                hex!("b734f05e9b9709ab631d960fa26d669c4aeaea64ae62004b9d34f483aa9acc33"),
+                hex!("03fc8a4a5a78632e2eb4d8460d69ff33c1d72574b79a35e402e801f2d0b1d6ee"),
+            ),
+        ];
+        for (msg, x, y) in vectors {
+            assert_eq!(encode_to_curve(msg, NU_DST).unwrap(), point(x, y));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Bitcoin Signed Message (BIP137): compact recoverable signatures of
+//! SHA256d(varint || "Bitcoin Signed Message:\n" || varint || message), in base64.
+//! The header byte tells which kind of address signed: 27-30 uncompressed P2PKH,
+//! 31-34 compressed P2PKH, 35-38 P2SH-P2WPKH and 39-42 P2WPKH.
+
+use super::address::Network;
+use super::recovery::recover;
+use super::transaction::{compact_size, sha256d};
+use super::{PrivateKey, RecoverableSignature};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use num_bigint::{BigInt, Sign};
+
+const MESSAGE_PREFIX: &[u8] = b"Bitcoin Signed Message:\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    P2pkhUncompressed,
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+}
+
+impl AddressType {
+    fn header_base(self) -> u8 {
+        match self {
+            AddressType::P2pkhUncompressed => 27,
+            AddressType::P2pkh => 31,
+            AddressType::P2shP2wpkh => 35,
+            AddressType::P2wpkh => 39,
+        }
+    }
+
+    fn from_header(header: u8) -> Result<Self> {
+        match header {
+            27..=30 => Ok(AddressType::P2pkhUncompressed),
+            31..=34 => Ok(AddressType::P2pkh),
+            35..=38 => Ok(AddressType::P2shP2wpkh),
+            39..=42 => Ok(AddressType::P2wpkh),
+            _ => Err(anyhow!("Unknown signature header {header}")),
+        }
+    }
+}
+
+/// returns the double SHA-256 of the prefixed message as the number to sign
+pub fn message_hash(message: &[u8]) -> BigInt {
+    let mut data = compact_size(MESSAGE_PREFIX.len());
+    data.extend_from_slice(MESSAGE_PREFIX);
+    data.extend(compact_size(message.len()));
+    data.extend_from_slice(message);
+    BigInt::from_bytes_be(Sign::Plus, &sha256d(&data))
+}
+
+impl PrivateKey {
+    /// Signs the message for an address of the given type, returns the base64 signature
+    pub fn sign_message(&self, message: &[u8], address_type: AddressType) -> Result<String> {
+        let sig = self
+            .sign_recoverable(&message_hash(message))
+            .ok_or_else(|| anyhow!("Secret is not in [1, n)"))?;
+        let mut compact = sig.compact(false);
+        compact[0] = address_type.header_base() + sig.recid();
+        Ok(STANDARD.encode(compact))
+    }
+}
+
+/// Verifies a base64 signature of the message against the address, on either network
+pub fn verify_message(address: &str, message: &[u8], signature: &str) -> bool {
+    let check = || -> Result<bool> {
+        let mut compact = STANDARD.decode(signature)?;
+        let address_type = AddressType::from_header(*compact.first().unwrap_or(&0))?;
+        // normalize the header to the plain recovery id
+        compact[0] = 27 + (compact[0] - 27) % 4;
+        let (sig, _) = RecoverableSignature::from_compact(&compact)?;
+        let z = message_hash(message);
+        let public = recover(&z, &sig)?;
+        for network in [Network::Mainnet, Network::Testnet] {
+            let expected = match address_type {
+                AddressType::P2pkhUncompressed => public.p2pkh(false, network)?,
+                AddressType::P2pkh => public.p2pkh(true, network)?,
+                AddressType::P2shP2wpkh => public.p2sh_p2wpkh(network)?,
+                AddressType::P2wpkh => public.p2wpkh(network)?,
+            };
+            let matches = match address_type {
+                // bech32 may be all uppercase but never mixed case, base58 is case-sensitive
+                AddressType::P2wpkh => {
+                    (address == address.to_lowercase() || address == address.to_uppercase())
+                        && expected.eq_ignore_ascii_case(address)
+                }
+                _ => expected == address,
+            };
+            if matches && public.verify(&z, sig.signature()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+    check().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::Point;
+
+    #[test]
+    fn test_message_hash() {
+        assert_eq!(
+            message_hash(b"test"),
+            BigInt::from_bytes_be(
+                Sign::Plus,
+                &hex_literal::hex!(
+                    "9ce428d58e8e4caf619dc6fc7b2c2c28f0561654d1f80f322c038ad5e67ff8a6"
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_verify_known_signature() {
+        // compressed P2PKH signature made with rust-bitcoin/libsecp256k1
+        let public = Point::try_from(
+            STANDARD
+                .decode("A1FTfMEntPpAty3qkEo0q2Dc1FEycI10a3jmwEFy+Qr6")
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        let address = public.p2pkh(true, Network::Mainnet).unwrap();
+        assert_eq!(address, "1DUm3t5rF5yMD6r1VbCmgbPDe6LAVYVGrQ");
+        let signature = "IAM2qX24tYx/bdBTIgVLhD8QEAjrPlJpmjB4nZHdRYGIBa4DmVulAcwjPnWe6Q5iEwXH6F0pUCJP/ZeHPWS1h1o=";
+        assert!(verify_message(
+            &address,
+            b"rust-bitcoin MessageSignature test",
+            signature
+        ));
+        assert!(verify_message(
+            "msziLwAq47QbzDKdDAB9WWbYW5vsRf7xGc",
+            b"rust-bitcoin MessageSignature test",
+            signature
+        ));
+        assert!(!verify_message(
+            &address,
+            b"a different message from what was signed",
+            signature
+        ));
+        assert!(!verify_message(
+            &public.p2wpkh(Network::Mainnet).unwrap(),
+            b"rust-bitcoin MessageSignature test",
+            signature
+        ));
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let key = PrivateKey::new(BigInt::from(0x5eed_u32));
+        let public = key.point();
+        let message = b"I own this address";
+        let cases = [
+            (
+                AddressType::P2pkhUncompressed,
+                public.p2pkh(false, Network::Mainnet).unwrap(),
+            ),
+            (
+                AddressType::P2pkh,
+                public.p2pkh(true, Network::Mainnet).unwrap(),
+            ),
+            (
+                AddressType::P2shP2wpkh,
+                public.p2sh_p2wpkh(Network::Mainnet).unwrap(),
+            ),
+            (
+                AddressType::P2wpkh,
+                public.p2wpkh(Network::Testnet).unwrap(),
+            ),
+        ];
+        for (address_type, address) in &cases {
+            let signature = key.sign_message(message, *address_type).unwrap();
+            assert!(
+                verify_message(address, message, &signature),
+                "{address_type:?}"
+            );
+            assert!(!verify_message(address, b"I own that address", &signature));
+            // the header binds the signature to one kind of address
+            for (other_type, other_address) in &cases {
+                if other_type != address_type {
+                    assert!(!verify_message(other_address, message, &signature));
+                }
+            }
+        }
+
+        // base58 addresses are case-sensitive, bech32 ones may only be all one case
+        let signature = key.sign_message(message, AddressType::P2pkh).unwrap();
+        let recased = cases[1].1.to_lowercase();
+        assert_ne!(recased, cases[1].1);
+        assert!(!verify_message(&recased, message, &signature));
+        let signature = key.sign_message(message, AddressType::P2wpkh).unwrap();
+        let address = &cases[3].1;
+        assert!(verify_message(&address.to_uppercase(), message, &signature));
+        let mixed = address[..4].to_uppercase() + &address[4..];
+        assert!(!verify_message(&mixed, message, &signature));
+
+        assert!(!verify_message(&cases[1].1, message, "not base64!"));
+        assert!(!verify_message(&cases[1].1, message, ""));
+    }
+}
@@ -0,0 +1,863 @@
+//! MuSig2 n-of-n Schnorr multi-signatures (BIP327). The signers aggregate their plain
+//! public keys into one x-only key, exchange two public nonces each, and sum their partial
+//! signatures into a BIP340 signature that verifies under the aggregate key.
+
+use super::schnorr::{self, challenge, int, tagged_hash, x_bytes, XOnlyPublicKey};
+use super::sec_format::{to_32_bytes, Compressed};
+use super::{Point, PrivateKey, G, N};
+use anyhow::{anyhow, Result};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use rand::{thread_rng, RngCore};
+
+/// returns the compressed SEC format of a finite point
+fn cbytes(point: &Point) -> Result<[u8; 33]> {
+    point
+        .sec::<Compressed>()
+        .ok_or_else(|| anyhow!("The point at infinity has no compressed encoding"))
+}
+
+/// returns the compressed SEC format, 33 zero bytes for the point at infinity
+fn cbytes_ext(point: &Point) -> [u8; 33] {
+    point.sec::<Compressed>().unwrap_or([0; 33])
+}
+
+/// parses a compressed SEC point, 33 zero bytes standing for the point at infinity
+fn cpoint_ext(bytes: &[u8]) -> Result<Point> {
+    if bytes == [0; 33] {
+        Point::new(None)
+    } else {
+        cpoint(bytes)
+    }
+}
+
+fn cpoint(bytes: &[u8]) -> Result<Point> {
+    match bytes {
+        [0x02 | 0x03, ..] if bytes.len() == 33 => Point::try_from(bytes),
+        _ => Err(anyhow!("Invalid compressed point {}", hex::encode(bytes))),
+    }
+}
+
+/// returns 1 if the point has even y, n - 1 otherwise
+fn parity_factor(point: &Point) -> BigInt {
+    if point.has_even_y() {
+        BigInt::from(1)
+    } else {
+        &*N - 1
+    }
+}
+
+/// returns the public keys sorted lexicographically by their compressed encoding
+pub fn key_sort(pubkeys: &[Point]) -> Result<Vec<Point>> {
+    let mut keyed = pubkeys
+        .iter()
+        .map(|key| Ok((cbytes(key)?, key.clone())))
+        .collect::<Result<Vec<_>>>()?;
+    keyed.sort_by_key(|(sec, _)| *sec);
+    Ok(keyed.into_iter().map(|(_, key)| key).collect())
+}
+
+/// The aggregate key of a list of signers with the tweaks applied to it so far
+#[derive(Debug, Clone)]
+pub struct KeyAggContext {
+    pubkeys: Vec<[u8; 33]>,
+    q: Point,
+    gacc: BigInt,
+    tacc: BigInt,
+}
+
+impl KeyAggContext {
+    /// Aggregates the public keys in the given order, which every signer has to agree on
+    pub fn new(pubkeys: &[Point]) -> Result<Self> {
+        let pubkeys = pubkeys.iter().map(cbytes).collect::<Result<Vec<_>>>()?;
+        let mut ctx = Self {
+            pubkeys,
+            q: Point::new(None)?,
+            gacc: BigInt::from(1),
+            tacc: BigInt::from(0),
+        };
+        let terms = ctx
+            .pubkeys
+            .iter()
+            .map(|pk| Ok(&cpoint(pk)? * &ctx.coefficient(pk)))
+            .collect::<Result<Vec<_>>>()?;
+        ctx.q = Point::combine(&terms).map_err(|_| anyhow!("Aggregate key is invalid"))?;
+        Ok(ctx)
+    }
+
+    /// returns the KeyAgg coefficient of the key, 1 for the second distinct key in the list
+    fn coefficient(&self, pk: &[u8; 33]) -> BigInt {
+        let second = self
+            .pubkeys
+            .iter()
+            .find(|key| *key != &self.pubkeys[0])
+            .copied()
+            .unwrap_or([0; 33]);
+        if pk == &second {
+            return BigInt::from(1);
+        }
+        let list = tagged_hash("KeyAgg list", &self.pubkeys.concat());
+        int(&tagged_hash(
+            "KeyAgg coefficient",
+            &[&list[..], pk].concat(),
+        ))
+        .mod_floor(&N)
+    }
+
+    /// returns the aggregate point, including the tweaks
+    pub fn aggregate_point(&self) -> &Point {
+        &self.q
+    }
+
+    /// returns the x-only aggregate key the final signature verifies under
+    pub fn aggregate_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::try_from(x_bytes(&self.q).as_slice())
+            .expect("the aggregate key is a finite point")
+    }
+
+    /// Tweaks the aggregate key, `x_only` tweaks act on the even-y version of the key as
+    /// taproot does, plain ones on the key as is as BIP32 does
+    pub fn apply_tweak(&self, tweak: &[u8; 32], x_only: bool) -> Result<Self> {
+        let g = if x_only {
+            parity_factor(&self.q)
+        } else {
+            BigInt::from(1)
+        };
+        let t = int(tweak);
+        let q = (&self.q * &g)
+            .tweak_add(&t)
+            .map_err(|e| anyhow!("Cannot tweak the aggregate key: {e}"))?;
+        Ok(Self {
+            pubkeys: self.pubkeys.clone(),
+            q,
+            gacc: (g.clone() * &self.gacc).mod_floor(&N),
+            tacc: (t + g * &self.tacc).mod_floor(&N),
+        })
+    }
+}
+
+/// The two secret nonces of a signer, bound to its public key. It is consumed by signing
+/// so that it cannot be used twice.
+#[derive(Debug)]
+pub struct SecNonce {
+    k1: BigInt,
+    k2: BigInt,
+    public: [u8; 33],
+}
+
+/// The two public nonces a signer shares, `R1 = k1⋅G` and `R2 = k2⋅G`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PubNonce {
+    r1: Point,
+    r2: Point,
+}
+
+impl TryFrom<&[u8]> for PubNonce {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != 66 {
+            return Err(anyhow!("Public nonce must be 66 bytes"));
+        }
+        Ok(Self {
+            r1: cpoint(&value[..33])?,
+            r2: cpoint(&value[33..])?,
+        })
+    }
+}
+
+impl PubNonce {
+    pub fn serialize(&self) -> [u8; 66] {
+        let mut res = [0; 66];
+        res[..33].copy_from_slice(&cbytes_ext(&self.r1));
+        res[33..].copy_from_slice(&cbytes_ext(&self.r2));
+        res
+    }
+}
+
+/// returns `hash_MuSig/nonce(rand || pk || aggpk || msg || extra_in || i) mod n`
+fn nonce_hash(
+    rand: &[u8; 32],
+    public: &[u8; 33],
+    aggregate: &[u8],
+    msg: Option<&[u8]>,
+    extra_in: &[u8],
+    i: u8,
+) -> BigInt {
+    let mut data = rand.to_vec();
+    data.push(public.len() as u8);
+    data.extend_from_slice(public);
+    data.push(aggregate.len() as u8);
+    data.extend_from_slice(aggregate);
+    match msg {
+        Some(msg) => {
+            data.push(1);
+            data.extend_from_slice(&(msg.len() as u64).to_be_bytes());
+            data.extend_from_slice(msg);
+        }
+        None => data.push(0),
+    }
+    data.extend_from_slice(&(extra_in.len() as u32).to_be_bytes());
+    data.extend_from_slice(extra_in);
+    data.push(i);
+    int(&tagged_hash("MuSig/nonce", &data)).mod_floor(&N)
+}
+
+/// Generates a nonce pair from fresh randomness. The secret key, aggregate key, message
+/// and extra input are optional, each one known in advance adds defense in depth.
+pub fn nonce_gen(
+    secret: Option<&PrivateKey>,
+    public: &Point,
+    aggregate: Option<&XOnlyPublicKey>,
+    msg: Option<&[u8]>,
+    extra_in: Option<&[u8]>,
+) -> Result<(SecNonce, PubNonce)> {
+    let mut rand = [0; 32];
+    thread_rng().fill_bytes(&mut rand);
+    nonce_gen_with_rand(&rand, secret, public, aggregate, msg, extra_in)
+}
+
+/// Generates a nonce pair from the given randomness, which must never repeat
+pub fn nonce_gen_with_rand(
+    rand: &[u8; 32],
+    secret: Option<&PrivateKey>,
+    public: &Point,
+    aggregate: Option<&XOnlyPublicKey>,
+    msg: Option<&[u8]>,
+    extra_in: Option<&[u8]>,
+) -> Result<(SecNonce, PubNonce)> {
+    let mut rand = *rand;
+    if let Some(secret) = secret {
+        let aux = tagged_hash("MuSig/aux", &rand);
+        rand.iter_mut()
+            .zip(to_32_bytes(&secret.secret))
+            .zip(aux)
+            .for_each(|((r, s), a)| *r = s ^ a);
+    }
+    let public = cbytes(public)?;
+    let aggregate = aggregate.map(XOnlyPublicKey::serialize);
+    let aggregate = aggregate.as_ref().map_or(&[][..], |key| &key[..]);
+    let extra_in = extra_in.unwrap_or_default();
+    let k1 = nonce_hash(&rand, &public, aggregate, msg, extra_in, 0);
+    let k2 = nonce_hash(&rand, &public, aggregate, msg, extra_in, 1);
+    if k1 == BigInt::from(0) || k2 == BigInt::from(0) {
+        return Err(anyhow!("Nonce is zero"));
+    }
+    let pubnonce = PubNonce {
+        r1: &*G * &k1,
+        r2: &*G * &k2,
+    };
+    Ok((SecNonce { k1, k2, public }, pubnonce))
+}
+
+/// The sums of the signers' public nonces, either of which may be the point at infinity
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggNonce {
+    r1: Point,
+    r2: Point,
+}
+
+impl TryFrom<&[u8]> for AggNonce {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != 66 {
+            return Err(anyhow!("Aggregate nonce must be 66 bytes"));
+        }
+        Ok(Self {
+            r1: cpoint_ext(&value[..33])?,
+            r2: cpoint_ext(&value[33..])?,
+        })
+    }
+}
+
+impl AggNonce {
+    /// Sums the public nonces of all signers
+    pub fn aggregate(pubnonces: &[PubNonce]) -> Result<Self> {
+        let sum = |nonces: Vec<&Point>| {
+            nonces
+                .into_iter()
+                .try_fold(Point::new(None)?, |sum, nonce| sum + nonce)
+        };
+        Ok(Self {
+            r1: sum(pubnonces.iter().map(|nonce| &nonce.r1).collect())?,
+            r2: sum(pubnonces.iter().map(|nonce| &nonce.r2).collect())?,
+        })
+    }
+
+    pub fn serialize(&self) -> [u8; 66] {
+        PubNonce {
+            r1: self.r1.clone(),
+            r2: self.r2.clone(),
+        }
+        .serialize()
+    }
+}
+
+/// A signer's share of the final signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialSignature(BigInt);
+
+impl TryFrom<&[u8]> for PartialSignature {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != 32 {
+            return Err(anyhow!("Partial signature must be 32 bytes"));
+        }
+        let s = int(value);
+        if s >= *N {
+            return Err(anyhow!("Partial signature is not in [0, n)"));
+        }
+        Ok(Self(s))
+    }
+}
+
+impl PartialSignature {
+    pub fn serialize(&self) -> [u8; 32] {
+        to_32_bytes(&self.0)
+    }
+}
+
+/// Everything the signers derive from the key aggregation, the aggregate nonce and the
+/// message: the nonce coefficient b, the final nonce R and the challenge e
+#[derive(Debug, Clone)]
+pub struct Session {
+    ctx: KeyAggContext,
+    b: BigInt,
+    r: Point,
+    e: BigInt,
+}
+
+impl Session {
+    pub fn new(ctx: &KeyAggContext, aggnonce: &AggNonce, msg: &[u8]) -> Result<Self> {
+        let q = x_bytes(&ctx.q);
+        let data = [&aggnonce.serialize()[..], &q, msg].concat();
+        let b = int(&tagged_hash("MuSig/noncecoef", &data)).mod_floor(&N);
+        let r = (&aggnonce.r1 + &(&aggnonce.r2 * &b))?;
+        // an aggregate nonce at infinity cannot be steered to, it is replaced by G
+        let r = if r.coordinate().is_none() {
+            G.clone()
+        } else {
+            r
+        };
+        let e = challenge(&x_bytes(&r), &q, msg);
+        Ok(Self {
+            ctx: ctx.clone(),
+            b,
+            r,
+            e,
+        })
+    }
+
+    /// returns the KeyAgg coefficient of a signer, who must be part of the session
+    fn coefficient(&self, public: &Point) -> Result<BigInt> {
+        let pk = cbytes(public)?;
+        if !self.ctx.pubkeys.contains(&pk) {
+            return Err(anyhow!("The key is not one of the signers"));
+        }
+        Ok(self.ctx.coefficient(&pk))
+    }
+
+    /// Produces the signer's partial signature, consuming its secret nonce
+    pub fn sign(&self, secnonce: SecNonce, key: &PrivateKey) -> Result<PartialSignature> {
+        let SecNonce { k1, k2, public } = secnonce;
+        if k1 == BigInt::from(0) || k1 >= *N || k2 == BigInt::from(0) || k2 >= *N {
+            return Err(anyhow!("Secret nonce is not in [1, n)"));
+        }
+        if key.secret <= BigInt::from(0) || key.secret >= *N {
+            return Err(anyhow!("Secret is not in [1, n)"));
+        }
+        if public != cbytes(&key.point)? {
+            return Err(anyhow!("Secret nonce was generated for another key"));
+        }
+        let (k1, k2) = if self.r.has_even_y() {
+            (k1, k2)
+        } else {
+            (&*N - k1, &*N - k2)
+        };
+        let a = self.coefficient(&key.point)?;
+        let d = parity_factor(&self.ctx.q) * &self.ctx.gacc * &key.secret;
+        let s = (k1 + &self.b * k2 + &self.e * a * d).mod_floor(&N);
+        Ok(PartialSignature(s))
+    }
+
+    /// Checks the partial signature of a signer against its public nonce
+    pub fn verify_partial(
+        &self,
+        psig: &PartialSignature,
+        pubnonce: &PubNonce,
+        public: &Point,
+    ) -> bool {
+        let Ok(a) = self.coefficient(public) else {
+            return false;
+        };
+        if psig.0 >= *N {
+            return false;
+        }
+        let Ok(re) = &pubnonce.r1 + &(&pubnonce.r2 * &self.b) else {
+            return false;
+        };
+        let re = if self.r.has_even_y() { re } else { re.negate() };
+        let g = (parity_factor(&self.ctx.q) * &self.ctx.gacc).mod_floor(&N);
+        (re + &(public * &(&self.e * a * g)))
+            .map(|rhs| &*G * &psig.0 == rhs)
+            .unwrap_or_default()
+    }
+
+    /// Sums the partial signatures of all signers into a BIP340 signature
+    pub fn aggregate(&self, psigs: &[PartialSignature]) -> Result<schnorr::Signature> {
+        if psigs.iter().any(|psig| psig.0 >= *N) {
+            return Err(anyhow!("Partial signature is not in [0, n)"));
+        }
+        let g = parity_factor(&self.ctx.q);
+        let s = psigs
+            .iter()
+            .fold(&self.e * g * &self.ctx.tacc, |s, psig| s + &psig.0)
+            .mod_floor(&N);
+        let mut sig = x_bytes(&self.r).to_vec();
+        sig.extend_from_slice(&to_32_bytes(&s));
+        schnorr::Signature::try_from(sig.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::taproot::tap_tweak_hash;
+    use hex_literal::hex;
+    use num_bigint::RandBigInt;
+
+    fn points(keys: &[[u8; 33]]) -> Vec<Point> {
+        keys.iter()
+            .map(|key| Point::try_from(key.as_slice()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_key_agg_vectors() {
+        let keys = points(&[
+            hex!("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            hex!("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            hex!("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ]);
+        let vectors = [
+            (
+                vec![0, 1, 2],
+                hex!("90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C"),
+            ),
+            (
+                vec![2, 1, 0],
+                hex!("6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B"),
+            ),
+            (
+                vec![0, 0, 0],
+                hex!("B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935"),
+            ),
+            (
+                vec![0, 0, 1, 1],
+                hex!("69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E"),
+            ),
+        ];
+        for (indices, expected) in vectors {
+            let pubkeys: Vec<Point> = indices.iter().map(|&i| keys[i].clone()).collect();
+            let ctx = KeyAggContext::new(&pubkeys).unwrap();
+            assert_eq!(ctx.aggregate_key().serialize(), expected);
+        }
+        assert_eq!(
+            key_sort(&[keys[1].clone(), keys[0].clone(), keys[2].clone()]).unwrap(),
+            [keys[2].clone(), keys[0].clone(), keys[1].clone()]
+        );
+        assert!(KeyAggContext::new(&[keys[0].clone(), Point::new(None).unwrap()]).is_err());
+        assert!(KeyAggContext::new(&[]).is_err());
+
+        // the error cases: keys that are not on the curve, have x >= p or a bad prefix
+        for invalid in [
+            hex!("020000000000000000000000000000000000000000000000000000000000000005"),
+            hex!("02FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30"),
+            hex!("04F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+        ] {
+            assert!(cpoint(&invalid).is_err());
+        }
+        let ctx = KeyAggContext::new(&keys[..2]).unwrap();
+        let n = hex!("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+        assert!(ctx.apply_tweak(&n, true).is_err());
+        // the tweak is the negated secret of the key, so the result would be infinity
+        let ctx = KeyAggContext::new(&points(&[hex!(
+            "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"
+        )]))
+        .unwrap();
+        let tweak = hex!("252E4BD67410A76CDF933D30EAA1608214037F1B105A013ECCD3C5C184A6110B");
+        assert!(ctx.apply_tweak(&tweak, false).is_err());
+    }
+
+    #[test]
+    fn test_nonce_gen_vectors() {
+        let secret = PrivateKey::new(int(&[0x02; 32]));
+        let public = points(&[hex!(
+            "024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766"
+        )])
+        .remove(0);
+        let aggregate = XOnlyPublicKey::try_from([0x07; 32].as_slice()).unwrap();
+        let vectors: [(Option<&[u8]>, [u8; 64]); 3] = [
+            (
+                Some(&[0x01; 32]),
+                hex!("B114E502BEAA4E301DD08A50264172C84E41650E6CB726B410C0694D59EFFB6495B5CAF28D045B973D63E3C99A44B807BDE375FD6CB39E46DC4A511708D0E9D2"),
+            ),
+            (
+                Some(&[]),
+                hex!("E862B068500320088138468D47E0E6F147E01B6024244AE45EAC40ACE5929B9F0789E051170B9E705D0B9EB49049A323BBBBB206D8E05C19F46C6228742AA7A9"),
+            ),
+            (
+                Some(&[0x26; 38]),
+                hex!("3221975ACBDEA6820EABF02A02B7F27D3A8EF68EE42787B88CBEFD9AA06AF3632EE85B1A61D8EF31126D4663A00DD96E9D1D4959E72D70FE5EBB6E7696EBA66F"),
+            ),
+        ];
+        for (msg, expected) in vectors {
+            let (secnonce, pubnonce) = nonce_gen_with_rand(
+                &[0x0f; 32],
+                Some(&secret),
+                &public,
+                Some(&aggregate),
+                msg,
+                Some(&[0x08; 32]),
+            )
+            .unwrap();
+            assert_eq!(to_32_bytes(&secnonce.k1), expected[..32]);
+            assert_eq!(to_32_bytes(&secnonce.k2), expected[32..]);
+            assert_eq!(pubnonce.r1, &*G * &secnonce.k1);
+        }
+
+        let public = points(&[hex!(
+            "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"
+        )])
+        .remove(0);
+        let (secnonce, _) =
+            nonce_gen_with_rand(&[0x0f; 32], None, &public, None, None, None).unwrap();
+        assert_eq!(
+            [to_32_bytes(&secnonce.k1), to_32_bytes(&secnonce.k2)].concat(),
+            hex!("89BDD787D0284E5E4D5FC572E49E316BAB7E21E3B1830DE37DFE80156FA41A6D0B17AE8D024C53679699A6FD7944D9C4A366B514BAF43088E0708B1023DD2897")
+        );
+    }
+
+    #[test]
+    fn test_sign_vectors() {
+        let key = PrivateKey::new(int(&hex!(
+            "7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671"
+        )));
+        let keys = points(&[
+            hex!("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+            hex!("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            hex!("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661"),
+        ]);
+        let pubnonces: Vec<PubNonce> = [
+            hex!("0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480"),
+            hex!("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F817980279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798"),
+            hex!("032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE9303E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046"),
+        ]
+        .iter()
+        .map(|nonce| PubNonce::try_from(nonce.as_slice()).unwrap())
+        .collect();
+        let aggnonce = AggNonce::aggregate(&pubnonces).unwrap();
+        assert_eq!(
+            aggnonce.serialize(),
+            hex!("028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9")
+        );
+        assert_eq!(
+            AggNonce::try_from(aggnonce.serialize().as_slice()).unwrap(),
+            aggnonce
+        );
+        let msg = hex!("F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF");
+        let vectors = [
+            (
+                [0, 1, 2],
+                hex!("012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB"),
+            ),
+            (
+                [1, 0, 2],
+                hex!("9FF2F7AAA856150CC8819254218D3ADEEB0535269051897724F9DB3789513A52"),
+            ),
+            (
+                [1, 2, 0],
+                hex!("FA23C359F6FAC4E7796BB93BC9F0532A95468C539BA20FF86D7C76ED92227900"),
+            ),
+        ];
+        for (indices, expected) in vectors {
+            let pubkeys: Vec<Point> = indices.iter().map(|&i| keys[i].clone()).collect();
+            let session =
+                Session::new(&KeyAggContext::new(&pubkeys).unwrap(), &aggnonce, &msg).unwrap();
+            let secnonce = SecNonce {
+                k1: int(&hex!(
+                    "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61"
+                )),
+                k2: int(&hex!(
+                    "FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F7"
+                )),
+                public: cbytes(key.point()).unwrap(),
+            };
+            let psig = session.sign(secnonce, &key).unwrap();
+            assert_eq!(psig.serialize(), expected);
+            assert!(session.verify_partial(&psig, &pubnonces[0], key.point()));
+            assert!(!session.verify_partial(&psig, &pubnonces[1], key.point()));
+            assert!(!session.verify_partial(&psig, &pubnonces[0], &keys[1]));
+        }
+    }
+
+    #[test]
+    fn test_tweak_vectors() {
+        let key = PrivateKey::new(int(&hex!(
+            "7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671"
+        )));
+        let keys = points(&[
+            hex!("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+            hex!("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            hex!("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+        ]);
+        let pubnonces: Vec<PubNonce> = [
+            hex!("0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480"),
+            hex!("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F817980279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798"),
+            hex!("032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE9303E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046"),
+        ]
+        .iter()
+        .map(|nonce| PubNonce::try_from(nonce.as_slice()).unwrap())
+        .collect();
+        let tweaks = [
+            hex!("E8F791FF9225A2AF0102AFFF4A9A723D9612A682A25EBE79802B263CDFCD83BB"),
+            hex!("AE2EA797CC0FE72AC5B97B97F3C6957D7E4199A167A58EB08BCAFFDA70AC0455"),
+            hex!("F52ECBC565B3D8BEA2DFD5B75A4F457E54369809322E4120831626F290FA87E0"),
+            hex!("1969AD73CC177FA0B4FCED6DF1F7BF9907E665FDE9BA196A74FED0A3CF5AEF9D"),
+        ];
+        let msg = hex!("F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF");
+        // the signer is last in the key order [1, 2, 0]
+        let pubkeys = [keys[1].clone(), keys[2].clone(), keys[0].clone()];
+        let nonces = [
+            pubnonces[1].clone(),
+            pubnonces[2].clone(),
+            pubnonces[0].clone(),
+        ];
+        let aggnonce = AggNonce::aggregate(&nonces).unwrap();
+        let vectors = [
+            (
+                vec![true],
+                hex!("E28A5C66E61E178C2BA19DB77B6CF9F7E2F0F56C17918CD13135E60CC848FE91"),
+            ),
+            (
+                vec![false],
+                hex!("38B0767798252F21BF5702C48028B095428320F73A4B14DB1E25DE58543D2D2D"),
+            ),
+            (
+                vec![false, true],
+                hex!("408A0A21C4A0F5DACAF9646AD6EB6FECD7F7A11F03ED1F48DFFF2185BC2C2408"),
+            ),
+            (
+                vec![false, false, true, true],
+                hex!("45ABD206E61E3DF2EC9E264A6FEC8292141A633C28586388235541F9ADE75435"),
+            ),
+            (
+                vec![true, false, true, false],
+                hex!("B255FDCAC27B40C7CE7848E2D3B7BF5EA0ED756DA81565AC804CCCA3E1D5D239"),
+            ),
+        ];
+        for (x_only, expected) in vectors {
+            let ctx = tweaks.iter().zip(x_only).fold(
+                KeyAggContext::new(&pubkeys).unwrap(),
+                |ctx, (tweak, x_only)| ctx.apply_tweak(tweak, x_only).unwrap(),
+            );
+            let session = Session::new(&ctx, &aggnonce, &msg).unwrap();
+            let secnonce = SecNonce {
+                k1: int(&hex!(
+                    "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61"
+                )),
+                k2: int(&hex!(
+                    "FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F7"
+                )),
+                public: cbytes(key.point()).unwrap(),
+            };
+            let psig = session.sign(secnonce, &key).unwrap();
+            assert_eq!(psig.serialize(), expected);
+            assert!(session.verify_partial(&psig, &nonces[2], key.point()));
+        }
+        let ctx = KeyAggContext::new(&pubkeys).unwrap();
+        let n = hex!("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+        assert!(ctx.apply_tweak(&n, false).is_err());
+    }
+
+    #[test]
+    fn test_sig_agg_vectors() {
+        let keys = points(&[
+            hex!("03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9"),
+            hex!("02D2DC6F5DF7C56ACF38C7FA0AE7A759AE30E19B37359DFDE015872324C7EF6E05"),
+            hex!("03C7FB101D97FF930ACD0C6760852EF64E69083DE0B06AC6335724754BB4B0522C"),
+            hex!("02352433B21E7E05D3B452B81CAE566E06D2E003ECE16D1074AABA4289E0E3D581"),
+        ]);
+        let pubnonces: Vec<PubNonce> = [
+            hex!("036E5EE6E28824029FEA3E8A9DDD2C8483F5AF98F7177C3AF3CB6F47CAF8D94AE902DBA67E4A1F3680826172DA15AFB1A8CA85C7C5CC88900905C8DC8C328511B53E"),
+            hex!("03E4F798DA48A76EEC1C9CC5AB7A880FFBA201A5F064E627EC9CB0031D1D58FC5103E06180315C5A522B7EC7C08B69DCD721C313C940819296D0A7AB8E8795AC1F00"),
+            hex!("02C0068FD25523A31578B8077F24F78F5BD5F2422AFF47C1FADA0F36B3CEB6C7D202098A55D1736AA5FCC21CF0729CCE852575C06C081125144763C2C4C4A05C09B6"),
+            hex!("031F5C87DCFBFCF330DEE4311D85E8F1DEA01D87A6F1C14CDFC7E4F1D8C441CFA40277BF176E9F747C34F81B0D9F072B1B404A86F402C2D86CF9EA9E9C69876EA3B9"),
+            hex!("023F7042046E0397822C4144A17F8B63D78748696A46C3B9F0A901D296EC3406C302022B0B464292CF9751D699F10980AC764E6F671EFCA15069BBE62B0D1C62522A"),
+        ]
+        .iter()
+        .map(|nonce| PubNonce::try_from(nonce.as_slice()).unwrap())
+        .collect();
+        let tweaks = [
+            hex!("B511DA492182A91B0FFB9A98020D55F260AE86D7ECBD0399C7383D59A5F2AF7C"),
+            hex!("A815FE049EE3C5AAB66310477FBC8BCCCAC2F3395F59F921C364ACD78A2F48DC"),
+            hex!("75448A87274B056468B977BE06EB1E9F657577B7320B0A3376EA51FD420D18A8"),
+        ];
+        let psigs: Vec<PartialSignature> = [
+            hex!("B15D2CD3C3D22B04DAE438CE653F6B4ECF042F42CFDED7C41B64AAF9B4AF53FB"),
+            hex!("6193D6AC61B354E9105BBDC8937A3454A6D705B6D57322A5A472A02CE99FCB64"),
+            hex!("9A87D3B79EC67228CB97878B76049B15DBD05B8158D17B5B9114D3C226887505"),
+            hex!("66F82EA90923689B855D36C6B7E032FB9970301481B99E01CDB4D6AC7C347A15"),
+            hex!("4F5AEE41510848A6447DCD1BBC78457EF69024944C87F40250D3EF2C25D33EFE"),
+            hex!("DDEF427BBB847CC027BEFF4EDB01038148917832253EBC355FC33F4A8E2FCCE4"),
+            hex!("97B890A26C981DA8102D3BC294159D171D72810FDF7C6A691DEF02F0F7AF3FDC"),
+            hex!("53FA9E08BA5243CBCB0D797C5EE83BC6728E539EB76C2D0BF0F971EE4E909971"),
+        ]
+        .iter()
+        .map(|psig| PartialSignature::try_from(psig.as_slice()).unwrap())
+        .collect();
+        let msg = hex!("599C67EA410D005B9DA90817CF03ED3B1C868E4DA4EDF00A5880B0082C237869");
+        let vectors = [
+            (
+                hex!("0341432722C5CD0268D829C702CF0D1CBCE57033EED201FD335191385227C3210C03D377F2D258B64AADC0E16F26462323D701D286046A2EA93365656AFD9875982B"),
+                [0, 1],
+                [0, 1],
+                vec![],
+                [0, 1],
+                hex!("041DA22223CE65C92C9A0D6C2CAC828AAF1EEE56304FEC371DDF91EBB2B9EF0912F1038025857FEDEB3FF696F8B99FA4BB2C5812F6095A2E0004EC99CE18DE1E"),
+            ),
+            (
+                hex!("0224AFD36C902084058B51B5D36676BBA4DC97C775873768E58822F87FE437D792028CB15929099EEE2F5DAE404CD39357591BA32E9AF4E162B8D3E7CB5EFE31CB20"),
+                [0, 2],
+                [0, 2],
+                vec![],
+                [2, 3],
+                hex!("1069B67EC3D2F3C7C08291ACCB17A9C9B8F2819A52EB5DF8726E17E7D6B52E9F01800260A7E9DAC450F4BE522DE4CE12BA91AEAF2B4279219EF74BE1D286ADD9"),
+            ),
+            (
+                hex!("0208C5C438C710F4F96A61E9FF3C37758814B8C3AE12BFEA0ED2C87FF6954FF186020B1816EA104B4FCA2D304D733E0E19CEAD51303FF6420BFD222335CAA402916D"),
+                [0, 3],
+                [0, 2],
+                vec![false],
+                [4, 5],
+                hex!("5C558E1DCADE86DA0B2F02626A512E30A22CF5255CAEA7EE32C38E9A71A0E9148BA6C0E6EC7683B64220F0298696F1B878CD47B107B81F7188812D593971E0CC"),
+            ),
+            (
+                hex!("02B5AD07AFCD99B6D92CB433FBD2A28FDEB98EAE2EB09B6014EF0F8197CD58403302E8616910F9293CF692C49F351DB86B25E352901F0E237BAFDA11F1C1CEF29FFD"),
+                [0, 4],
+                [0, 3],
+                vec![true, false, true],
+                [6, 7],
+                hex!("839B08820B681DBA8DAF4CC7B104E8F2638F9388F8D7A555DC17B6E6971D7426CE07BF6AB01F1DB50E4E33719295F4094572B79868E440FB3DEFD3FAC1DB589E"),
+            ),
+        ];
+        for (expected_aggnonce, nonce_indices, key_indices, x_only, psig_indices, expected) in
+            vectors
+        {
+            let nonces: Vec<PubNonce> = nonce_indices
+                .iter()
+                .map(|&i| pubnonces[i].clone())
+                .collect();
+            let aggnonce = AggNonce::aggregate(&nonces).unwrap();
+            assert_eq!(aggnonce.serialize(), expected_aggnonce);
+            let pubkeys: Vec<Point> = key_indices.iter().map(|&i| keys[i].clone()).collect();
+            let ctx = tweaks.iter().zip(x_only).fold(
+                KeyAggContext::new(&pubkeys).unwrap(),
+                |ctx, (tweak, x_only)| ctx.apply_tweak(tweak, x_only).unwrap(),
+            );
+            let session = Session::new(&ctx, &aggnonce, &msg).unwrap();
+            let psigs: Vec<PartialSignature> =
+                psig_indices.iter().map(|&i| psigs[i].clone()).collect();
+            let sig = session.aggregate(&psigs).unwrap();
+            assert_eq!(sig.serialize(), expected);
+            assert!(ctx.aggregate_key().verify(&msg, &sig));
+        }
+        // a partial signature of n is out of range
+        let n = hex!("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+        assert!(PartialSignature::try_from(n.as_slice()).is_err());
+    }
+
+    /// runs a full signing session, returns the aggregate key and the final signature
+    fn sign_all(
+        keys: &[PrivateKey],
+        tweaks: &[([u8; 32], bool)],
+        msg: &[u8],
+    ) -> (XOnlyPublicKey, schnorr::Signature) {
+        let pubkeys: Vec<Point> = keys.iter().map(|key| key.point().clone()).collect();
+        let ctx = tweaks.iter().fold(
+            KeyAggContext::new(&pubkeys).unwrap(),
+            |ctx, (tweak, x_only)| ctx.apply_tweak(tweak, *x_only).unwrap(),
+        );
+        let aggregate = ctx.aggregate_key();
+        let (secnonces, pubnonces): (Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|key| {
+                nonce_gen(Some(key), key.point(), Some(&aggregate), Some(msg), None).unwrap()
+            })
+            .unzip();
+        let aggnonce = AggNonce::aggregate(&pubnonces).unwrap();
+        let session = Session::new(&ctx, &aggnonce, msg).unwrap();
+        let psigs: Vec<PartialSignature> = keys
+            .iter()
+            .zip(secnonces)
+            .zip(&pubnonces)
+            .map(|((key, secnonce), pubnonce)| {
+                let psig = session.sign(secnonce, key).unwrap();
+                assert!(session.verify_partial(&psig, pubnonce, key.point()));
+                psig
+            })
+            .collect();
+        (aggregate, session.aggregate(&psigs).unwrap())
+    }
+
+    #[test]
+    fn test_signing_session() {
+        let keys: Vec<PrivateKey> = (0..3)
+            .map(|_| PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N)))
+            .collect();
+        let msg = b"n-of-n";
+        let tweak = |byte| ([byte; 32], byte % 2 == 0);
+        for tweaks in [vec![], vec![tweak(1)], vec![tweak(2), tweak(3), tweak(4)]] {
+            let (aggregate, sig) = sign_all(&keys, &tweaks, msg);
+            assert!(aggregate.verify(msg, &sig));
+            assert!(!aggregate.verify(b"n-of-m", &sig));
+        }
+
+        // an x-only tweak by the TapTweak hash gives the BIP86 output key
+        let pubkeys: Vec<Point> = keys.iter().map(|key| key.point().clone()).collect();
+        let ctx = KeyAggContext::new(&pubkeys).unwrap();
+        let internal = ctx.aggregate_key();
+        let tweaked = ctx
+            .apply_tweak(&tap_tweak_hash(&internal, None), true)
+            .unwrap();
+        assert_eq!(tweaked.aggregate_key(), internal.tap_tweak(None).unwrap().0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let alice = PrivateKey::new(BigInt::from(11));
+        let bob = PrivateKey::new(BigInt::from(12));
+        let ctx = KeyAggContext::new(&[alice.point().clone(), bob.point().clone()]).unwrap();
+        let (alice_nonce, alice_pubnonce) =
+            nonce_gen(Some(&alice), alice.point(), None, None, None).unwrap();
+        let (_, bob_pubnonce) = nonce_gen(None, bob.point(), None, None, None).unwrap();
+        let aggnonce = AggNonce::aggregate(&[alice_pubnonce, bob_pubnonce]).unwrap();
+        let session = Session::new(&ctx, &aggnonce, b"msg").unwrap();
+        // the nonce belongs to alice
+        assert!(session.sign(alice_nonce, &bob).is_err());
+        // carol is not a signer
+        let carol = PrivateKey::new(BigInt::from(13));
+        let (carol_nonce, _) = nonce_gen(Some(&carol), carol.point(), None, None, None).unwrap();
+        assert!(session.sign(carol_nonce, &carol).is_err());
+
+        assert!(PubNonce::try_from([0; 66].as_slice()).is_err());
+        assert!(PubNonce::try_from([2; 65].as_slice()).is_err());
+        // aggregate nonces may be at infinity
+        let infinity = AggNonce::try_from([0; 66].as_slice()).unwrap();
+        assert!(Session::new(&ctx, &infinity, b"msg").is_ok());
+        assert!(PartialSignature::try_from(to_32_bytes(&N).as_slice()).is_err());
+        assert!(ctx.apply_tweak(&to_32_bytes(&N), false).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Public key recovery from ECDSA signatures and the 65-byte compact recoverable format
+//! `header || r || s` with header = 27 + recid, plus 4 when the key is meant to be compressed.
+
+use super::sec_format::to_32_bytes;
+use super::{Point, PrivateKey, RecoverableSignature, Signature, N};
+use crate::ecc::{curve_params::SECP256K1, ecdsa};
+use anyhow::{anyhow, Result};
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+
+impl PrivateKey {
+    /// Signs z keeping the recovery id, so the public key does not have to be shipped
+    pub fn sign_recoverable(&self, z: &BigInt) -> Option<RecoverableSignature> {
+        ecdsa::sign_recoverable(&SECP256K1, &self.secret.mod_floor(&N), z)
+    }
+}
+
+/// Recovers the public key that signed z
+pub fn recover(z: &BigInt, sig: &RecoverableSignature) -> Result<Point> {
+    ecdsa::recover(&SECP256K1, z, sig)
+        .map(Point)
+        .ok_or_else(|| anyhow!("Public key cannot be recovered from the signature"))
+}
+
+impl RecoverableSignature {
+    /// returns the 65-byte compact serialization, `compressed` tells which SEC format
+    /// of the recovered key the signature commits to
+    pub fn compact(&self, compressed: bool) -> [u8; 65] {
+        let mut res = [0; 65];
+        res[0] = 27 + self.recid + if compressed { 4 } else { 0 };
+        res[1..33].copy_from_slice(&to_32_bytes(&self.signature.r));
+        res[33..65].copy_from_slice(&to_32_bytes(&self.signature.s));
+        res
+    }
+
+    /// parses the 65-byte compact serialization, returns the signature and the compression flag
+    pub fn from_compact(value: &[u8]) -> Result<(Self, bool)> {
+        if value.len() != 65 {
+            return Err(anyhow!("Compact signature must be 65 bytes"));
+        }
+        let header = value[0];
+        if !(27..=34).contains(&header) {
+            return Err(anyhow!("Unknown compact signature header {header}"));
+        }
+        let compressed = header >= 31;
+        let recid = (header - 27) & 3;
+        let signature = Signature::new(
+            BigInt::from_bytes_be(Sign::Plus, &value[1..33]),
+            BigInt::from_bytes_be(Sign::Plus, &value[33..65]),
+        );
+        Ok((Self::new(signature, recid), compressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::sec_format::Compressed;
+    use hex_literal::hex;
+    use num_bigint::RandBigInt;
+    use rand::thread_rng;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_recover() {
+        // signatures of SHA-256("example message") with recovery ids 0 and 1
+        let z = BigInt::from_bytes_be(Sign::Plus, &Sha256::digest(b"example message"));
+        let vectors = [
+            (
+                hex!("021a7a569e91dbf60581509c7fc946d1003b60c7dee85299538db6353538d59574"),
+                hex!("ce53abb3721bafc561408ce8ff99c909f7f0b18a2f788649d6470162ab1aa032"),
+                hex!("3971edc523a6d6453f3fb6128d318d9db1a5ff3386feb1047d9816e780039d52"),
+                0,
+            ),
+            (
+                hex!("036d6caac248af96f6afa7f904f550253a0f3ef3f5aa2fe6838a95b216691468e2"),
+                hex!("46c05b6368a44b8810d79859441d819b8e7cdc8bfd371e35c53196f4bcacdb51"),
+                hex!("35c7facce2a97b95eacba8a586d87b7958aaf8368ab29cee481f76e871dbd9cb"),
+                1,
+            ),
+        ];
+        for (public, r, s, recid) in vectors {
+            let sig = RecoverableSignature::new(
+                Signature::from((
+                    BigInt::from_bytes_be(Sign::Plus, &r),
+                    BigInt::from_bytes_be(Sign::Plus, &s),
+                )),
+                recid,
+            );
+            assert_eq!(
+                recover(&z, &sig).unwrap().sec::<Compressed>().unwrap(),
+                public
+            );
+            // the other parity leads to another key, which does not verify
+            let flipped = RecoverableSignature::new(sig.signature().clone(), recid ^ 1);
+            assert_ne!(
+                recover(&z, &flipped).ok(),
+                Some(Point::try_from(public.as_slice()).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_recoverable() {
+        for _ in 0..3 {
+            let key = PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N));
+            let z = thread_rng().gen_bigint(256);
+            let sig = key.sign_recoverable(&z).unwrap();
+            assert!(key.point().verify(&z, sig.signature()));
+            assert_eq!(&recover(&z, &sig).unwrap(), key.point());
+        }
+    }
+
+    #[test]
+    fn test_compact() {
+        let key = PrivateKey::new(BigInt::from(0xbeefu32));
+        let z = BigInt::from(1234567);
+        let sig = key.sign_recoverable(&z).unwrap();
+        for compressed in [false, true] {
+            let compact = sig.compact(compressed);
+            assert_eq!(compact[0], 27 + sig.recid() + 4 * u8::from(compressed));
+            let (parsed, parsed_compressed) = RecoverableSignature::from_compact(&compact).unwrap();
+            assert_eq!(parsed, sig);
+            assert_eq!(parsed_compressed, compressed);
+            assert_eq!(&recover(&z, &parsed).unwrap(), key.point());
+        }
+        assert!(RecoverableSignature::from_compact(&[0; 65]).is_err());
+        assert!(RecoverableSignature::from_compact(&[27; 64]).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! BIP340 Schnorr signatures over secp256k1 with x-only public keys.
+
+use super::sec_format::to_32_bytes;
+use super::{Point, PrivateKey, G, N, P};
+use anyhow::{anyhow, Result};
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// returns SHA256(SHA256(tag) || SHA256(tag) || msg)
+pub fn tagged_hash(tag: &str, msg: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    Sha256::new()
+        .chain_update(tag_hash)
+        .chain_update(tag_hash)
+        .chain_update(msg)
+        .finalize()
+        .into()
+}
+
+pub(super) fn int(bytes: &[u8]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, bytes)
+}
+
+/// returns the challenge e = int(hash_BIP0340/challenge(R.x || P.x || m)) mod n
+pub(super) fn challenge(r: &[u8; 32], public: &[u8; 32], msg: &[u8]) -> BigInt {
+    let mut data = Vec::with_capacity(64 + msg.len());
+    data.extend_from_slice(r);
+    data.extend_from_slice(public);
+    data.extend_from_slice(msg);
+    int(&tagged_hash("BIP0340/challenge", &data)).mod_floor(&N)
+}
+
+/// The parity of the y-coordinate an x-only key was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    Even = 0,
+    Odd = 1,
+}
+
+impl From<bool> for Parity {
+    fn from(odd: bool) -> Self {
+        if odd {
+            Parity::Odd
+        } else {
+            Parity::Even
+        }
+    }
+}
+
+/// A public key identified by its x-coordinate alone, standing for the point with even y
+#[derive(Debug, Clone, PartialEq)]
+pub struct XOnlyPublicKey {
+    point: Point,
+}
+
+impl TryFrom<&[u8]> for XOnlyPublicKey {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != 32 {
+            return Err(anyhow!("x-only public key must be 32 bytes"));
+        }
+        Ok(Self {
+            point: Point::lift_x(&int(value), true)?,
+        })
+    }
+}
+
+impl XOnlyPublicKey {
+    /// returns the x-only key of a finite point and the parity of its y-coordinate,
+    /// the key stands for the negated point when it is odd
+    pub fn from_point(point: &Point) -> Result<(Self, Parity)> {
+        if point.coordinate().is_none() {
+            return Err(anyhow!("The point at infinity has no x-only key"));
+        }
+        if point.has_even_y() {
+            let key = Self {
+                point: point.clone(),
+            };
+            Ok((key, Parity::Even))
+        } else {
+            let key = Self {
+                point: point.negate(),
+            };
+            Ok((key, Parity::Odd))
+        }
+    }
+
+    /// returns the even-y point of the key
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    /// returns the 32-byte x-coordinate
+    pub fn serialize(&self) -> [u8; 32] {
+        x_bytes(&self.point)
+    }
+
+    /// Verifies a BIP340 signature of an arbitrary-length message
+    pub fn verify(&self, msg: &[u8], sig: &Signature) -> bool {
+        if int(&sig.r) >= *P || sig.s >= *N {
+            return false;
+        }
+        let e = challenge(&sig.r, &self.serialize(), msg);
+        // R = s⋅G - e⋅P
+        let Ok(r) = &*G * &sig.s + &self.point * &(&*N - e) else {
+            return false;
+        };
+        r.coordinate().is_some() && r.has_even_y() && x_bytes(&r) == sig.r
+    }
+}
+
+/// returns the x-coordinate of a finite point, zeroes for the point at infinity
+pub(super) fn x_bytes(point: &Point) -> [u8; 32] {
+    point
+        .coordinate()
+        .map(|c| to_32_bytes(&c.x.num))
+        .unwrap_or_default()
+}
+
+/// 64-byte BIP340 signature: the x-coordinate of R followed by s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    r: [u8; 32],
+    s: BigInt,
+}
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != 64 {
+            return Err(anyhow!("Schnorr signature must be 64 bytes"));
+        }
+        let mut r = [0; 32];
+        r.copy_from_slice(&value[..32]);
+        Ok(Self {
+            r,
+            s: int(&value[32..]),
+        })
+    }
+}
+
+impl Signature {
+    pub fn r(&self) -> &[u8; 32] {
+        &self.r
+    }
+
+    pub fn s(&self) -> &BigInt {
+        &self.s
+    }
+
+    pub fn serialize(&self) -> [u8; 64] {
+        let mut res = [0; 64];
+        res[..32].copy_from_slice(&self.r);
+        res[32..].copy_from_slice(&to_32_bytes(&self.s));
+        res
+    }
+}
+
+impl Point {
+    /// returns the x-only key of the point and the parity of its y-coordinate
+    pub fn x_only_public_key(&self) -> Result<(XOnlyPublicKey, Parity)> {
+        XOnlyPublicKey::from_point(self)
+    }
+}
+
+impl PrivateKey {
+    /// returns the x-only public key and the parity of the point's y-coordinate,
+    /// the secret is implicitly negated when it is odd
+    pub fn x_only_public_key(&self) -> Result<(XOnlyPublicKey, Parity)> {
+        self.point.x_only_public_key()
+    }
+
+    /// Signs the message with fresh auxiliary randomness
+    pub fn sign_schnorr(&self, msg: &[u8]) -> Result<Signature> {
+        let mut aux_rand = [0; 32];
+        thread_rng().fill_bytes(&mut aux_rand);
+        self.sign_schnorr_with_aux_rand(msg, &aux_rand)
+    }
+
+    /// Signs the message following BIP340, the auxiliary randomness is mixed into the nonce
+    /// to protect against side-channel attacks, all zeroes gives deterministic signatures
+    pub fn sign_schnorr_with_aux_rand(&self, msg: &[u8], aux_rand: &[u8; 32]) -> Result<Signature> {
+        if self.secret <= BigInt::from(0) || self.secret >= *N {
+            return Err(anyhow!("Secret is not in [1, n)"));
+        }
+        let (x_only, parity) = self.x_only_public_key()?;
+        let public = x_only.serialize();
+        let d = match parity {
+            Parity::Even => self.secret.clone(),
+            Parity::Odd => &*N - &self.secret,
+        };
+
+        let mut t = to_32_bytes(&d);
+        t.iter_mut()
+            .zip(tagged_hash("BIP0340/aux", aux_rand))
+            .for_each(|(t, a)| *t ^= a);
+        let mut nonce_data = Vec::with_capacity(64 + msg.len());
+        nonce_data.extend_from_slice(&t);
+        nonce_data.extend_from_slice(&public);
+        nonce_data.extend_from_slice(msg);
+        let k = int(&tagged_hash("BIP0340/nonce", &nonce_data)).mod_floor(&N);
+        if k == BigInt::from(0) {
+            return Err(anyhow!("Nonce is zero"));
+        }
+        let r = &*G * &k;
+        let k = if r.has_even_y() { k } else { &*N - k };
+        let r = x_bytes(&r);
+
+        let e = challenge(&r, &public, msg);
+        let sig = Signature {
+            r,
+            s: (k + e * d).mod_floor(&N),
+        };
+        if !x_only.verify(msg, &sig) {
+            return Err(anyhow!("Produced signature does not verify"));
+        }
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_VECTORS: &str = include_str!("schnorr/bip340-test-vectors.csv");
+
+    #[test]
+    fn test_tagged_hash() {
+        assert_eq!(
+            tagged_hash("BIP0340/challenge", b""),
+            Sha256::digest([Sha256::digest(b"BIP0340/challenge").as_slice(); 2].concat())
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn test_bip340_vectors() {
+        for line in TEST_VECTORS.lines().skip(1) {
+            let fields: Vec<&str> = line.splitn(8, ',').collect();
+            let [index, secret, public, aux_rand, msg, sig, result, ..] = fields[..] else {
+                panic!("malformed line {line}");
+            };
+            let public = hex::decode(public).unwrap();
+            let msg = hex::decode(msg).unwrap();
+            let sig = hex::decode(sig).unwrap();
+
+            if !secret.is_empty() {
+                let key = PrivateKey::new(int(&hex::decode(secret).unwrap()));
+                assert_eq!(
+                    key.x_only_public_key().unwrap().0.serialize().as_slice(),
+                    public,
+                    "{index}"
+                );
+                let aux_rand: [u8; 32] = hex::decode(aux_rand).unwrap().try_into().unwrap();
+                let actual = key.sign_schnorr_with_aux_rand(&msg, &aux_rand).unwrap();
+                assert_eq!(actual.serialize().as_slice(), sig, "{index}");
+            }
+
+            let valid = match (
+                XOnlyPublicKey::try_from(public.as_slice()),
+                Signature::try_from(sig.as_slice()),
+            ) {
+                (Ok(public), Ok(sig)) => public.verify(&msg, &sig),
+                _ => false,
+            };
+            assert_eq!(valid, result == "TRUE", "{index}");
+        }
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let key = PrivateKey::new(BigInt::from(0xc0ffeeu32));
+        let sig = key.sign_schnorr(b"hello world").unwrap();
+        let (public, _) = key.x_only_public_key().unwrap();
+        assert!(public.verify(b"hello world", &sig));
+        assert!(!public.verify(b"hello worle", &sig));
+        assert_eq!(
+            Signature::try_from(sig.serialize().as_slice()).unwrap(),
+            sig
+        );
+        assert!(PrivateKey::new(BigInt::from(0)).sign_schnorr(b"").is_err());
+    }
+}
@@ -0,0 +1,475 @@
+//! A small Bitcoin script interpreter, enough to check the standard single-key templates:
+//! P2PKH, P2SH-wrapped and native P2WPKH, P2WSH of such scripts and taproot key path spends.
+
+use super::address::hash160;
+use super::schnorr::{self, XOnlyPublicKey};
+use super::transaction::{Transaction, TxOut, SIGHASH_DEFAULT};
+use super::Point;
+use crate::ecc::ecdsa::Signature;
+use anyhow::{anyhow, Result};
+use num_bigint::{BigInt, Sign};
+use sha2::{Digest, Sha256};
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1NEGATE: u8 = 0x4f;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_SHA256: u8 = 0xa8;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+
+/// appends the shortest push of the data to the script
+pub fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+    match data.len() {
+        len @ 0..=0x4b => script.push(len as u8),
+        len @ 0x4c..=0xff => script.extend([OP_PUSHDATA1, len as u8]),
+        len @ 0x100..=0xffff => {
+            script.push(OP_PUSHDATA2);
+            script.extend((len as u16).to_le_bytes());
+        }
+        len => {
+            script.push(OP_PUSHDATA4);
+            script.extend((len as u32).to_le_bytes());
+        }
+    }
+    script.extend_from_slice(data);
+}
+
+/// returns OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+pub fn p2pkh_script(hash: &[u8; 20]) -> Vec<u8> {
+    let mut script = vec![OP_DUP, OP_HASH160];
+    push_data(&mut script, hash);
+    script.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+    script
+}
+
+/// returns OP_HASH160 <hash> OP_EQUAL
+pub fn p2sh_script(hash: &[u8; 20]) -> Vec<u8> {
+    let mut script = vec![OP_HASH160];
+    push_data(&mut script, hash);
+    script.push(OP_EQUAL);
+    script
+}
+
+/// returns OP_n <program>, the output script of a segwit address
+pub fn witness_program_script(version: u8, program: &[u8]) -> Vec<u8> {
+    let mut script = vec![if version == 0 {
+        OP_0
+    } else {
+        OP_1 + version - 1
+    }];
+    push_data(&mut script, program);
+    script
+}
+
+/// returns the version and program of a segwit output script
+pub fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    let [version, len, program @ ..] = script else {
+        return None;
+    };
+    let version = match *version {
+        OP_0 => 0,
+        op @ OP_1..=OP_16 => op - OP_1 + 1,
+        _ => return None,
+    };
+    (2..=40)
+        .contains(len)
+        .then_some(program)
+        .filter(|program| program.len() == *len as usize)
+        .map(|program| (version, program))
+}
+
+fn is_p2sh(script: &[u8]) -> bool {
+    matches!(script, [OP_HASH160, 20, .., OP_EQUAL] if script.len() == 23)
+}
+
+/// splits the script into opcodes, the data of pushes attached
+fn instructions(script: &[u8]) -> Result<Vec<(u8, Vec<u8>)>> {
+    let mut res = vec![];
+    let mut rest = script;
+    while let [op, tail @ ..] = rest {
+        let (len, tail) = match *op {
+            len @ 0x01..=0x4b => (len as usize, tail),
+            OP_PUSHDATA1 if !tail.is_empty() => (tail[0] as usize, &tail[1..]),
+            OP_PUSHDATA2 if tail.len() >= 2 => {
+                (u16::from_le_bytes([tail[0], tail[1]]) as usize, &tail[2..])
+            }
+            OP_PUSHDATA4 if tail.len() >= 4 => (
+                u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]) as usize,
+                &tail[4..],
+            ),
+            OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => return Err(anyhow!("Truncated push")),
+            _ => (0, tail),
+        };
+        if len > tail.len() {
+            return Err(anyhow!("Push past the end of the script"));
+        }
+        res.push((*op, tail[..len].to_vec()));
+        rest = &tail[len..];
+    }
+    Ok(res)
+}
+
+fn cast_to_bool(item: &[u8]) -> bool {
+    item.iter()
+        .enumerate()
+        .any(|(i, byte)| *byte != 0 && !(i == item.len() - 1 && *byte == 0x80))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SigVersion {
+    Base,
+    WitnessV0,
+}
+
+/// the input being checked, what signatures commit to
+struct Checker<'a> {
+    tx: &'a Transaction,
+    index: usize,
+    prevouts: &'a [TxOut],
+}
+
+impl Checker<'_> {
+    fn check_ecdsa(
+        &self,
+        sig: &[u8],
+        public: &[u8],
+        script_code: &[u8],
+        sig_version: SigVersion,
+    ) -> Result<bool> {
+        let Some((hash_type, der)) = sig.split_last() else {
+            return Ok(false);
+        };
+        let public = Point::try_from(public)?;
+        let sig = Signature::from_der(der)?;
+        let sighash = match sig_version {
+            SigVersion::Base => self
+                .tx
+                .legacy_sighash(self.index, script_code, *hash_type)?,
+            SigVersion::WitnessV0 => self.tx.segwit_v0_sighash(
+                self.index,
+                script_code,
+                self.prevouts[self.index].value,
+                *hash_type,
+            )?,
+        };
+        // standard policy, which BIP322 requires, only accepts low s
+        Ok(public.verify_strict(&BigInt::from_bytes_be(Sign::Plus, &sighash), &sig))
+    }
+
+    fn check_schnorr(&self, sig: &[u8], public: &[u8]) -> Result<bool> {
+        let (sig, hash_type) = match sig.len() {
+            64 => (sig, SIGHASH_DEFAULT),
+            65 if sig[64] != SIGHASH_DEFAULT => (&sig[..64], sig[64]),
+            _ => return Err(anyhow!("Invalid Schnorr signature length")),
+        };
+        let sighash = self
+            .tx
+            .taproot_key_path_sighash(self.index, self.prevouts, hash_type)?;
+        Ok(XOnlyPublicKey::try_from(public)?.verify(&sighash, &schnorr::Signature::try_from(sig)?))
+    }
+
+    /// runs the script on the stack, `script` also being the code signatures commit to
+    fn eval(&self, script: &[u8], stack: &mut Vec<Vec<u8>>, sig_version: SigVersion) -> Result<()> {
+        let pop = |stack: &mut Vec<Vec<u8>>| stack.pop().ok_or_else(|| anyhow!("Stack underflow"));
+        for (op, data) in instructions(script)? {
+            match op {
+                OP_0..=OP_PUSHDATA4 => stack.push(data),
+                OP_1NEGATE => stack.push(vec![0x81]),
+                OP_1..=OP_16 => stack.push(vec![op - OP_1 + 1]),
+                OP_VERIFY => {
+                    if !cast_to_bool(&pop(stack)?) {
+                        return Err(anyhow!("OP_VERIFY failed"));
+                    }
+                }
+                OP_RETURN => return Err(anyhow!("OP_RETURN executed")),
+                OP_DUP => {
+                    let top = stack
+                        .last()
+                        .ok_or_else(|| anyhow!("Stack underflow"))?
+                        .clone();
+                    stack.push(top);
+                }
+                OP_EQUAL | OP_EQUALVERIFY => {
+                    let equal = pop(stack)? == pop(stack)?;
+                    if op == OP_EQUALVERIFY && !equal {
+                        return Err(anyhow!("OP_EQUALVERIFY failed"));
+                    } else if op == OP_EQUAL {
+                        stack.push(if equal { vec![1] } else { vec![] });
+                    }
+                }
+                OP_SHA256 => {
+                    let top = pop(stack)?;
+                    stack.push(Sha256::digest(top).to_vec());
+                }
+                OP_HASH160 => {
+                    let top = pop(stack)?;
+                    stack.push(hash160(&top).to_vec());
+                }
+                OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                    let public = pop(stack)?;
+                    let sig = pop(stack)?;
+                    let valid = self.check_ecdsa(&sig, &public, script, sig_version)?;
+                    if op == OP_CHECKSIGVERIFY && !valid {
+                        return Err(anyhow!("OP_CHECKSIGVERIFY failed"));
+                    } else if op == OP_CHECKSIG {
+                        stack.push(if valid { vec![1] } else { vec![] });
+                    }
+                }
+                _ => return Err(anyhow!("Unsupported opcode {op:#04x}")),
+            }
+        }
+        Ok(())
+    }
+
+    fn eval_to_true(
+        &self,
+        script: &[u8],
+        mut stack: Vec<Vec<u8>>,
+        sig_version: SigVersion,
+    ) -> Result<()> {
+        self.eval(script, &mut stack, sig_version)?;
+        match stack.last() {
+            Some(top) if cast_to_bool(top) => Ok(()),
+            _ => Err(anyhow!("Script evaluated to false")),
+        }
+    }
+
+    fn verify_witness_program(
+        &self,
+        version: u8,
+        program: &[u8],
+        witness: &[Vec<u8>],
+    ) -> Result<()> {
+        match (version, program.len(), witness) {
+            (0, 20, [_, _]) => {
+                let program: [u8; 20] = program.try_into()?;
+                self.eval_to_true(
+                    &p2pkh_script(&program),
+                    witness.to_vec(),
+                    SigVersion::WitnessV0,
+                )
+            }
+            (0, 32, [stack @ .., witness_script]) => {
+                if Sha256::digest(witness_script).as_slice() != program {
+                    return Err(anyhow!("Witness script does not match the program"));
+                }
+                self.eval_to_true(witness_script, stack.to_vec(), SigVersion::WitnessV0)
+            }
+            (1, 32, [sig]) => {
+                if self.check_schnorr(sig, program)? {
+                    Ok(())
+                } else {
+                    Err(anyhow!("Invalid taproot key path signature"))
+                }
+            }
+            (1, 32, _) => Err(anyhow!("Taproot script path spends are not supported")),
+            _ => Err(anyhow!("Unsupported witness program")),
+        }
+    }
+}
+
+/// Verifies that the input of tx spends its previous output, `prevouts` holding
+/// the outputs spent by every input
+pub fn verify_input(tx: &Transaction, index: usize, prevouts: &[TxOut]) -> Result<()> {
+    let (Some(input), Some(prevout)) = (tx.inputs.get(index), prevouts.get(index)) else {
+        return Err(anyhow!("Input {index} or the output it spends is missing"));
+    };
+    let checker = Checker {
+        tx,
+        index,
+        prevouts,
+    };
+    let script_sig = instructions(&input.script_sig)?;
+    if script_sig.iter().any(|(op, _)| *op > OP_16) {
+        return Err(anyhow!("scriptSig must be push only"));
+    }
+    let mut stack = vec![];
+    checker.eval(&input.script_sig, &mut stack, SigVersion::Base)?;
+    let script_pubkey = &prevout.script_pubkey;
+
+    if let Some((version, program)) = witness_program(script_pubkey) {
+        if !input.script_sig.is_empty() {
+            return Err(anyhow!("Native segwit spends must have an empty scriptSig"));
+        }
+        return checker.verify_witness_program(version, program, &input.witness);
+    }
+    checker.eval_to_true(script_pubkey, stack.clone(), SigVersion::Base)?;
+    if !is_p2sh(script_pubkey) {
+        return Ok(());
+    }
+    let redeem_script = stack
+        .pop()
+        .ok_or_else(|| anyhow!("Missing redeem script"))?;
+    match witness_program(&redeem_script) {
+        Some((version, program)) if stack.is_empty() => {
+            checker.verify_witness_program(version, program, &input.witness)
+        }
+        Some(_) => Err(anyhow!(
+            "Nested segwit scriptSig must only push the redeem script"
+        )),
+        None => checker.eval_to_true(&redeem_script, stack, SigVersion::Base),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::sec_format::Compressed;
+    use crate::ecc::secp256k1::transaction::{OutPoint, TxIn, SIGHASH_ALL};
+    use crate::ecc::secp256k1::{PrivateKey, N};
+
+    fn spend(script_pubkey: Vec<u8>) -> (Transaction, Vec<TxOut>) {
+        let prevouts = vec![TxOut {
+            value: 50_000,
+            script_pubkey,
+        }];
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: [7; 32],
+                    vout: 1,
+                },
+                ..Default::default()
+            }],
+            outputs: vec![TxOut {
+                value: 40_000,
+                script_pubkey: vec![OP_RETURN],
+            }],
+            lock_time: 0,
+        };
+        (tx, prevouts)
+    }
+
+    fn ecdsa_sig(key: &PrivateKey, sighash: [u8; 32]) -> Vec<u8> {
+        let mut sig = key
+            .sign(&BigInt::from_bytes_be(Sign::Plus, &sighash))
+            .unwrap()
+            .der();
+        sig.push(SIGHASH_ALL);
+        sig
+    }
+
+    #[test]
+    fn test_p2pkh() {
+        let key = PrivateKey::new(BigInt::from(1001));
+        let public = key.sec::<Compressed>().unwrap();
+        let script_pubkey = p2pkh_script(&hash160(&public));
+        let (mut tx, prevouts) = spend(script_pubkey.clone());
+        let sig = ecdsa_sig(
+            &key,
+            tx.legacy_sighash(0, &script_pubkey, SIGHASH_ALL).unwrap(),
+        );
+        push_data(&mut tx.inputs[0].script_sig, &sig);
+        push_data(&mut tx.inputs[0].script_sig, &public);
+        assert!(verify_input(&tx, 0, &prevouts).is_ok());
+
+        tx.outputs[0].value += 1;
+        assert!(verify_input(&tx, 0, &prevouts).is_err());
+    }
+
+    #[test]
+    fn test_p2wpkh() {
+        let key = PrivateKey::new(BigInt::from(1002));
+        let public = key.sec::<Compressed>().unwrap();
+        let hash = hash160(&public);
+        for script_pubkey in [
+            witness_program_script(0, &hash),
+            p2sh_script(&hash160(&witness_program_script(0, &hash))),
+        ] {
+            let (mut tx, prevouts) = spend(script_pubkey.clone());
+            if is_p2sh(&script_pubkey) {
+                push_data(
+                    &mut tx.inputs[0].script_sig,
+                    &witness_program_script(0, &hash),
+                );
+            }
+            let sighash = tx
+                .segwit_v0_sighash(0, &p2pkh_script(&hash), 50_000, SIGHASH_ALL)
+                .unwrap();
+            tx.inputs[0].witness = vec![ecdsa_sig(&key, sighash), public.to_vec()];
+            assert!(verify_input(&tx, 0, &prevouts).is_ok());
+
+            tx.inputs[0].witness[1] = PrivateKey::new(BigInt::from(1003))
+                .sec::<Compressed>()
+                .unwrap()
+                .to_vec();
+            assert!(verify_input(&tx, 0, &prevouts).is_err());
+        }
+    }
+
+    #[test]
+    fn test_high_s() {
+        // replaces s by n - s, which verifies under the same key but is not standard
+        let high_s = |sig: &[u8]| {
+            let (hash_type, der) = sig.split_last().unwrap();
+            let sig = Signature::from_der(der).unwrap();
+            let mut high = Signature::new(sig.r().clone(), &*N - sig.s()).der();
+            high.push(*hash_type);
+            high
+        };
+        let key = PrivateKey::new(BigInt::from(1005));
+        let public = key.sec::<Compressed>().unwrap();
+        let hash = hash160(&public);
+
+        let script_pubkey = p2pkh_script(&hash);
+        let (mut tx, prevouts) = spend(script_pubkey.clone());
+        let sig = ecdsa_sig(
+            &key,
+            tx.legacy_sighash(0, &script_pubkey, SIGHASH_ALL).unwrap(),
+        );
+        push_data(&mut tx.inputs[0].script_sig, &high_s(&sig));
+        push_data(&mut tx.inputs[0].script_sig, &public);
+        assert!(verify_input(&tx, 0, &prevouts).is_err());
+
+        let (mut tx, prevouts) = spend(witness_program_script(0, &hash));
+        let sighash = tx
+            .segwit_v0_sighash(0, &p2pkh_script(&hash), 50_000, SIGHASH_ALL)
+            .unwrap();
+        let sig = ecdsa_sig(&key, sighash);
+        tx.inputs[0].witness = vec![sig.clone(), public.to_vec()];
+        assert!(verify_input(&tx, 0, &prevouts).is_ok());
+        tx.inputs[0].witness[0] = high_s(&sig);
+        assert!(verify_input(&tx, 0, &prevouts).is_err());
+    }
+
+    #[test]
+    fn test_taproot_key_path() {
+        let key = PrivateKey::new(BigInt::from(1004));
+        let public = key.x_only_public_key().unwrap().0.serialize();
+        let (mut tx, prevouts) = spend(witness_program_script(1, &public));
+        let sighash = tx
+            .taproot_key_path_sighash(0, &prevouts, SIGHASH_DEFAULT)
+            .unwrap();
+        tx.inputs[0].witness = vec![key.sign_schnorr(&sighash).unwrap().serialize().to_vec()];
+        assert!(verify_input(&tx, 0, &prevouts).is_ok());
+
+        tx.lock_time = 1;
+        assert!(verify_input(&tx, 0, &prevouts).is_err());
+    }
+
+    #[test]
+    fn test_instructions() {
+        let mut script = vec![];
+        push_data(&mut script, &[0xab; 0x4c]);
+        push_data(&mut script, &[0xcd; 0x100]);
+        script.push(OP_CHECKSIG);
+        let ops = instructions(&script).unwrap();
+        assert_eq!(ops.len(), 3);
+        assert_eq!(ops[0], (OP_PUSHDATA1, vec![0xab; 0x4c]));
+        assert_eq!(ops[1], (OP_PUSHDATA2, vec![0xcd; 0x100]));
+        assert!(instructions(&script[..10]).is_err());
+        assert!(!cast_to_bool(&[0, 0, 0x80]));
+        assert!(cast_to_bool(&[0, 1, 0]));
+    }
+}
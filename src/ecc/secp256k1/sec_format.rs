@@ -1,9 +1,10 @@
-use super::PrivateKey;
+use super::Point;
 
 use crate::ecc::{
     elliptic_curve_finite_field::Coordinate as ECCoordinate, finite_field::FieldElement,
 };
 
+use num_bigint::BigInt;
 use num_integer::Integer;
 
 pub struct Compressed;
@@ -12,22 +13,29 @@ pub struct Uncompressed;
 pub trait SecFormat {
     type Output;
 
-    fn sec(pk: &PrivateKey) -> Self::Output;
+    fn sec(point: &Point) -> Self::Output;
+}
+
+/// returns the big-endian bytes of a field element, left-padded to 32 bytes
+pub(crate) fn to_32_bytes(num: &BigInt) -> [u8; 32] {
+    let (_, bytes) = num.to_bytes_be();
+    let mut res = [0; 32];
+    res[32 - bytes.len()..].copy_from_slice(&bytes);
+    res
 }
 
 impl SecFormat for Compressed {
     type Output = Option<[u8; 33]>;
 
-    fn sec(pk: &PrivateKey) -> Self::Output {
-        pk.point.coordinate().map(
+    fn sec(point: &Point) -> Self::Output {
+        point.coordinate().map(
             |ECCoordinate {
                  x: FieldElement { num: x, .. },
                  y: FieldElement { num: y, .. },
              }| {
                 let mut res = [0; 33];
                 res[0] = if y.is_even() { b'\x02' } else { b'\x03' };
-                let x_dest = &mut res[1..33];
-                x_dest.copy_from_slice(&x.to_bytes_be().1[..]);
+                res[1..33].copy_from_slice(&to_32_bytes(x));
 
                 res
             },
@@ -38,18 +46,16 @@ impl SecFormat for Compressed {
 impl SecFormat for Uncompressed {
     type Output = Option<[u8; 65]>;
 
-    fn sec(pk: &PrivateKey) -> Self::Output {
-        pk.point.coordinate().map(
+    fn sec(point: &Point) -> Self::Output {
+        point.coordinate().map(
             |ECCoordinate {
                  x: FieldElement { num: x, .. },
                  y: FieldElement { num: y, .. },
              }| {
                 let mut res = [0; 65];
                 res[0] = b'\x04';
-                let x_dest = &mut res[1..33];
-                x_dest.copy_from_slice(&x.to_bytes_be().1[..]);
-                let y_dest = &mut res[33..65];
-                y_dest.copy_from_slice(&y.to_bytes_be().1[..]);
+                res[1..33].copy_from_slice(&to_32_bytes(x));
+                res[33..65].copy_from_slice(&to_32_bytes(y));
 
                 res
             },
@@ -0,0 +1,152 @@
+//! Taproot key path (BIP341): the output key `Q = P + int(hash_TapTweak(P || root))⋅G`
+//! commits to an x-only internal key P and optionally to the Merkle root of a script tree.
+//! BIP86 outputs commit to no script tree at all.
+
+use super::address::Network;
+pub use super::schnorr::Parity;
+use super::schnorr::{tagged_hash, XOnlyPublicKey};
+use super::script::witness_program_script;
+use super::PrivateKey;
+use anyhow::{anyhow, Result};
+use bech32::segwit;
+use num_bigint::{BigInt, Sign};
+
+pub mod tree;
+
+/// returns the TapTweak hash of the internal key and the script tree Merkle root, if any
+pub fn tap_tweak_hash(internal: &XOnlyPublicKey, merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut data = internal.serialize().to_vec();
+    if let Some(root) = merkle_root {
+        data.extend_from_slice(root);
+    }
+    tagged_hash("TapTweak", &data)
+}
+
+impl XOnlyPublicKey {
+    /// returns the output key committing to the internal key and the Merkle root,
+    /// together with its parity which the control blocks of script path spends carry
+    pub fn tap_tweak(&self, merkle_root: Option<&[u8; 32]>) -> Result<(XOnlyPublicKey, Parity)> {
+        let tweak = BigInt::from_bytes_be(Sign::Plus, &tap_tweak_hash(self, merkle_root));
+        self.point().tweak_add(&tweak)?.x_only_public_key()
+    }
+
+    /// returns the segwit v1 output script paying to the key as given, so it must already
+    /// be an output key
+    pub fn p2tr_script(&self) -> Vec<u8> {
+        witness_program_script(1, &self.serialize())
+    }
+
+    /// returns the bech32m address paying to the key as given, so it must already be
+    /// an output key
+    pub fn p2tr(&self, network: Network) -> Result<String> {
+        Ok(segwit::encode_v1(network.hrp(), &self.serialize())?)
+    }
+}
+
+impl PrivateKey {
+    /// returns the key of the taproot output committing to the Merkle root, able to sign
+    /// for the key path
+    pub fn tap_tweak(&self, merkle_root: Option<&[u8; 32]>) -> Result<PrivateKey> {
+        let (internal, parity) = self.point.x_only_public_key()?;
+        let tweak = BigInt::from_bytes_be(Sign::Plus, &tap_tweak_hash(&internal, merkle_root));
+        // the internal key stands for the even-y point, so an odd-y secret is negated first
+        match parity {
+            Parity::Even => self.tweak_add(&tweak),
+            Parity::Odd => self.negate()?.tweak_add(&tweak),
+        }
+        .map_err(|e| anyhow!("Cannot tweak the key for taproot: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::bip32::DerivationPath;
+    use crate::ecc::secp256k1::bip39::Mnemonic;
+    use crate::ecc::secp256k1::Point;
+    use hex_literal::hex;
+
+    fn x_only(bytes: &[u8]) -> XOnlyPublicKey {
+        XOnlyPublicKey::try_from(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_bip341_script_pubkeys() {
+        let vectors = [
+            (
+                hex!("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d"),
+                None,
+                hex!("b86e7be8f39bab32a6f2c0443abbc210f0edac0e2c53d501b36b64437d9c6c70"),
+                hex!("53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"),
+                "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5",
+            ),
+            (
+                hex!("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"),
+                Some(hex!(
+                    "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+                )),
+                hex!("cbd8679ba636c1110ea247542cfbd964131a6be84f873f7f3b62a777528ed001"),
+                hex!("147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"),
+                "bc1pz37fc4cn9ah8anwm4xqqhvxygjf9rjf2resrw8h8w4tmvcs0863sa2e586",
+            ),
+        ];
+        for (internal, merkle_root, tweak, output, address) in vectors {
+            let internal = x_only(&internal);
+            assert_eq!(tap_tweak_hash(&internal, merkle_root.as_ref()), tweak);
+            let (output_key, _) = internal.tap_tweak(merkle_root.as_ref()).unwrap();
+            assert_eq!(output_key.serialize(), output);
+            assert_eq!(output_key.p2tr(Network::Mainnet).unwrap(), address);
+            let mut script = vec![0x51, 0x20];
+            script.extend_from_slice(&output);
+            assert_eq!(output_key.p2tr_script(), script);
+        }
+    }
+
+    #[test]
+    fn test_bip86() {
+        let mnemonic: Mnemonic =
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+                .parse()
+                .unwrap();
+        let master = mnemonic.to_master_key("", Network::Mainnet).unwrap();
+        let path: DerivationPath = "m/86'/0'/0'/0/0".parse().unwrap();
+        let key = master.derive_path(&path).unwrap();
+        let (internal, _) = key.private_key().x_only_public_key().unwrap();
+        assert_eq!(
+            internal.serialize(),
+            hex!("cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115")
+        );
+        let (output, _) = internal.tap_tweak(None).unwrap();
+        assert_eq!(
+            output.serialize(),
+            hex!("a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c")
+        );
+        assert_eq!(
+            output.p2tr(Network::Mainnet).unwrap(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+    }
+
+    #[test]
+    fn test_key_path_signing() {
+        let merkle_root = [7; 32];
+        // 5⋅G has even y and 6⋅G odd y
+        for secret in [5, 6] {
+            let key = PrivateKey::new(BigInt::from(secret));
+            let (internal, parity) = key.x_only_public_key().unwrap();
+            assert_eq!(parity, Parity::from(secret == 6));
+            for root in [None, Some(&merkle_root)] {
+                let (output, parity) = internal.tap_tweak(root).unwrap();
+                let tweaked = key.tap_tweak(root).unwrap();
+                assert_eq!(
+                    tweaked.x_only_public_key().unwrap(),
+                    (output.clone(), parity)
+                );
+                let sig = tweaked.sign_schnorr(b"key path").unwrap();
+                assert!(output.verify(b"key path", &sig));
+                assert!(!internal.verify(b"key path", &sig));
+            }
+        }
+        assert!(Point::new(None).unwrap().x_only_public_key().is_err());
+    }
+}
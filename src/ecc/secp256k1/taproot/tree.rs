@@ -0,0 +1,500 @@
+//! Taproot script trees (BIP341): leaves commit to a script and its leaf version, branches
+//! to their children's hashes in lexicographic order. A script path spend reveals the leaf
+//! together with a control block holding the internal key and the Merkle path to the root.
+
+use super::{Parity, XOnlyPublicKey};
+use crate::ecc::secp256k1::schnorr::tagged_hash;
+use crate::ecc::secp256k1::transaction::compact_size;
+use anyhow::{anyhow, Result};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// The leaf version of BIP342 tapscript
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+
+/// Deepest Merkle path a control block can hold
+pub const MAX_DEPTH: usize = 128;
+
+/// returns the TapLeaf hash of the script with the given leaf version
+pub fn tap_leaf_hash(leaf_version: u8, script: &[u8]) -> [u8; 32] {
+    let mut data = vec![leaf_version];
+    data.extend(compact_size(script.len()));
+    data.extend_from_slice(script);
+    tagged_hash("TapLeaf", &data)
+}
+
+/// returns the TapBranch hash of two children, which are sorted first
+pub fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    tagged_hash("TapBranch", &[&first[..], &second[..]].concat())
+}
+
+/// A binary tree of scripts an output key can commit to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapTree {
+    Leaf { version: u8, script: Vec<u8> },
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    /// returns a tapscript leaf
+    pub fn leaf(script: Vec<u8>) -> Self {
+        TapTree::Leaf {
+            version: TAPSCRIPT_LEAF_VERSION,
+            script,
+        }
+    }
+
+    /// returns a leaf of the given version, which must be even and not clash with the annex tag
+    pub fn leaf_with_version(version: u8, script: Vec<u8>) -> Result<Self> {
+        if version & 1 != 0 || version == 0x50 {
+            return Err(anyhow!("Invalid leaf version {version:#04x}"));
+        }
+        Ok(TapTree::Leaf { version, script })
+    }
+
+    pub fn branch(left: TapTree, right: TapTree) -> Self {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    /// Builds the tree minimizing the expected Merkle path length, where each subtree is
+    /// spent with probability proportional to its weight. Ties go to the earlier subtree.
+    pub fn huffman(weighted: impl IntoIterator<Item = (u64, TapTree)>) -> Result<Self> {
+        let mut trees: Vec<Option<TapTree>> = vec![];
+        let mut heap = BinaryHeap::new();
+        for (weight, tree) in weighted {
+            heap.push((Reverse(weight), Reverse(trees.len())));
+            trees.push(Some(tree));
+        }
+        let tree = loop {
+            let Some((Reverse(weight), Reverse(index))) = heap.pop() else {
+                break Err(anyhow!("A script tree needs at least one leaf"));
+            };
+            let first = trees[index].take().expect("each tree is popped once");
+            let Some((Reverse(other_weight), Reverse(other_index))) = heap.pop() else {
+                break Ok(first);
+            };
+            let second = trees[other_index].take().expect("each tree is popped once");
+            heap.push((
+                Reverse(weight.saturating_add(other_weight)),
+                Reverse(trees.len()),
+            ));
+            trees.push(Some(TapTree::branch(first, second)));
+        }?;
+        if tree.depth() > MAX_DEPTH {
+            return Err(anyhow!("Script tree is deeper than {MAX_DEPTH}"));
+        }
+        Ok(tree)
+    }
+
+    /// returns the length of the longest Merkle path in the tree
+    pub fn depth(&self) -> usize {
+        match self {
+            TapTree::Leaf { .. } => 0,
+            TapTree::Branch(left, right) => 1 + left.depth().max(right.depth()),
+        }
+    }
+
+    /// returns the TapLeaf or TapBranch hash of the tree, the Merkle root at the top
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf { version, script } => tap_leaf_hash(*version, script),
+            TapTree::Branch(left, right) => tap_branch_hash(&left.hash(), &right.hash()),
+        }
+    }
+
+    pub fn merkle_root(&self) -> [u8; 32] {
+        self.hash()
+    }
+
+    /// returns the leaves with their depths, from left to right
+    pub fn leaves(&self) -> Vec<(usize, u8, &[u8])> {
+        match self {
+            TapTree::Leaf { version, script } => vec![(0, *version, script.as_slice())],
+            TapTree::Branch(left, right) => left
+                .leaves()
+                .into_iter()
+                .chain(right.leaves())
+                .map(|(depth, version, script)| (depth + 1, version, script))
+                .collect(),
+        }
+    }
+
+    /// returns the hashes from the leaf's sibling up to the root's child
+    fn merkle_path(&self, leaf_hash: &[u8; 32]) -> Option<Vec<[u8; 32]>> {
+        match self {
+            TapTree::Leaf { .. } => (self.hash() == *leaf_hash).then(Vec::new),
+            TapTree::Branch(left, right) => {
+                let with_sibling = |mut path: Vec<[u8; 32]>, sibling: &TapTree| {
+                    path.push(sibling.hash());
+                    path
+                };
+                left.merkle_path(leaf_hash)
+                    .map(|path| with_sibling(path, right))
+                    .or_else(|| {
+                        right
+                            .merkle_path(leaf_hash)
+                            .map(|path| with_sibling(path, left))
+                    })
+            }
+        }
+    }
+
+    /// returns the output key committing to the internal key and the tree
+    pub fn output_key(&self, internal_key: &XOnlyPublicKey) -> Result<(XOnlyPublicKey, Parity)> {
+        internal_key.tap_tweak(Some(&self.merkle_root()))
+    }
+
+    /// returns the control block spending the leaf of the given version and script
+    pub fn control_block(
+        &self,
+        internal_key: &XOnlyPublicKey,
+        leaf_version: u8,
+        script: &[u8],
+    ) -> Result<ControlBlock> {
+        let merkle_path = self
+            .merkle_path(&tap_leaf_hash(leaf_version, script))
+            .ok_or_else(|| anyhow!("The script is not a leaf of the tree"))?;
+        if merkle_path.len() > MAX_DEPTH {
+            return Err(anyhow!("Leaf is deeper than {MAX_DEPTH}"));
+        }
+        let (_, output_parity) = self.output_key(internal_key)?;
+        Ok(ControlBlock {
+            leaf_version,
+            output_parity,
+            internal_key: internal_key.clone(),
+            merkle_path,
+        })
+    }
+}
+
+/// What a script path spend reveals besides the script: the leaf version, the parity of
+/// the output key, the internal key and the Merkle path of the leaf
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlBlock {
+    leaf_version: u8,
+    output_parity: Parity,
+    internal_key: XOnlyPublicKey,
+    merkle_path: Vec<[u8; 32]>,
+}
+
+impl TryFrom<&[u8]> for ControlBlock {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() < 33 || (value.len() - 33) % 32 != 0 || value.len() > 33 + 32 * MAX_DEPTH {
+            return Err(anyhow!(
+                "Control block must be 33 + 32m bytes with m <= {MAX_DEPTH}"
+            ));
+        }
+        Ok(Self {
+            leaf_version: value[0] & 0xfe,
+            output_parity: Parity::from(value[0] & 1 == 1),
+            internal_key: XOnlyPublicKey::try_from(&value[1..33])?,
+            merkle_path: value[33..]
+                .chunks(32)
+                .map(|hash| hash.try_into().expect("chunks of 32 bytes"))
+                .collect(),
+        })
+    }
+}
+
+impl ControlBlock {
+    pub fn leaf_version(&self) -> u8 {
+        self.leaf_version
+    }
+
+    pub fn output_parity(&self) -> Parity {
+        self.output_parity
+    }
+
+    pub fn internal_key(&self) -> &XOnlyPublicKey {
+        &self.internal_key
+    }
+
+    pub fn merkle_path(&self) -> &[[u8; 32]] {
+        &self.merkle_path
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut res = vec![self.leaf_version | self.output_parity as u8];
+        res.extend_from_slice(&self.internal_key.serialize());
+        self.merkle_path
+            .iter()
+            .for_each(|hash| res.extend_from_slice(hash));
+        res
+    }
+
+    /// Checks that the script is a leaf of a tree the output key commits to
+    pub fn verify(&self, output_key: &XOnlyPublicKey, script: &[u8]) -> bool {
+        let root = self
+            .merkle_path
+            .iter()
+            .fold(tap_leaf_hash(self.leaf_version, script), |hash, sibling| {
+                tap_branch_hash(&hash, sibling)
+            });
+        self.internal_key
+            .tap_tweak(Some(&root))
+            .map(|(key, parity)| key == *output_key && parity == self.output_parity)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::secp256k1::address::Network;
+    use crate::ecc::secp256k1::PrivateKey;
+    use hex_literal::hex;
+    use num_bigint::BigInt;
+
+    fn internal_key(secret: u32) -> XOnlyPublicKey {
+        PrivateKey::new(BigInt::from(secret))
+            .x_only_public_key()
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_bip341_single_leaf_vectors() {
+        let vectors = [
+            (
+                hex!("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"),
+                hex!("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                    .to_vec(),
+                hex!("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+                hex!("147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"),
+                hex!("c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27").to_vec(),
+            ),
+            (
+                hex!("93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820"),
+                hex!("20b617298552a72ade070667e86ca63b8f5789a9fe8731ef91202a91c9f3459007ac")
+                    .to_vec(),
+                hex!("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+                hex!("e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e"),
+                hex!("c093478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820").to_vec(),
+            ),
+        ];
+        for (internal, script, root, output, control_block) in vectors {
+            let internal = XOnlyPublicKey::try_from(internal.as_slice()).unwrap();
+            let tree = TapTree::leaf(script.clone());
+            assert_eq!(tree.merkle_root(), root);
+            let (output_key, _) = tree.output_key(&internal).unwrap();
+            assert_eq!(output_key.serialize(), output);
+            let actual = tree
+                .control_block(&internal, TAPSCRIPT_LEAF_VERSION, &script)
+                .unwrap();
+            assert_eq!(actual.serialize(), control_block);
+            assert_eq!(
+                ControlBlock::try_from(control_block.as_slice()).unwrap(),
+                actual
+            );
+            assert!(actual.verify(&output_key, &script));
+        }
+    }
+
+    #[test]
+    fn test_bip341_multi_leaf_vectors() {
+        // the trees are [leaf 0, leaf 1] and [leaf 0, [leaf 1, leaf 2]]
+        let vectors = [
+            (
+                hex!("ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592"),
+                vec![
+                    (
+                        0xc0,
+                        hex!(
+                            "20387671353e273264c495656e27e39ba899ea8fee3bb69fb2a680e22093447d48ac"
+                        )
+                        .to_vec(),
+                    ),
+                    (0xfa, hex!("06424950333431").to_vec()),
+                ],
+                hex!("6c2dc106ab816b73f9d07e3cd1ef2c8c1256f519748e0813e4edd2405d277bef"),
+                hex!("712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5"),
+                "bc1pwyjywgrd0ffr3tx8laflh6228dj98xkjj8rum0zfpd6h0e930h6saqxrrm",
+            ),
+            (
+                hex!("f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8"),
+                vec![
+                    (
+                        0xc0,
+                        hex!(
+                            "2044b178d64c32c4a05cc4f4d1407268f764c940d20ce97abfd44db5c3592b72fdac"
+                        )
+                        .to_vec(),
+                    ),
+                    (0xc0, hex!("07546170726f6f74").to_vec()),
+                ],
+                hex!("ab179431c28d3b68fb798957faf5497d69c883c6fb1e1cd9f81483d87bac90cc"),
+                hex!("77e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220"),
+                "bc1pwl3s54fzmk0cjnpl3w9af39je7pv5ldg504x5guk2hpecpg2kgsqaqstjq",
+            ),
+            (
+                hex!("e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6f"),
+                vec![
+                    (
+                        0xc0,
+                        hex!(
+                            "2072ea6adcf1d371dea8fba1035a09f3d24ed5a059799bae114084130ee5898e69ac"
+                        )
+                        .to_vec(),
+                    ),
+                    (
+                        0xc0,
+                        hex!(
+                            "202352d137f2f3ab38d1eaa976758873377fa5ebb817372c71e2c542313d4abda8ac"
+                        )
+                        .to_vec(),
+                    ),
+                    (
+                        0xc0,
+                        hex!(
+                            "207337c0dd4253cb86f2c43a2351aadd82cccb12a172cd120452b9bb8324f2186aac"
+                        )
+                        .to_vec(),
+                    ),
+                ],
+                hex!("ccbd66c6f7e8fdab47b3a486f59d28262be857f30d4773f2d5ea47f7761ce0e2"),
+                hex!("91b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605"),
+                "bc1pjxmy65eywgafs5tsunw95ruycpqcqnev6ynxp7jaasylcgtcxczs6n332e",
+            ),
+            (
+                hex!("55adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d"),
+                vec![
+                    (
+                        0xc0,
+                        hex!(
+                            "2071981521ad9fc9036687364118fb6ccd2035b96a423c59c5430e98310a11abe2ac"
+                        )
+                        .to_vec(),
+                    ),
+                    (
+                        0xc0,
+                        hex!(
+                            "20d5094d2dbe9b76e2c245a2b89b6006888952e2faa6a149ae318d69e520617748ac"
+                        )
+                        .to_vec(),
+                    ),
+                    (
+                        0xc0,
+                        hex!(
+                            "20c440b462ad48c7a77f94cd4532d8f2119dcebbd7c9764557e62726419b08ad4cac"
+                        )
+                        .to_vec(),
+                    ),
+                ],
+                hex!("2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def"),
+                hex!("75169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831"),
+                "bc1pw5tf7sqp4f50zka7629jrr036znzew70zxyvvej3zrpf8jg8hqcssyuewe",
+            ),
+        ];
+        for (internal, leaves, root, output, address) in vectors {
+            let mut leaves = leaves
+                .into_iter()
+                .map(|(version, script)| TapTree::leaf_with_version(version, script).unwrap());
+            let first = leaves.next().unwrap();
+            let rest: Vec<TapTree> = leaves.collect();
+            let tree = match <[TapTree; 2]>::try_from(rest) {
+                Ok([second, third]) => TapTree::branch(first, TapTree::branch(second, third)),
+                Err(mut rest) => TapTree::branch(first, rest.remove(0)),
+            };
+            let internal = XOnlyPublicKey::try_from(internal.as_slice()).unwrap();
+            assert_eq!(tree.merkle_root(), root);
+            let (output_key, _) = tree.output_key(&internal).unwrap();
+            assert_eq!(output_key.serialize(), output);
+            assert_eq!(output_key.p2tr(Network::Mainnet).unwrap(), address);
+            for (depth, version, script) in tree.leaves() {
+                let control_block = tree.control_block(&internal, version, script).unwrap();
+                assert_eq!(control_block.merkle_path().len(), depth);
+                assert!(control_block.verify(&output_key, script));
+            }
+        }
+    }
+
+    #[test]
+    fn test_control_blocks() {
+        let scripts: Vec<Vec<u8>> = (0..5u8).map(|i| vec![0x51 + i]).collect();
+        let tree = TapTree::branch(
+            TapTree::branch(
+                TapTree::leaf(scripts[0].clone()),
+                TapTree::leaf_with_version(0xc2, scripts[1].clone()).unwrap(),
+            ),
+            TapTree::branch(
+                TapTree::leaf(scripts[2].clone()),
+                TapTree::branch(
+                    TapTree::leaf(scripts[3].clone()),
+                    TapTree::leaf(scripts[4].clone()),
+                ),
+            ),
+        );
+        let internal = internal_key(7);
+        let (output, _) = tree.output_key(&internal).unwrap();
+        for (depth, version, script) in tree.leaves() {
+            let control_block = tree.control_block(&internal, version, script).unwrap();
+            assert_eq!(control_block.merkle_path().len(), depth);
+            assert_eq!(control_block.leaf_version(), version);
+            assert!(control_block.verify(&output, script));
+            assert!(!control_block.verify(&output, &[0x00]));
+            assert!(!control_block.verify(&internal, script));
+            let parsed = ControlBlock::try_from(control_block.serialize().as_slice()).unwrap();
+            assert_eq!(parsed, control_block);
+        }
+        assert!(tree
+            .control_block(&internal, TAPSCRIPT_LEAF_VERSION, &scripts[1])
+            .is_err());
+        assert!(tree.control_block(&internal, 0xc0, &[0x00]).is_err());
+    }
+
+    #[test]
+    fn test_huffman() {
+        let weighted = [
+            (1, vec![0x51]),
+            (10, vec![0x52]),
+            (1, vec![0x53]),
+            (2, vec![0x54]),
+        ]
+        .map(|(weight, script)| (weight, TapTree::leaf(script)));
+        let tree = TapTree::huffman(weighted).unwrap();
+        let mut depths: Vec<(usize, &[u8])> = tree
+            .leaves()
+            .into_iter()
+            .map(|(depth, _, script)| (depth, script))
+            .collect();
+        depths.sort();
+        assert_eq!(
+            depths,
+            [(1, &[0x52][..]), (2, &[0x54]), (3, &[0x51]), (3, &[0x53])]
+        );
+
+        let leaf = TapTree::leaf(vec![0x51]);
+        assert_eq!(TapTree::huffman([(5, leaf.clone())]).unwrap(), leaf);
+        assert!(TapTree::huffman([]).is_err());
+
+        // a subtree already at the maximum depth cannot be paired with anything
+        let deep = (0..MAX_DEPTH).fold(leaf.clone(), |tree, _| {
+            TapTree::branch(tree, TapTree::leaf(vec![0x52]))
+        });
+        assert_eq!(deep.depth(), MAX_DEPTH);
+        assert_eq!(TapTree::huffman([(1, deep.clone())]).unwrap(), deep);
+        assert!(TapTree::huffman([(1, deep), (1, leaf)]).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(TapTree::leaf_with_version(0xc1, vec![]).is_err());
+        assert!(TapTree::leaf_with_version(0x50, vec![]).is_err());
+        let control_block = TapTree::leaf(vec![0x51])
+            .control_block(&internal_key(3), TAPSCRIPT_LEAF_VERSION, &[0x51])
+            .unwrap()
+            .serialize();
+        assert!(ControlBlock::try_from(&control_block[..32]).is_err());
+        assert!(
+            ControlBlock::try_from([&control_block[..], &[0; 31]].concat().as_slice()).is_err()
+        );
+        assert!(
+            ControlBlock::try_from([&control_block[..], &[0; 32 * 129]].concat().as_slice())
+                .is_err()
+        );
+        assert!(ControlBlock::try_from([&control_block[..], &[0; 32]].concat().as_slice()).is_ok());
+    }
+}
@@ -0,0 +1,406 @@
+//! Minimal Bitcoin transactions: consensus serialization, txid and the signature hashes
+//! of legacy, segwit v0 (BIP143) and taproot key path (BIP341) inputs.
+
+use super::schnorr::tagged_hash;
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+/// BIP341 hash type committing to all inputs and outputs, with a 64-byte signature
+pub const SIGHASH_DEFAULT: u8 = 0x00;
+pub const SIGHASH_ALL: u8 = 0x01;
+
+/// returns SHA256(SHA256(data))
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// returns the Bitcoin CompactSize encoding of the length
+pub fn compact_size(len: usize) -> Vec<u8> {
+    match len {
+        0..=0xfc => vec![len as u8],
+        0xfd..=0xffff => [&[0xfd], &(len as u16).to_le_bytes()[..]].concat(),
+        0x10000..=0xffff_ffff => [&[0xfe], &(len as u32).to_le_bytes()[..]].concat(),
+        _ => [&[0xff], &(len as u64).to_le_bytes()[..]].concat(),
+    }
+}
+
+fn push_bytes(res: &mut Vec<u8>, data: &[u8]) {
+    res.extend(compact_size(data.len()));
+    res.extend_from_slice(data);
+}
+
+/// cursor over consensus-encoded data
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.data.len() {
+            return Err(anyhow!("Unexpected end of data"));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut res = [0; N];
+        res.copy_from_slice(self.take(N)?);
+        Ok(res)
+    }
+
+    fn compact_size(&mut self) -> Result<usize> {
+        let len = match self.array::<1>()?[0] {
+            0xfd => u16::from_le_bytes(self.array()?) as u64,
+            0xfe => u32::from_le_bytes(self.array()?) as u64,
+            0xff => u64::from_le_bytes(self.array()?),
+            len => len as u64,
+        };
+        usize::try_from(len).map_err(|_| anyhow!("Length does not fit in memory"))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.compact_size()?;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn witness(&mut self) -> Result<Vec<Vec<u8>>> {
+        (0..self.compact_size()?).map(|_| self.bytes()).collect()
+    }
+}
+
+/// returns the consensus encoding of a witness stack
+pub fn serialize_witness(witness: &[Vec<u8>]) -> Vec<u8> {
+    let mut res = compact_size(witness.len());
+    witness.iter().for_each(|item| push_bytes(&mut res, item));
+    res
+}
+
+/// parses the consensus encoding of a witness stack
+pub fn parse_witness(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut reader = Reader { data };
+    let witness = reader.witness()?;
+    if !reader.data.is_empty() {
+        return Err(anyhow!("Trailing bytes after witness"));
+    }
+    Ok(witness)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutPoint {
+    /// txid in internal byte order, the reverse of how it is usually displayed
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxIn {
+    pub previous_output: OutPoint,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxOut {
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+impl TxOut {
+    fn serialize(&self) -> Vec<u8> {
+        let mut res = self.value.to_le_bytes().to_vec();
+        push_bytes(&mut res, &self.script_pubkey);
+        res
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transaction {
+    pub version: i32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    pub lock_time: u32,
+}
+
+impl Transaction {
+    /// returns the serialization with witnesses, if any input has one
+    pub fn serialize(&self) -> Vec<u8> {
+        self.encode(self.inputs.iter().any(|input| !input.witness.is_empty()))
+    }
+
+    fn encode(&self, with_witness: bool) -> Vec<u8> {
+        let mut res = self.version.to_le_bytes().to_vec();
+        if with_witness {
+            res.extend([0x00, 0x01]);
+        }
+        res.extend(compact_size(self.inputs.len()));
+        for input in &self.inputs {
+            res.extend(input.previous_output.txid);
+            res.extend(input.previous_output.vout.to_le_bytes());
+            push_bytes(&mut res, &input.script_sig);
+            res.extend(input.sequence.to_le_bytes());
+        }
+        res.extend(compact_size(self.outputs.len()));
+        self.outputs
+            .iter()
+            .for_each(|output| res.extend(output.serialize()));
+        if with_witness {
+            self.inputs
+                .iter()
+                .for_each(|input| res.extend(serialize_witness(&input.witness)));
+        }
+        res.extend(self.lock_time.to_le_bytes());
+        res
+    }
+
+    /// parses a transaction in either the legacy or the segwit serialization
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader { data };
+        let version = i32::from_le_bytes(reader.array()?);
+        let segwit = reader.data.starts_with(&[0x00, 0x01]);
+        if segwit {
+            reader.take(2)?;
+        }
+        let mut inputs = (0..reader.compact_size()?)
+            .map(|_| {
+                Ok(TxIn {
+                    previous_output: OutPoint {
+                        txid: reader.array()?,
+                        vout: u32::from_le_bytes(reader.array()?),
+                    },
+                    script_sig: reader.bytes()?,
+                    sequence: u32::from_le_bytes(reader.array()?),
+                    witness: vec![],
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let outputs = (0..reader.compact_size()?)
+            .map(|_| {
+                Ok(TxOut {
+                    value: u64::from_le_bytes(reader.array()?),
+                    script_pubkey: reader.bytes()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if segwit {
+            for input in inputs.iter_mut() {
+                input.witness = reader.witness()?;
+            }
+        }
+        let lock_time = u32::from_le_bytes(reader.array()?);
+        if !reader.data.is_empty() {
+            return Err(anyhow!("Trailing bytes after transaction"));
+        }
+        Ok(Self {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        })
+    }
+
+    /// returns the txid in internal byte order
+    pub fn txid(&self) -> [u8; 32] {
+        sha256d(&self.encode(false))
+    }
+
+    fn check_input(&self, index: usize, hash_type: u8) -> Result<()> {
+        if index >= self.inputs.len() {
+            return Err(anyhow!("Input {index} does not exist"));
+        }
+        if hash_type != SIGHASH_ALL {
+            return Err(anyhow!("Unsupported hash type {hash_type:#04x}"));
+        }
+        Ok(())
+    }
+
+    /// returns the pre-segwit signature hash of an input spending an output with script_code
+    pub fn legacy_sighash(
+        &self,
+        index: usize,
+        script_code: &[u8],
+        hash_type: u8,
+    ) -> Result<[u8; 32]> {
+        self.check_input(index, hash_type)?;
+        let mut tx = self.clone();
+        for (i, input) in tx.inputs.iter_mut().enumerate() {
+            input.script_sig = if i == index {
+                script_code.to_vec()
+            } else {
+                vec![]
+            };
+            input.witness.clear();
+        }
+        let mut data = tx.encode(false);
+        data.extend(u32::from(hash_type).to_le_bytes());
+        Ok(sha256d(&data))
+    }
+
+    /// returns the BIP143 signature hash of a segwit v0 input spending `value` satoshis
+    pub fn segwit_v0_sighash(
+        &self,
+        index: usize,
+        script_code: &[u8],
+        value: u64,
+        hash_type: u8,
+    ) -> Result<[u8; 32]> {
+        self.check_input(index, hash_type)?;
+        let prevouts: Vec<u8> = self
+            .inputs
+            .iter()
+            .flat_map(|input| {
+                [
+                    &input.previous_output.txid[..],
+                    &input.previous_output.vout.to_le_bytes(),
+                ]
+                .concat()
+            })
+            .collect();
+        let sequences: Vec<u8> = self
+            .inputs
+            .iter()
+            .flat_map(|input| input.sequence.to_le_bytes())
+            .collect();
+        let outputs: Vec<u8> = self.outputs.iter().flat_map(TxOut::serialize).collect();
+        let input = &self.inputs[index];
+
+        let mut data = self.version.to_le_bytes().to_vec();
+        data.extend(sha256d(&prevouts));
+        data.extend(sha256d(&sequences));
+        data.extend(input.previous_output.txid);
+        data.extend(input.previous_output.vout.to_le_bytes());
+        push_bytes(&mut data, script_code);
+        data.extend(value.to_le_bytes());
+        data.extend(input.sequence.to_le_bytes());
+        data.extend(sha256d(&outputs));
+        data.extend(self.lock_time.to_le_bytes());
+        data.extend(u32::from(hash_type).to_le_bytes());
+        Ok(sha256d(&data))
+    }
+
+    /// returns the BIP341 signature hash of a taproot key path spend, `prevouts` are
+    /// the outputs spent by every input of the transaction
+    pub fn taproot_key_path_sighash(
+        &self,
+        index: usize,
+        prevouts: &[TxOut],
+        hash_type: u8,
+    ) -> Result<[u8; 32]> {
+        self.check_input(
+            index,
+            if hash_type == SIGHASH_DEFAULT {
+                SIGHASH_ALL
+            } else {
+                hash_type
+            },
+        )?;
+        if prevouts.len() != self.inputs.len() {
+            return Err(anyhow!("Every input needs the output it spends"));
+        }
+        let sha256 = |data: Vec<u8>| -> [u8; 32] { Sha256::digest(data).into() };
+        let mut data = vec![0x00, hash_type];
+        data.extend(self.version.to_le_bytes());
+        data.extend(self.lock_time.to_le_bytes());
+        data.extend(sha256(
+            self.inputs
+                .iter()
+                .flat_map(|input| {
+                    [
+                        &input.previous_output.txid[..],
+                        &input.previous_output.vout.to_le_bytes(),
+                    ]
+                    .concat()
+                })
+                .collect(),
+        ));
+        data.extend(sha256(
+            prevouts
+                .iter()
+                .flat_map(|prevout| prevout.value.to_le_bytes())
+                .collect(),
+        ));
+        data.extend(sha256(
+            prevouts
+                .iter()
+                .flat_map(|prevout| {
+                    let mut script = vec![];
+                    push_bytes(&mut script, &prevout.script_pubkey);
+                    script
+                })
+                .collect(),
+        ));
+        data.extend(sha256(
+            self.inputs
+                .iter()
+                .flat_map(|input| input.sequence.to_le_bytes())
+                .collect(),
+        ));
+        data.extend(sha256(
+            self.outputs.iter().flat_map(TxOut::serialize).collect(),
+        ));
+        // key path spend without annex
+        data.push(0x00);
+        data.extend((index as u32).to_le_bytes());
+        Ok(tagged_hash("TapSighash", &data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_compact_size() {
+        assert_eq!(compact_size(0xfc), vec![0xfc]);
+        assert_eq!(compact_size(0xfd), vec![0xfd, 0xfd, 0x00]);
+        assert_eq!(compact_size(0x10000), vec![0xfe, 0x00, 0x00, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_segwit_v0_sighash() {
+        // native P2WPKH example from BIP143
+        let raw = hex!("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000");
+        let tx = Transaction::parse(&raw).unwrap();
+        assert_eq!(tx.serialize(), raw);
+        let script_code = hex!("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac");
+        assert_eq!(
+            tx.segwit_v0_sighash(1, &script_code, 600_000_000, SIGHASH_ALL)
+                .unwrap(),
+            hex!("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670")
+        );
+        assert!(tx
+            .segwit_v0_sighash(2, &script_code, 0, SIGHASH_ALL)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_segwit() {
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                sequence: 0xffff_fffd,
+                witness: vec![vec![1, 2, 3], vec![]],
+                ..Default::default()
+            }],
+            outputs: vec![TxOut {
+                value: 1000,
+                script_pubkey: vec![0x6a],
+            }],
+            lock_time: 800_000,
+        };
+        assert_eq!(Transaction::parse(&tx.serialize()).unwrap(), tx);
+        let txid = tx.txid();
+        tx.inputs[0].witness.clear();
+        assert_eq!(Transaction::parse(&tx.serialize()).unwrap(), tx);
+        // the txid does not commit to witnesses
+        assert_eq!(tx.txid(), txid);
+        assert!(Transaction::parse(&tx.serialize()[1..]).is_err());
+        assert_eq!(
+            parse_witness(&serialize_witness(&[vec![0xab; 300]])).unwrap(),
+            vec![vec![0xab; 300]]
+        );
+    }
+}
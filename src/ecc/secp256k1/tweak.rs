@@ -0,0 +1,153 @@
+//! Tweaking secp256k1 keys by a scalar, the building block of BIP32 derivation, taproot
+//! and pay-to-contract. Private and public tweaks agree: tweaking the secret and then
+//! taking its point gives the tweaked point.
+
+use super::{Point, PrivateKey, G, N};
+use anyhow::{anyhow, Result};
+use num_bigint::BigInt;
+use num_integer::Integer;
+
+/// returns an error unless `lower <= tweak < n`
+fn check_range(tweak: &BigInt, lower: u8) -> Result<()> {
+    if tweak < &BigInt::from(lower) || tweak >= &*N {
+        return Err(anyhow!("Tweak is not in [{lower}, n)"));
+    }
+    Ok(())
+}
+
+impl PrivateKey {
+    fn check_secret(&self) -> Result<()> {
+        check_range(&self.secret, 1).map_err(|_| anyhow!("Secret is not in [1, n)"))
+    }
+
+    /// returns the key with secret `secret + tweak`, the tweak must be in [0, n)
+    pub fn tweak_add(&self, tweak: &BigInt) -> Result<PrivateKey> {
+        self.check_secret()?;
+        check_range(tweak, 0)?;
+        let secret = (&self.secret + tweak).mod_floor(&N);
+        if secret == BigInt::from(0) {
+            return Err(anyhow!("Tweaked secret is zero"));
+        }
+        Ok(PrivateKey::new(secret))
+    }
+
+    /// returns the key with secret `secret * tweak`, the tweak must be in [1, n)
+    pub fn tweak_mul(&self, tweak: &BigInt) -> Result<PrivateKey> {
+        self.check_secret()?;
+        check_range(tweak, 1)?;
+        Ok(PrivateKey::new((&self.secret * tweak).mod_floor(&N)))
+    }
+
+    /// returns the key with secret `n - secret`, whose point is the negated point
+    pub fn negate(&self) -> Result<PrivateKey> {
+        self.check_secret()?;
+        Ok(PrivateKey {
+            secret: &*N - &self.secret,
+            point: self.point.negate(),
+        })
+    }
+}
+
+impl Point {
+    fn check_finite(&self) -> Result<()> {
+        if self.coordinate().is_none() {
+            return Err(anyhow!("Cannot tweak the point at infinity"));
+        }
+        Ok(())
+    }
+
+    /// returns `self + tweak⋅G`, the tweak must be in [0, n)
+    pub fn tweak_add(&self, tweak: &BigInt) -> Result<Point> {
+        self.check_finite()?;
+        check_range(tweak, 0)?;
+        let point = (&*G * tweak + self)?;
+        if point.coordinate().is_none() {
+            return Err(anyhow!("Tweaked point is the point at infinity"));
+        }
+        Ok(point)
+    }
+
+    /// returns `tweak⋅self`, the tweak must be in [1, n)
+    pub fn tweak_mul(&self, tweak: &BigInt) -> Result<Point> {
+        self.check_finite()?;
+        check_range(tweak, 1)?;
+        Ok(self * tweak)
+    }
+
+    /// returns the point with the same x and negated y
+    pub fn negate(&self) -> Point {
+        Point(-&self.0)
+    }
+
+    /// returns the sum of the public keys, failing on an empty list or a sum at infinity
+    pub fn combine<'a>(keys: impl IntoIterator<Item = &'a Point>) -> Result<Point> {
+        let mut keys = keys.into_iter();
+        let first = keys
+            .next()
+            .ok_or_else(|| anyhow!("No public keys to combine"))?;
+        let sum = keys.try_fold(first.clone(), |sum, key| sum + key)?;
+        if sum.coordinate().is_none() {
+            return Err(anyhow!("Combined key is the point at infinity"));
+        }
+        Ok(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_tweaks_agree() {
+        let key = PrivateKey::new(thread_rng().gen_bigint_range(&BigInt::from(1), &N));
+        let tweak = thread_rng().gen_bigint_range(&BigInt::from(1), &N);
+
+        let added = key.tweak_add(&tweak).unwrap();
+        assert_eq!(key.point().tweak_add(&tweak).unwrap(), added.point());
+        let multiplied = key.tweak_mul(&tweak).unwrap();
+        assert_eq!(key.point().tweak_mul(&tweak).unwrap(), multiplied.point());
+        let negated = key.negate().unwrap();
+        assert_eq!(key.point().negate(), negated.point());
+        assert_eq!(negated.point(), PrivateKey::new(&*N - &key.secret).point());
+        assert_eq!(
+            key.tweak_add(&BigInt::from(0)).unwrap().point(),
+            key.point()
+        );
+    }
+
+    #[test]
+    fn test_tweak_checks() {
+        let key = PrivateKey::new(BigInt::from(5));
+        let out_of_range = [BigInt::from(-1), N.clone(), &*N + 1];
+        for tweak in &out_of_range {
+            assert!(key.tweak_add(tweak).is_err());
+            assert!(key.tweak_mul(tweak).is_err());
+            assert!(key.point().tweak_add(tweak).is_err());
+            assert!(key.point().tweak_mul(tweak).is_err());
+        }
+        assert!(key.tweak_mul(&BigInt::from(0)).is_err());
+        assert!(key.point().tweak_mul(&BigInt::from(0)).is_err());
+        // the tweak cancels the key out
+        assert!(key.tweak_add(&(&*N - 5)).is_err());
+        assert!(key.point().tweak_add(&(&*N - 5)).is_err());
+
+        let zero = PrivateKey::new(BigInt::from(0));
+        assert!(zero.tweak_add(&BigInt::from(1)).is_err());
+        assert!(zero.negate().is_err());
+        assert!(zero.point().tweak_add(&BigInt::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_combine() {
+        let keys: Vec<PrivateKey> = (1..=3).map(|i| PrivateKey::new(BigInt::from(i))).collect();
+        assert_eq!(
+            Point::combine(keys.iter().map(PrivateKey::point)).unwrap(),
+            PrivateKey::new(BigInt::from(6)).point()
+        );
+        let key = &keys[0];
+        assert!(Point::combine([key.point(), &key.point().negate()]).is_err());
+        assert!(Point::combine([]).is_err());
+    }
+}
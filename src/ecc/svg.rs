@@ -0,0 +1,485 @@
+use crate::ecc::elliptic_curve::{Curve, Point as RealPoint};
+use crate::ecc::elliptic_curve_finite_field::{
+    Coordinate as FiniteCoordinate, CurveOverFiniteField, Point as FinitePoint,
+};
+use crate::ecc::finite_field::FieldElement;
+use anyhow::{anyhow, Result};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use std::fmt::{Display, Formatter, Write};
+use std::path::Path;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 640.0;
+const MARGIN: f64 = 40.0;
+const CURVE_SAMPLES: usize = 2000;
+/// Every point gets enumerated and drawn, which only makes sense for toy fields
+pub const MAX_PLOTTED_PRIME: u64 = 1 << 12;
+
+/// Rendered SVG document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Svg(String);
+
+impl Svg {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, &self.0).map_err(Into::into)
+    }
+}
+
+impl Display for Svg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Region of the real plane drawn by [`render_curve`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            x_min: -5.0,
+            x_max: 5.0,
+            y_min: -10.0,
+            y_max: 10.0,
+        }
+    }
+}
+
+/// Constructions drawn on top of a curve: the chord/tangent construction of `P + Q`
+/// and the orbit `P, 2P, ..., kP`.
+#[derive(Debug, Clone)]
+pub struct Overlay<P> {
+    pub addition: Option<(P, P)>,
+    pub orbit: Option<(P, usize)>,
+}
+
+impl<P> Default for Overlay<P> {
+    fn default() -> Self {
+        Self {
+            addition: None,
+            orbit: None,
+        }
+    }
+}
+
+/// Maps plot coordinates to the SVG canvas and accumulates the document body
+struct Canvas {
+    viewport: Viewport,
+    body: String,
+}
+
+impl Canvas {
+    fn new(viewport: Viewport) -> Result<Self> {
+        if viewport.x_min >= viewport.x_max || viewport.y_min >= viewport.y_max {
+            return Err(anyhow!("Empty viewport"));
+        }
+        Ok(Self {
+            viewport,
+            body: String::new(),
+        })
+    }
+
+    fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        let Viewport {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+        } = self.viewport;
+        (
+            MARGIN + (x - x_min) / (x_max - x_min) * (WIDTH - 2.0 * MARGIN),
+            HEIGHT - MARGIN - (y - y_min) / (y_max - y_min) * (HEIGHT - 2.0 * MARGIN),
+        )
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), class: &str) {
+        let ((x1, y1), (x2, y2)) = (self.map(from.0, from.1), self.map(to.0, to.1));
+        let _ = writeln!(
+            self.body,
+            r#"<line class="{class}" x1="{x1:.2}" y1="{y1:.2}" x2="{x2:.2}" y2="{y2:.2}"/>"#
+        );
+    }
+
+    fn polyline(&mut self, points: &[(f64, f64)], class: &str) {
+        if points.len() < 2 {
+            return;
+        }
+        let path = points
+            .iter()
+            .map(|(x, y)| {
+                let (x, y) = self.map(*x, *y);
+                format!("{x:.2},{y:.2}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(self.body, r#"<polyline class="{class}" points="{path}"/>"#);
+    }
+
+    fn circle(&mut self, (x, y): (f64, f64), radius: f64, class: &str) {
+        let (x, y) = self.map(x, y);
+        let _ = writeln!(
+            self.body,
+            r#"<circle class="{class}" cx="{x:.2}" cy="{y:.2}" r="{radius}"/>"#
+        );
+    }
+
+    fn label(&mut self, (x, y): (f64, f64), text: &str) {
+        let (x, y) = self.map(x, y);
+        let _ = writeln!(
+            self.body,
+            r#"<text class="label" x="{:.2}" y="{:.2}">{text}</text>"#,
+            x + 6.0,
+            y - 6.0
+        );
+    }
+
+    fn finish(self, title: &str) -> Svg {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}">"#
+        );
+        let _ = writeln!(svg, "<title>{title}</title>");
+        svg.push_str(
+            "<style>\
+             .axis{stroke:#999;stroke-width:1}\
+             .curve{fill:none;stroke:#1f77b4;stroke-width:2}\
+             .point{fill:#1f77b4}\
+             .chord{stroke:#d62728;stroke-width:1.5}\
+             .chord-point{fill:#d62728;opacity:0.5}\
+             .reflection{stroke:#d62728;stroke-width:1;stroke-dasharray:4 4}\
+             .operand{fill:#2ca02c}\
+             .result{fill:#ff7f0e}\
+             .orbit{fill:none;stroke:#9467bd;stroke-width:1;stroke-dasharray:2 2}\
+             .orbit-point{fill:#9467bd}\
+             .label{font-family:monospace;font-size:12px}\
+             </style>\n",
+        );
+        svg.push_str(&self.body);
+        svg.push_str("</svg>\n");
+        Svg(svg)
+    }
+}
+
+fn to_f64(value: &BigRational) -> Result<f64> {
+    value
+        .to_f64()
+        .ok_or_else(|| anyhow!("Coordinate {value} does not fit into f64"))
+}
+
+fn real_coordinates(point: &RealPoint) -> Result<Option<(f64, f64)>> {
+    point
+        .coordinate()
+        .map(|c| Ok((to_f64(c.x())?, to_f64(c.y())?)))
+        .transpose()
+}
+
+/// Plots the real curve y^2 = x^3 + ax + b inside the viewport
+pub fn render_curve(
+    curve: &Curve,
+    viewport: Viewport,
+    overlay: &Overlay<RealPoint>,
+) -> Result<Svg> {
+    let mut canvas = Canvas::new(viewport)?;
+    let (a, b) = (to_f64(curve.a())?, to_f64(curve.b())?);
+    canvas.line((viewport.x_min, 0.0), (viewport.x_max, 0.0), "axis");
+    canvas.line((0.0, viewport.y_min), (0.0, viewport.y_max), "axis");
+
+    // both branches y = ±sqrt(f(x)) of every interval where f(x) >= 0, closed at the roots
+    let f = |x: f64| x * x * x + a * x + b;
+    let root = |mut lo: f64, mut hi: f64| {
+        for _ in 0..64 {
+            let mid = (lo + hi) / 2.0;
+            if (f(mid) >= 0.0) == (f(hi) >= 0.0) {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        hi
+    };
+    let step = (viewport.x_max - viewport.x_min) / CURVE_SAMPLES as f64;
+    // x samples of each interval, and whether it starts/ends at a root rather than the border
+    let mut segments: Vec<(Vec<f64>, bool, bool)> = vec![];
+    let mut segment: Vec<f64> = vec![];
+    let mut starts_at_root = false;
+    for i in 0..=CURVE_SAMPLES {
+        let x = viewport.x_min + step * i as f64;
+        match (f(x) >= 0.0, segment.is_empty()) {
+            (true, true) if i > 0 => {
+                starts_at_root = true;
+                segment.extend([root(x - step, x), x]);
+            }
+            (true, _) => segment.push(x),
+            (false, false) => {
+                segment.push(root(x - step, x));
+                segments.push((std::mem::take(&mut segment), starts_at_root, true));
+            }
+            (false, true) => {}
+        }
+    }
+    if !segment.is_empty() {
+        segments.push((segment, starts_at_root, false));
+    }
+    for (segment, starts_at_root, ends_at_root) in segments {
+        let upper: Vec<_> = segment
+            .iter()
+            .map(|x| (*x, f(*x).max(0.0).sqrt()))
+            .collect();
+        let lower: Vec<_> = upper.iter().rev().map(|(x, y)| (*x, -y)).collect();
+        // walk through the root the branches meet at, so no edge crosses the border
+        if ends_at_root {
+            canvas.polyline(&[upper, lower].concat(), "curve");
+        } else if starts_at_root {
+            canvas.polyline(&[lower, upper].concat(), "curve");
+        } else {
+            canvas.polyline(&upper, "curve");
+            canvas.polyline(&lower, "curve");
+        }
+    }
+
+    if let Some((p, q)) = &overlay.addition {
+        if p.curve() != curve || q.curve() != curve {
+            return Err(anyhow!("Overlay points are not on the plotted curve"));
+        }
+        let (Some(p_xy), Some(q_xy)) = (real_coordinates(p)?, real_coordinates(q)?) else {
+            return Err(anyhow!("Cannot draw the addition of the point at infinity"));
+        };
+        let sum = p + q;
+        match real_coordinates(&sum)? {
+            None => canvas.line((p_xy.0, viewport.y_min), (p_xy.0, viewport.y_max), "chord"),
+            Some(sum_xy) => {
+                let (p_c, q_c) = (p.coordinate().unwrap(), q.coordinate().unwrap());
+                let slope = if p_c == q_c {
+                    let three = BigRational::from_integer(BigInt::from(3));
+                    let two = BigRational::from_integer(BigInt::from(2));
+                    (three * p_c.x() * p_c.x() + curve.a()) / (two * p_c.y())
+                } else {
+                    (q_c.y() - p_c.y()) / (q_c.x() - p_c.x())
+                };
+                let slope = to_f64(&slope)?;
+                let at = |x: f64| (x, p_xy.1 + slope * (x - p_xy.0));
+                canvas.line(at(viewport.x_min), at(viewport.x_max), "chord");
+                canvas.line((sum_xy.0, -sum_xy.1), sum_xy, "reflection");
+                canvas.circle((sum_xy.0, -sum_xy.1), 4.0, "chord-point");
+                canvas.circle(sum_xy, 5.0, "result");
+                canvas.label(sum_xy, "P+Q");
+            }
+        }
+        canvas.circle(p_xy, 5.0, "operand");
+        canvas.label(p_xy, "P");
+        canvas.circle(q_xy, 5.0, "operand");
+        canvas.label(q_xy, "Q");
+    }
+
+    if let Some((p, k)) = &overlay.orbit {
+        if p.curve() != curve {
+            return Err(anyhow!("Overlay points are not on the plotted curve"));
+        }
+        let mut multiples = vec![];
+        let mut current = p.clone();
+        for _ in 0..*k {
+            multiples.push(real_coordinates(&current)?);
+            current = &current + p;
+        }
+        draw_orbit(&mut canvas, &multiples);
+    }
+
+    Ok(canvas.finish(&format!("y^2 = x^3 + {}x + {}", curve.a(), curve.b())))
+}
+
+/// Scatters every point of a curve over a small prime field
+pub fn render_finite_curve(
+    curve: &CurveOverFiniteField,
+    overlay: &Overlay<FinitePoint>,
+) -> Result<Svg> {
+    let prime = curve.a().prime.clone();
+    let p = prime
+        .to_u64()
+        .filter(|p| *p <= MAX_PLOTTED_PRIME)
+        .ok_or_else(|| anyhow!("Field F_{prime} is too large to plot"))?;
+    let size = p as f64;
+    let mut canvas = Canvas::new(Viewport {
+        x_min: -0.5,
+        x_max: size - 0.5,
+        y_min: -0.5,
+        y_max: size - 0.5,
+    })?;
+    canvas.line((-0.5, -0.5), (size - 0.5, -0.5), "axis");
+    canvas.line((-0.5, -0.5), (-0.5, size - 0.5), "axis");
+
+    let radius = (WIDTH / size / 3.0).clamp(0.8, 4.0);
+    let finite_coordinates = |point: &FinitePoint| -> Result<Option<(f64, f64)>> {
+        if point.curve() != curve {
+            return Err(anyhow!("Overlay points are not on the plotted curve"));
+        }
+        Ok(point.coordinate().map(|FiniteCoordinate { x, y }| {
+            (
+                x.num.to_f64().unwrap_or_default(),
+                y.num.to_f64().unwrap_or_default(),
+            )
+        }))
+    };
+
+    for point in curve.points()? {
+        if let Some(xy) = finite_coordinates(&point)? {
+            canvas.circle(xy, radius, "point");
+        }
+    }
+
+    if let Some((p_point, q_point)) = &overlay.addition {
+        let (Some(p_xy), Some(q_xy)) = (finite_coordinates(p_point)?, finite_coordinates(q_point)?)
+        else {
+            return Err(anyhow!("Cannot draw the addition of the point at infinity"));
+        };
+        let sum = (p_point + q_point)?;
+        match finite_coordinates(&sum)? {
+            None => canvas.line((p_xy.0, -0.5), (p_xy.0, size - 0.5), "chord"),
+            Some(sum_xy) => {
+                // the line y = s(x - x1) + y1 wraps around modulo p, so draw its points
+                let (p_c, q_c) = (p_point.coordinate().unwrap(), q_point.coordinate().unwrap());
+                let slope = if p_c == q_c {
+                    let three = FieldElement::new(3, prime.clone());
+                    let two = FieldElement::new(2, prime.clone());
+                    ((((three * &p_c.x)? * &p_c.x)? + curve.a())? / (two * &p_c.y)?)?
+                } else {
+                    ((&q_c.y - &p_c.y)? / (&q_c.x - &p_c.x)?)?
+                };
+                for x in 0..p {
+                    let x = FieldElement::new(x, prime.clone());
+                    let y = ((&slope * (&x - &p_c.x)?)? + &p_c.y)?;
+                    let xy = (
+                        x.num.to_f64().unwrap_or_default(),
+                        y.num.to_f64().unwrap_or_default(),
+                    );
+                    canvas.circle(xy, radius / 2.0, "chord-point");
+                }
+                let reflected_y = -&sum.coordinate().unwrap().y;
+                let reflected = (sum_xy.0, reflected_y.num.to_f64().unwrap_or_default());
+                canvas.line(reflected, sum_xy, "reflection");
+                canvas.circle(sum_xy, radius * 1.5, "result");
+                canvas.label(sum_xy, "P+Q");
+            }
+        }
+        canvas.circle(p_xy, radius * 1.5, "operand");
+        canvas.label(p_xy, "P");
+        canvas.circle(q_xy, radius * 1.5, "operand");
+        canvas.label(q_xy, "Q");
+    }
+
+    if let Some((point, k)) = &overlay.orbit {
+        let mut multiples = vec![];
+        let mut current = point.clone();
+        for _ in 0..*k {
+            multiples.push(finite_coordinates(&current)?);
+            current = (&current + point)?;
+        }
+        draw_orbit(&mut canvas, &multiples);
+    }
+
+    Ok(canvas.finish(&format!(
+        "y^2 = x^3 + {}x + {} over F_{prime}",
+        curve.a().num,
+        curve.b().num
+    )))
+}
+
+/// connects consecutive multiples, the point at infinity breaks the path
+fn draw_orbit(canvas: &mut Canvas, multiples: &[Option<(f64, f64)>]) {
+    for pair in multiples.windows(2) {
+        if let [Some(from), Some(to)] = pair {
+            canvas.line(*from, *to, "orbit");
+        }
+    }
+    for (i, xy) in multiples.iter().enumerate() {
+        if let Some(xy) = xy {
+            canvas.circle(*xy, 4.0, "orbit-point");
+            canvas.label(*xy, &format!("{}P", i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::elliptic_curve::Coordinate as RealCoordinate;
+
+    fn finite_point(curve: &CurveOverFiniteField, x: i64, y: i64) -> FinitePoint {
+        FinitePoint::new(
+            Some(FiniteCoordinate::new(
+                FieldElement::new(x, 223),
+                FieldElement::new(y, 223),
+            )),
+            curve.clone(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_curve() {
+        let curve = Curve::default();
+        let p = RealPoint::new(Some(RealCoordinate::from((-1, -1))), curve.clone()).unwrap();
+        let q = RealPoint::new(Some(RealCoordinate::from((2, 5))), curve.clone()).unwrap();
+        let overlay = Overlay {
+            addition: Some((p.clone(), q)),
+            orbit: Some((p, 3)),
+        };
+        let svg = render_curve(&curve, Viewport::default(), &overlay).unwrap();
+        assert!(svg.as_str().starts_with("<svg"));
+        assert!(svg.as_str().trim_end().ends_with("</svg>"));
+        assert!(svg.as_str().contains(r#"class="curve""#));
+        assert!(svg.as_str().contains(">P+Q</text>"));
+        assert!(svg.as_str().contains(">3P</text>"));
+    }
+
+    #[test]
+    fn test_render_curve_two_components() {
+        // x^3 - 4x = x(x - 2)(x + 2) has an oval and an unbounded branch
+        let svg =
+            render_curve(&Curve::new(-4, 0), Viewport::default(), &Overlay::default()).unwrap();
+        assert_eq!(svg.as_str().matches(r#"class="curve""#).count(), 2);
+    }
+
+    #[test]
+    fn test_render_finite_curve() {
+        let curve = CurveOverFiniteField::new((0, 223), (7, 223));
+        let svg = render_finite_curve(&curve, &Overlay::default()).unwrap();
+        // the group has 252 elements including the point at infinity
+        assert_eq!(svg.as_str().matches(r#"class="point""#).count(), 251);
+
+        let overlay = Overlay {
+            addition: Some((finite_point(&curve, 192, 105), finite_point(&curve, 17, 56))),
+            orbit: Some((finite_point(&curve, 15, 86), 7)),
+        };
+        let svg = render_finite_curve(&curve, &overlay).unwrap();
+        assert_eq!(svg.as_str().matches(r#"class="chord-point""#).count(), 223);
+        assert!(svg.as_str().contains(">P+Q</text>"));
+        // (15, 86) has order 7, so 7P is the point at infinity and is not drawn
+        assert!(svg.as_str().contains(">6P</text>"));
+        assert!(!svg.as_str().contains(">7P</text>"));
+
+        let path = std::env::temp_dir().join("programming_bitcoin_f223.svg");
+        svg.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), svg.as_str());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_render_finite_curve_rejects_large_fields() {
+        let prime: BigInt = BigInt::from(2).pow(127) - 1;
+        let curve = CurveOverFiniteField::new((0, prime.clone()), (7, prime));
+        assert!(render_finite_curve(&curve, &Overlay::default()).is_err());
+    }
+}